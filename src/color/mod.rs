@@ -0,0 +1,101 @@
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+static CHOICE: OnceLock<ColorChoice> = OnceLock::new();
+
+/// `--color <auto|always|never>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color terminals unless `$NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    fn enabled(self, is_terminal: bool, no_color: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => is_terminal && !no_color,
+        }
+    }
+}
+
+/// Sets the choice from `--color`. Without it, `Auto` is used.
+pub fn set_choice(choice: ColorChoice) {
+    let _ = CHOICE.set(choice);
+}
+
+/// Output stream whose terminal decides `Auto`.
+#[derive(Debug, Clone, Copy)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Style {
+    Bold,
+    Red,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "1",
+            Style::Red => "31",
+        }
+    }
+}
+
+/// `text` wrapped in the ANSI codes of `style` when color is enabled for `stream`.
+pub fn paint(text: &str, style: Style, stream: Stream) -> String {
+    let is_terminal = match stream {
+        Stream::Stdout => std::io::stdout().is_terminal(),
+        Stream::Stderr => std::io::stderr().is_terminal(),
+    };
+    // https://no-color.org: set and non-empty disables color
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let choice = CHOICE.get().copied().unwrap_or_default();
+    if choice.enabled(is_terminal, no_color) {
+        wrap(text, style)
+    } else {
+        text.to_string()
+    }
+}
+
+fn wrap(text: &str, style: Style) -> String {
+    format!("\x1b[{}m{}\x1b[0m", style.code(), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled() {
+        assert!(ColorChoice::Auto.enabled(true, false));
+        assert!(!ColorChoice::Auto.enabled(false, false));
+        assert!(!ColorChoice::Auto.enabled(true, true));
+        assert!(ColorChoice::Always.enabled(false, true));
+        assert!(!ColorChoice::Never.enabled(true, false));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("Error:", Style::Red), "\x1b[31mError:\x1b[0m");
+        assert_eq!(ColorChoice::parse("always"), Some(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse("yes"), None);
+    }
+}
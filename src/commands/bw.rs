@@ -1,8 +1,16 @@
+use super::doctor::confirm;
+use super::lock::RepoLock;
+use super::summary::Summary;
+use crate::color::{paint, Stream, Style};
 use crate::error::GhbareError;
-use serde::Deserialize;
+use crate::git::git_cmd;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 #[derive(Debug, Deserialize)]
 pub struct BwConfig {
@@ -11,8 +19,70 @@ pub struct BwConfig {
 
     #[serde(default)]
     pub post_add_commands: String,
+
+    /// git config entries applied to every new worktree (e.g., user.email)
+    #[serde(default)]
+    pub worktree_git_config: BTreeMap<String, String>,
+
+    /// Glob patterns of git-ignored files copied from the base worktree, matched
+    /// against the repo-relative path (e.g., ".env", "**/.env" for any directory)
+    #[serde(default)]
+    pub copy_from_worktree: Vec<String>,
+
+    /// Base branch per branch-name prefix; the first matching rule wins
+    #[serde(default)]
+    pub base_rules: Vec<BaseRule>,
+
+    /// Cone-mode sparse-checkout directories used when `--sparse` is not given
+    #[serde(default)]
+    pub default_sparse_patterns: Vec<String>,
+
+    /// What `bw add` does when the branch already exists (default: fail)
+    #[serde(default)]
+    pub on_existing_branch: OnExistingBranch,
+
+    /// Delete the branch along with its worktree in `bw rm` (default: keep)
+    #[serde(default)]
+    pub remove_deletes_branch: bool,
+
+    /// Make `bw add` fail on directory name collisions between branches,
+    /// as with `--no-auto-dir` (default: false)
+    #[serde(default)]
+    pub strict_dirnames: bool,
+
+    /// `bw add` refuses to go beyond this many worktrees unless `--force` (default: unlimited)
+    #[serde(default)]
+    pub max_worktrees: Option<usize>,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnExistingBranch {
+    /// Check the existing branch out in the new worktree
+    Reuse,
+    #[default]
+    Fail,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BaseRule {
+    pub prefix: String,
+    pub base: String,
+}
+
+impl BwConfig {
+    /// Base for `branch`: first matching `base_rules` entry, else `base_branch`.
+    fn base_for(&self, branch: &str) -> &str {
+        self.base_rules
+            .iter()
+            .find(|rule| branch.starts_with(&rule.prefix))
+            .map(|rule| rule.base.as_str())
+            .unwrap_or(&self.base_branch)
+    }
+}
+
+const ENV_FILE_PATTERN: &str = ".env*";
+
 fn default_base_branch() -> String {
     "main".to_string()
 }
@@ -22,57 +92,402 @@ impl Default for BwConfig {
         Self {
             base_branch: default_base_branch(),
             post_add_commands: String::new(),
+            worktree_git_config: BTreeMap::new(),
+            copy_from_worktree: Vec::new(),
+            base_rules: Vec::new(),
+            default_sparse_patterns: Vec::new(),
+            on_existing_branch: OnExistingBranch::default(),
+            remove_deletes_branch: false,
+            strict_dirnames: false,
+            max_worktrees: None,
         }
     }
 }
 
-pub fn execute_add(branch: Option<&str>, base_override: Option<String>) -> anyhow::Result<()> {
+#[derive(Debug, Default)]
+pub struct AddOptions {
+    /// Base branch to create from (overrides bw.toml)
+    pub base: Option<String>,
+    /// Print the result as JSON to stdout
+    pub print_json: bool,
+    /// Print only the worktree path to stdout (for `cd "$(bw add --print-path x)"`)
+    pub print_path: bool,
+    /// Show what would be created without creating anything
+    pub dry_run: bool,
+    /// Copy ignored `.env*` files from the base worktree
+    pub copy_env: bool,
+    /// Skip post_add_commands
+    pub no_commands: bool,
+    /// Skip post_add_commands and git hooks during worktree creation
+    pub no_verify: bool,
+    /// Move uncommitted changes of the current worktree into the new one
+    pub stash: bool,
+    /// Register the worktree without checking out files
+    pub no_checkout: bool,
+    /// Sparse-checkout directories (overrides default_sparse_patterns)
+    pub sparse: Vec<String>,
+    /// Message of an empty commit created on a new branch
+    pub message: Option<String>,
+    /// Branch from the commit checked out in the current directory (same as `--base HEAD`)
+    pub from_head: bool,
+    /// Allow `from_head` while a rebase, merge or bisect is in progress,
+    /// and creating more worktrees than `max_worktrees`
+    pub force: bool,
+    /// Check out an existing branch instead of failing (overrides on_existing_branch)
+    pub reuse_branch: bool,
+    /// Fetch the base from origin when it cannot be found
+    pub fetch: bool,
+    /// Pick the base from local and remote branches with fzf
+    pub pick_base: bool,
+    /// Note shown next to the worktree in `bw list`
+    pub label: Option<String>,
+    /// Use only local refs and never contact a remote
+    pub offline: bool,
+    /// Push the branch to origin and set it as upstream after creation
+    pub push: bool,
+    /// Fail when the directory name is taken by a worktree of another branch
+    pub no_auto_dir: bool,
+    /// Worktree location instead of `<repo>/<dirname>` (relative to the current directory)
+    pub path: Option<PathBuf>,
+    /// Print only warnings and the created worktree path
+    pub quiet: bool,
+    /// Print the path of the branch's existing worktree instead of failing
+    pub switch: bool,
+    /// Create a new branch from the base even when `origin/<branch>` exists
+    pub new: bool,
+    /// Branch from the base commit of this stash and pop it into the worktree
+    pub from_stash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AddOutput {
+    branch: String,
+    dirname: String,
+    path: String,
+    base: String,
+}
+
+pub fn execute_add(branch: Option<&str>, opts: AddOptions) -> anyhow::Result<()> {
+    let quiet = opts.quiet;
+    let info = |message: std::fmt::Arguments| {
+        if !quiet {
+            eprintln!("{}", message);
+        }
+    };
     let repo_root = find_repo_root()?;
-    eprintln!("Repository root: {}", repo_root.display());
+    info(format_args!("Repository root: {}", repo_root.display()));
+    let _lock = RepoLock::acquire(&repo_root)?;
+
+    if opts.offline {
+        // partial clone の遅延 fetch はネットワークを待たずに失敗させる
+        // （post_add_commands には run_post_add_commands で渡す）
+        crate::git::disable_lazy_fetch();
+    }
 
     // Clean up stale worktree registrations if needed
-    prune_worktrees_if_needed(&repo_root);
+    prune_worktrees_if_needed(&repo_root, quiet, opts.dry_run);
 
-    let config = load_bw_config(&repo_root)?;
+    let mut config = load_bw_config(&repo_root)?;
 
-    let base_branch = base_override.unwrap_or(config.base_branch);
+    if opts.copy_env {
+        config.copy_from_worktree.push(ENV_FILE_PATTERN.to_string());
+    }
 
     // ブランチ名の決定: 指定があればそれを使用、なければ自動生成
     let branch = match branch {
         Some(b) => b.to_string(),
         None => {
             let generated = generate_wip_branch_name();
-            eprintln!("Auto-generated branch name: {}", generated);
+            info(format_args!("Auto-generated branch name: {}", generated));
             generated
         }
     };
 
-    let dirname = branch_to_dirname(&branch);
-    let worktree_path = repo_root.join(&dirname);
+    // ベースの決定: --from-stash > --from-head / --base HEAD > --base / --pick-base > base_rules > base_branch
+    let from_stash = opts.from_stash.as_deref().map(stash_ref);
+    let explicit_base =
+        opts.from_head || opts.base.is_some() || opts.pick_base || from_stash.is_some();
+    let picked_base = if opts.pick_base { pick_base(&repo_root)? } else { None };
+    let mut base_branch = if let Some(stash) = &from_stash {
+        let base = stash_base(&repo_root, stash)?;
+        info(format_args!("Using the base commit of {}: {}", stash, base));
+        base
+    } else if opts.from_head || opts.base.as_deref() == Some("HEAD") {
+        let cwd = std::env::current_dir()?;
+        if !opts.force {
+            if let Some(operation) = git_dir(&cwd).and_then(|d| in_progress_operation(&d)) {
+                return Err(GhbareError::WorktreeError(format!(
+                    "A {} is in progress in {}; HEAD may not be what you expect.\n\
+                     Finish or abort it first, or pass --force to branch from it anyway.",
+                    operation,
+                    cwd.display()
+                ))
+                .into());
+            }
+        }
+        let head = resolve_head(&cwd)?;
+        info(format_args!("Using current HEAD as base: {}", head));
+        head
+    } else {
+        opts.base
+            .clone()
+            .or(picked_base)
+            .unwrap_or_else(|| config.base_for(&branch).to_string())
+    };
+
+    let worktree_path = match &opts.path {
+        Some(path) => std::path::absolute(path)?,
+        None => repo_root.join(branch_to_dirname(&branch)),
+    };
+    // --path では最後の要素を表示用の名前にする（ラベルはパスで、衝突検出は対象外）
+    let dirname = worktree_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| branch_to_dirname(&branch));
 
+    if opts.switch {
+        if let Some(existing) = existing_worktree_for(&repo_root, &worktree_path, &branch)? {
+            // 作成時と同じく、パスを stdout に出すのは --print-path のときだけ
+            info(format_args!("Worktree already exists: {}", existing.display()));
+            if opts.print_path {
+                println!("{}", existing.display());
+            } else if quiet {
+                eprintln!("{}", existing.display());
+            }
+            return Ok(());
+        }
+    }
+    if opts.path.is_none() && (opts.no_auto_dir || config.strict_dirnames) {
+        check_dirname_collision(&list_worktrees(&repo_root)?, &dirname, &branch)?;
+    }
     if worktree_path.exists() {
         return Err(GhbareError::WorktreeAlreadyExists(worktree_path.display().to_string()).into());
     }
 
-    eprintln!(
+    if let Some(max) = config.max_worktrees {
+        check_worktree_limit(count_worktrees(&list_worktrees(&repo_root)?), max, opts.force)?;
+    }
+
+    let is_new_branch = !branch_exists(&repo_root, &branch);
+    if from_stash.is_some() && !is_new_branch {
+        return Err(GhbareError::WorktreeError(format!(
+            "Branch '{}' already exists; --from-stash needs a new branch",
+            branch
+        ))
+        .into());
+    }
+    let policy = if opts.reuse_branch {
+        OnExistingBranch::Reuse
+    } else {
+        config.on_existing_branch
+    };
+    check_existing_branch(&branch, is_new_branch, policy)?;
+
+    // git checkout <branch> と同じく、origin にだけあるブランチはそれを追跡して作成する
+    let mut track = false;
+    if is_new_branch && !opts.new && !explicit_base && remote_branch_exists(&repo_root, &branch) {
+        let remote = format!("origin/{}", branch);
+        info(format_args!(
+            "Branch '{}' exists on origin; tracking {} (pass --new to branch from {} instead)",
+            branch, remote, base_branch
+        ));
+        base_branch = remote;
+        track = true;
+    } else if is_new_branch && has_any_commits(&repo_root) {
+        // ベースがローカルになく origin にだけある場合は origin/<base> から作成して追跡する
+        let lookup = if opts.offline {
+            BaseLookup::Offline
+        } else if opts.fetch && opts.dry_run {
+            BaseLookup::WouldFetch
+        } else if opts.fetch {
+            BaseLookup::Fetch
+        } else {
+            BaseLookup::Local
+        };
+        if let ResolvedBase::Remote(remote) = resolve_base(&repo_root, &base_branch, lookup, quiet)? {
+            info(format_args!("Base '{}' exists only on origin; using {}", base_branch, remote));
+            if opts.offline {
+                info(format_args!(
+                    "Note: {} was not updated (--offline) and may be out of date",
+                    remote
+                ));
+            }
+            base_branch = remote;
+            track = true;
+        }
+    }
+
+    let output = AddOutput {
+        branch: branch.clone(),
+        dirname: dirname.clone(),
+        path: worktree_path.display().to_string(),
+        base: base_branch.clone(),
+    };
+
+    if opts.dry_run {
+        eprintln!(
+            "Would create worktree: {} (branch: {}, base: {})",
+            dirname, branch, base_branch
+        );
+        if opts.print_json {
+            println!("{}", serde_json::to_string(&output)?);
+        }
+        return Ok(());
+    }
+
+    info(format_args!(
         "Creating worktree: {} (branch: {}, base: {})",
         dirname, branch, base_branch
-    );
-    add_worktree(&repo_root, &worktree_path, &branch, &base_branch)?;
+    ));
+    let stashed = if opts.stash {
+        let cwd = std::env::current_dir()?;
+        stash_push(&cwd, &format!("bw add --stash: {}", branch), quiet)?
+    } else {
+        false
+    };
+
+    // stash を worktree に戻すまでのエラーでは、変更が stash に残っていることを伝える
+    let keep_stash = |e: GhbareError| {
+        if stashed {
+            eprintln!("Warning: your changes are still in the stash (git stash list)");
+        }
+        e
+    };
+
+    let mut sparse_patterns = if opts.sparse.is_empty() {
+        config.default_sparse_patterns.clone()
+    } else {
+        opts.sparse.clone()
+    };
+    if !sparse_patterns.is_empty() && !has_any_commits(&repo_root) {
+        // コミットがなければチェックアウトするファイルもない
+        info(format_args!("Skipping sparse-checkout: the repository has no commits yet"));
+        sparse_patterns.clear();
+    }
+
+    let flags = WorktreeAddFlags {
+        track,
+        no_verify: opts.no_verify,
+        // sparse-checkout を設定してからチェックアウトする
+        no_checkout: opts.no_checkout || !sparse_patterns.is_empty(),
+        quiet,
+    };
+    add_worktree(&repo_root, &worktree_path, &branch, &base_branch, &flags).map_err(keep_stash)?;
+
+    apply_worktree_git_config(&worktree_path, &config.worktree_git_config, quiet)
+        .map_err(keep_stash)?;
+
+    if let Some(label) = &opts.label {
+        let key = super::labels::key(&repo_root, &worktree_path);
+        super::labels::set(&repo_root, &key, Some(label)).map_err(keep_stash)?;
+    }
+
+    if !sparse_patterns.is_empty() {
+        apply_sparse_checkout(&worktree_path, &sparse_patterns, !opts.no_checkout, quiet)
+            .map_err(keep_stash)?;
+    }
 
-    if !config.post_add_commands.is_empty() {
-        run_post_add_commands(&config.post_add_commands, &worktree_path)?;
+    if let Some(message) = &opts.message {
+        if is_new_branch {
+            create_empty_commit(&worktree_path, message, quiet).map_err(keep_stash)?;
+        } else {
+            info(format_args!("Skipping --message: '{}' is an existing branch", branch));
+        }
+    }
+
+    if stashed {
+        info(format_args!("Applying stashed changes..."));
+        if !stash_pop(&worktree_path, quiet) {
+            eprintln!(
+                "Warning: stash did not apply cleanly; it was kept (git stash list)"
+            );
+        }
+    }
+    if let Some(stash) = &from_stash {
+        info(format_args!("Applying {}...", stash));
+        if !stash_pop_ref(&worktree_path, stash, quiet) {
+            // 競合はそのまま worktree に残して解決を任せる
+            eprintln!(
+                "Warning: {} did not apply cleanly; resolve the conflicts in {} \
+                 (the stash was kept, drop it with git stash drop)",
+                stash,
+                worktree_path.display()
+            );
+        }
+    }
+
+    if !config.copy_from_worktree.is_empty() {
+        let worktrees = list_worktrees(&repo_root)?;
+        match find_source_worktree(&worktrees, &base_branch, &worktree_path) {
+            Some(source) => {
+                copy_ignored_files(source, &worktree_path, &config.copy_from_worktree, quiet)?;
+            }
+            None => eprintln!("Warning: no worktree to copy files from"),
+        }
+    }
+
+    if opts.no_commands || opts.no_verify {
+        info(format_args!("Skipping post-add commands"));
+    } else if !config.post_add_commands.is_empty() {
+        run_post_add_commands(
+            &config.post_add_commands,
+            &worktree_path,
+            &branch,
+            &base_branch,
+            opts.offline,
+            quiet,
+        )?;
+    }
+
+    if opts.push {
+        if !is_new_branch && has_upstream(&worktree_path, &branch) {
+            info(format_args!("Skipping --push: '{}' already has an upstream", branch));
+        } else if !push_upstream(&worktree_path, &branch, quiet) {
+            // ワークツリーは作成済みなので失敗扱いにはしない
+            eprintln!(
+                "Warning: git push -u origin {} failed; push it manually later",
+                branch
+            );
+        }
     }
 
-    eprintln!("\nDone! Worktree created at: {}", worktree_path.display());
+    info(format_args!("\nDone! Worktree created at: {}", worktree_path.display()));
+
+    if opts.print_json {
+        println!("{}", serde_json::to_string(&output)?);
+    } else if quiet {
+        if opts.print_path {
+            println!("{}", output.path);
+        } else {
+            eprintln!("{}", output.path);
+        }
+    } else {
+        if opts.print_path {
+            println!("{}", output.path);
+        }
+        let mut summary = Summary::new("bw add");
+        summary.add("branch", &output.branch);
+        summary.add("base", &output.base);
+        summary.add("path", &output.path);
+        summary.print();
+    }
 
     Ok(())
 }
 
-pub fn execute_rm(name: &str, force: bool) -> anyhow::Result<()> {
+/// `delete_branch` is `Some` when `--delete-branch` or `--keep-branch` was given;
+/// otherwise `remove_deletes_branch` in bw.toml decides. `force` only applies to
+/// the worktree; an unmerged branch is deleted only with `force_branch`.
+pub fn execute_rm(
+    name: &str,
+    force: bool,
+    delete_branch: Option<bool>,
+    force_branch: bool,
+) -> anyhow::Result<()> {
     let repo_root = find_repo_root()?;
-    let dirname = branch_to_dirname(name);
-    let worktree_path = repo_root.join(&dirname);
+    let _lock = RepoLock::acquire(&repo_root)?;
+    let worktree_path = worktree_target(&repo_root, name);
+    let label_key = super::labels::key(&repo_root, &worktree_path);
 
     if !worktree_path.exists() {
         return Err(
@@ -80,6 +495,106 @@ pub fn execute_rm(name: &str, force: bool) -> anyhow::Result<()> {
         );
     }
 
+    let config = load_bw_config(&repo_root)?;
+    // worktree を消す前にブランチ名を控えておく
+    let branch = if should_delete_branch(delete_branch, &config) {
+        worktree_branch(&repo_root, &worktree_path)
+    } else {
+        None
+    };
+
+    remove_worktree(&repo_root, &worktree_path, force)?;
+
+    super::labels::set(&repo_root, &label_key, None)?;
+
+    if let Some(branch) = branch {
+        delete_local_branch(&repo_root, &branch, force_branch)?;
+    }
+
+    eprintln!("Done! Worktree removed: {}", name);
+
+    Ok(())
+}
+
+/// For `bw add --switch`: the path of the worktree that has `branch` checked out,
+/// wherever it is (`--path`, `bw mv`, a renamed directory). `None` when there is
+/// none and `worktree_path` is free; anything else at that path is an error, as
+/// without `--switch`.
+fn existing_worktree_for(
+    repo_root: &Path,
+    worktree_path: &Path,
+    branch: &str,
+) -> Result<Option<PathBuf>, GhbareError> {
+    let worktrees = list_worktrees(repo_root)?;
+    let linked = || worktrees.iter().filter(|w| !w.bare);
+    if let Some(existing) = linked().find(|w| w.short_branch() == Some(branch)) {
+        return Ok(Some(existing.path.clone()));
+    }
+    if !worktree_path.exists() {
+        return Ok(None);
+    }
+    let target = worktree_path.canonicalize()?;
+    match linked().find(|w| w.path.canonicalize().is_ok_and(|p| p == target)) {
+        Some(other) => Err(GhbareError::DirnameCollision {
+            dirname: worktree_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            branch: branch.to_string(),
+            existing: other.branch_or_short_head(),
+        }),
+        None => Err(GhbareError::WorktreeAlreadyExists(worktree_path.display().to_string())),
+    }
+}
+
+/// Worktree named by `name`: an existing path (absolute or relative to the
+/// current directory) of a registered worktree, else `<repo>/<dirname of name>`.
+pub(crate) fn worktree_target(repo_root: &Path, name: &str) -> PathBuf {
+    registered_worktree_path(repo_root, Path::new(name))
+        .unwrap_or_else(|| repo_root.join(branch_to_dirname(name)))
+}
+
+fn registered_worktree_path(repo_root: &Path, path: &Path) -> Option<PathBuf> {
+    let target = path.canonicalize().ok()?;
+    let worktrees = list_worktrees(repo_root).ok()?;
+    worktrees
+        .iter()
+        .filter(|w| !w.bare)
+        .any(|w| w.path.canonicalize().is_ok_and(|p| p == target))
+        .then_some(target)
+}
+
+fn should_delete_branch(flag: Option<bool>, config: &BwConfig) -> bool {
+    flag.unwrap_or(config.remove_deletes_branch)
+}
+
+fn worktree_branch(repo_root: &Path, worktree_path: &Path) -> Option<String> {
+    let target = worktree_path.canonicalize().ok()?;
+    let worktrees = list_worktrees(repo_root).ok()?;
+    worktrees
+        .iter()
+        .find(|w| w.path.canonicalize().is_ok_and(|p| p == target))
+        .and_then(|w| w.short_branch().map(|b| b.to_string()))
+}
+
+/// Deletes `branch` with `git branch -d`, or `-D` when `force` is set (`--force-branch`).
+fn delete_local_branch(repo_root: &Path, branch: &str, force: bool) -> Result<(), GhbareError> {
+    eprintln!("Deleting branch: {}", branch);
+    let status = git_cmd()
+        .args(["branch", if force { "-D" } else { "-d" }, branch])
+        .current_dir(repo_root)
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "git branch -d failed for '{}' (not fully merged? use --force-branch)",
+            branch
+        )));
+    }
+    Ok(())
+}
+
+fn remove_worktree(repo_root: &Path, worktree_path: &Path, force: bool) -> Result<(), GhbareError> {
     eprintln!("Removing worktree: {}", worktree_path.display());
 
     let mut args = vec!["worktree", "remove"];
@@ -88,219 +603,2758 @@ pub fn execute_rm(name: &str, force: bool) -> anyhow::Result<()> {
     }
     args.push(worktree_path.to_str().unwrap());
 
-    let status = Command::new("git")
+    let status = git_cmd()
         .args(&args)
-        .current_dir(&repo_root)
+        .current_dir(repo_root)
         .status()
         .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
 
     if !status.success() {
         return Err(GhbareError::WorktreeError(format!(
             "git worktree remove failed for '{}'",
-            name
-        ))
-        .into());
+            worktree_path.display()
+        )));
     }
+    Ok(())
+}
 
-    eprintln!("Done! Worktree removed: {}", name);
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    /// Allow selecting multiple worktrees
+    pub multi: bool,
+    /// Terminate output with NUL instead of newline
+    pub null: bool,
+    /// Print branch names instead of selecting a path
+    pub branch_only: bool,
+    /// Show a diff preview of each worktree in fzf
+    pub preview: bool,
+    /// Never pipe plain output through $PAGER
+    pub no_pager: bool,
+    /// Only print worktrees whose last commit is older than this many days
+    pub stale: Option<u64>,
+    /// Remove the worktrees matched by `stale`
+    pub remove: bool,
+    /// Do not ask before removing them
+    pub yes: bool,
+    /// Print every worktree as JSON instead of selecting one
+    pub json: bool,
+    /// Indent the JSON output
+    pub pretty: bool,
+    /// Only include worktrees whose branch matches this glob (or regex)
+    pub filter: Option<String>,
+    /// Treat `filter` as a regular expression
+    pub regex: bool,
+    /// Print only the number of worktrees
+    pub count: bool,
+    /// List the worktrees of every repository under root, grouped by repository
+    pub all_repos: bool,
+    /// Add the abbreviated HEAD commit to each worktree
+    pub show_head: bool,
+}
 
-    Ok(())
+/// Schema version of `bw list --json`. Bump it when fields change meaning or
+/// are removed; adding optional fields keeps the version.
+const LIST_JSON_VERSION: u32 = 1;
+
+/// `{"version": 1, "worktrees": [...]}`
+#[derive(Debug, Serialize)]
+struct ListOutput<'a> {
+    version: u32,
+    worktrees: Vec<ListEntry<'a>>,
 }
 
-fn find_repo_root() -> Result<PathBuf, GhbareError> {
-    let current = std::env::current_dir()?;
-    let mut dir = current.as_path();
+/// `{"version": 1, "repos": [{"path": ..., "worktrees": [...]}]}` for `--all-repos`
+#[derive(Debug, Serialize)]
+struct AllReposOutput<'a> {
+    version: u32,
+    repos: Vec<RepoWorktrees<'a>>,
+}
 
-    loop {
-        let bare_path = dir.join(".bare");
-        if bare_path.exists() && bare_path.is_dir() {
-            return Ok(dir.to_path_buf());
-        }
+#[derive(Debug, Serialize)]
+struct RepoWorktrees<'a> {
+    path: String,
+    worktrees: Vec<ListEntry<'a>>,
+}
 
-        match dir.parent() {
-            Some(parent) => dir = parent,
-            None => return Err(GhbareError::RepoRootNotFound),
-        }
-    }
+#[derive(Debug, Serialize)]
+struct ListEntry<'a> {
+    path: String,
+    branch: Option<&'a str>,
+    head: Option<&'a str>,
+    /// First 8 characters of `head`, with `--show-head`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head_short: Option<&'a str>,
+    detached: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<&'a str>,
 }
 
-fn load_bw_config(repo_root: &Path) -> Result<BwConfig, GhbareError> {
-    let config_path = repo_root.join("bw.toml");
+/// Shows uncommitted changes of the highlighted worktree; `{1}` is its path column.
+const DEFAULT_PREVIEW_COMMAND: &str = "git -C {1} -c color.ui=always diff --stat";
 
-    if !config_path.exists() {
-        return Ok(BwConfig::default());
+pub fn execute_list(opts: ListOptions) -> anyhow::Result<()> {
+    if opts.all_repos {
+        return list_all_repos(&opts);
     }
 
-    let content = fs::read_to_string(&config_path)?;
-    let config: BwConfig = toml::from_str(&content)
-        .map_err(|e| GhbareError::ConfigParseError(e.to_string()))?;
+    let repo_root = find_repo_root()?;
+    let worktrees = load_listed_worktrees(&repo_root, &opts)?;
+    let null = opts.null;
 
-    Ok(config)
+    if opts.count {
+        println!("{}", count_worktrees(&worktrees));
+        return Ok(());
+    }
+
+    if let Some(days) = opts.stale {
+        return list_stale(&repo_root, &worktrees, days, opts.remove, opts.yes);
+    }
+
+    if opts.json {
+        println!("{}", worktrees_json(&worktrees, opts.pretty, opts.show_head)?);
+        return Ok(());
+    }
+
+    if opts.branch_only {
+        let terminator = if null { '\0' } else { '\n' };
+        let output: String = worktrees
+            .iter()
+            .filter(|w| !w.bare)
+            .map(|w| match w.short_head().filter(|_| opts.show_head) {
+                Some(head) => format!("{}\t{}{}", w.branch_or_short_head(), head, terminator),
+                None => format!("{}{}", w.branch_or_short_head(), terminator),
+            })
+            .collect();
+        // NUL-separated output is meant for xargs, never for a pager
+        super::pager::print_paged(&output, opts.no_pager || null)?;
+        return Ok(());
+    }
+
+    let config = crate::config::get_config().ok();
+    let preview = if opts.preview {
+        let configured = config.as_ref().and_then(|c| c.fzf_preview_command.clone());
+        Some(configured.unwrap_or_else(|| DEFAULT_PREVIEW_COMMAND.to_string()))
+    } else {
+        None
+    };
+    let picker = PickerOptions {
+        multi: opts.multi,
+        preview,
+        show_head: opts.show_head,
+        ..PickerOptions::layout_from(config.as_ref())
+    };
+
+    let paths = select_worktrees(&worktrees, &picker)?;
+    print!("{}", format_paths(&paths, null));
+    std::io::stdout().flush()?;
+
+    Ok(())
 }
 
-fn branch_to_dirname(branch: &str) -> String {
-    branch.replace('/', "-")
+/// Worktrees of `repo_root` with labels attached, narrowed by `--filter`.
+fn load_listed_worktrees(
+    repo_root: &Path,
+    opts: &ListOptions,
+) -> Result<Vec<WorktreeEntry>, GhbareError> {
+    let mut worktrees = list_worktrees(repo_root)?;
+    super::labels::attach(repo_root, &super::labels::load(repo_root)?, &mut worktrees);
+    if let Some(pattern) = &opts.filter {
+        let filter = BranchFilter::new(pattern, opts.regex)?;
+        worktrees.retain(|w| w.bare || w.short_branch().is_some_and(|b| filter.matches(b)));
+    }
+    Ok(worktrees)
 }
 
-fn generate_wip_branch_name() -> String {
-    let output = Command::new("date")
-        .arg("+%m%d-%H%M%S")
-        .output()
-        .expect("Failed to execute date command");
+/// `bw list --all-repos`: every repository under root with its worktrees.
+fn list_all_repos(opts: &ListOptions) -> anyhow::Result<()> {
+    let root = crate::config::get_root()?;
+    let mut repos = Vec::new();
+    for repo in find_bare_repos(&root)? {
+        let worktrees = load_listed_worktrees(&repo, opts)?;
+        let name = repo.strip_prefix(&root).unwrap_or(&repo).to_path_buf();
+        repos.push((name, worktrees));
+    }
 
-    let timestamp = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    format!("wip/{}", timestamp)
+    if opts.count {
+        println!("{}", repos.iter().map(|(_, w)| count_worktrees(w)).sum::<usize>());
+        return Ok(());
+    }
+    if opts.json {
+        println!("{}", all_repos_json(&root, &repos, opts.pretty, opts.show_head)?);
+        return Ok(());
+    }
+    super::pager::print_paged(&format_all_repos(&repos, opts.show_head), opts.no_pager)?;
+    Ok(())
 }
 
-fn prune_worktrees_if_needed(repo_root: &Path) {
-    // Check if pruning is needed (output may go to stdout or stderr)
-    let output = Command::new("git")
-        .args(["worktree", "prune", "--dry-run"])
-        .current_dir(repo_root)
-        .output();
-
-    if let Ok(output) = output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if !stdout.trim().is_empty() || !stderr.trim().is_empty() {
-            eprintln!("Pruning stale worktree entries...");
-            let _ = Command::new("git")
-                .args(["worktree", "prune"])
-                .current_dir(repo_root)
-                .status();
+/// One header line per repository (relative to root), then its worktrees indented.
+fn format_all_repos(repos: &[(PathBuf, Vec<WorktreeEntry>)], show_head: bool) -> String {
+    let mut output = String::new();
+    for (name, worktrees) in repos {
+        let header = name.display().to_string();
+        output.push_str(&format!("{}\n", paint(&header, Style::Bold, Stream::Stdout)));
+        for worktree in worktrees.iter().filter(|w| !w.bare) {
+            output.push_str(&format!("  {}\n", worktree_line(worktree, show_head)));
         }
     }
+    output
 }
 
-fn branch_exists(repo_root: &Path, branch: &str) -> bool {
-    Command::new("git")
-        .args([
-            "show-ref",
-            "--verify",
-            "--quiet",
-            &format!("refs/heads/{}", branch),
-        ])
-        .current_dir(repo_root)
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false)
+fn all_repos_json(
+    root: &Path,
+    repos: &[(PathBuf, Vec<WorktreeEntry>)],
+    pretty: bool,
+    show_head: bool,
+) -> serde_json::Result<String> {
+    let output = AllReposOutput {
+        version: LIST_JSON_VERSION,
+        repos: repos
+            .iter()
+            .map(|(name, worktrees)| RepoWorktrees {
+                path: root.join(name).display().to_string(),
+                worktrees: list_entries(worktrees, show_head),
+            })
+            .collect(),
+    };
+    if pretty {
+        serde_json::to_string_pretty(&output)
+    } else {
+        serde_json::to_string(&output)
+    }
 }
 
-fn has_any_commits(repo_root: &Path) -> bool {
-    Command::new("git")
-        .args(["rev-parse", "HEAD"])
-        .current_dir(repo_root)
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+/// Fails when `count` worktrees already reach `max_worktrees`, unless `force`.
+fn check_worktree_limit(count: usize, max: usize, force: bool) -> Result<(), GhbareError> {
+    if count >= max && !force {
+        return Err(GhbareError::TooManyWorktrees { count, max });
+    }
+    Ok(())
 }
 
-fn add_orphan_worktree(
-    repo_root: &Path,
-    worktree_path: &Path,
-    branch_name: &str,
-) -> Result<(), GhbareError> {
-    let status = Command::new("git")
-        .args([
-            "worktree",
-            "add",
-            "-b",
-            branch_name,
-            "--orphan",
-            worktree_path.to_str().unwrap(),
-        ])
-        .current_dir(repo_root)
-        .status()
-        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+/// Number of checked-out worktrees; the bare repository itself is not counted.
+fn count_worktrees(worktrees: &[WorktreeEntry]) -> usize {
+    worktrees.iter().filter(|w| !w.bare).count()
+}
 
-    if !status.success() {
-        return Err(GhbareError::WorktreeError(format!(
-            "git worktree add --orphan failed for branch '{}'",
-            branch_name
-        )));
-    }
-    Ok(())
+/// Branch-name matcher for `bw list --filter`.
+enum BranchFilter {
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
 }
 
-fn add_worktree(
-    repo_root: &Path,
-    worktree_path: &Path,
-    branch_name: &str,
-    base_branch: &str,
-) -> Result<(), GhbareError> {
-    // コミットがない場合は orphan worktree を作成
-    if !has_any_commits(repo_root) {
-        return add_orphan_worktree(repo_root, worktree_path, branch_name);
+impl BranchFilter {
+    fn new(pattern: &str, regex: bool) -> Result<Self, GhbareError> {
+        let invalid = |e: String| {
+            GhbareError::WorktreeError(format!("Invalid filter '{}': {}", pattern, e))
+        };
+        if regex {
+            regex::Regex::new(pattern)
+                .map(Self::Regex)
+                .map_err(|e| invalid(e.to_string()))
+        } else {
+            glob::Pattern::new(pattern)
+                .map(Self::Glob)
+                .map_err(|e| invalid(e.to_string()))
+        }
     }
 
-    let status = if branch_exists(repo_root, branch_name) {
-        // 既存ブランチ: git worktree add <path> <branch>
-        Command::new("git")
-            .args([
-                "worktree",
-                "add",
-                worktree_path.to_str().unwrap(),
-                branch_name,
-            ])
-            .current_dir(repo_root)
-            .status()
+    fn matches(&self, branch: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => pattern.matches(branch),
+            Self::Regex(regex) => regex.is_match(branch),
+        }
+    }
+}
+
+/// Serializes the non-bare worktrees; compact unless `pretty` is set.
+fn list_entries(worktrees: &[WorktreeEntry], show_head: bool) -> Vec<ListEntry<'_>> {
+    worktrees
+        .iter()
+        .filter(|w| !w.bare)
+        .map(|w| ListEntry {
+            path: w.path.display().to_string(),
+            branch: w.short_branch(),
+            head: w.head.as_deref(),
+            head_short: w.short_head().filter(|_| show_head),
+            detached: w.detached,
+            label: w.label.as_deref(),
+        })
+        .collect()
+}
+
+fn worktrees_json(
+    worktrees: &[WorktreeEntry],
+    pretty: bool,
+    show_head: bool,
+) -> serde_json::Result<String> {
+    let output = ListOutput {
+        version: LIST_JSON_VERSION,
+        worktrees: list_entries(worktrees, show_head),
+    };
+    if pretty {
+        serde_json::to_string_pretty(&output)
     } else {
-        // 新規ブランチ: git worktree add -b <branch> <path> <base>
-        Command::new("git")
-            .args([
-                "worktree",
-                "add",
-                "-b",
-                branch_name,
-                worktree_path.to_str().unwrap(),
-                base_branch,
-            ])
-            .current_dir(repo_root)
-            .status()
+        serde_json::to_string(&output)
     }
-    .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+}
 
-    if !status.success() {
-        return Err(GhbareError::WorktreeError(format!(
-            "git worktree add failed for branch '{}'",
-            branch_name
-        )));
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Prints `path<TAB>N days` for worktrees whose last commit is older than `days`.
+/// With `remove`, asks (unless `yes`) and removes them, except the default
+/// branch's worktree and the one holding the current directory. A failed
+/// removal is reported and the others still go.
+fn list_stale(
+    repo_root: &Path,
+    worktrees: &[WorktreeEntry],
+    days: u64,
+    remove: bool,
+    yes: bool,
+) -> anyhow::Result<()> {
+    let now = crate::index::now_unix();
+    let stale: Vec<(&WorktreeEntry, u64)> = worktrees
+        .iter()
+        .filter(|w| !w.bare)
+        .filter_map(|w| {
+            let age = age_in_days(last_commit_time(&w.path)?, now);
+            (age >= days).then_some((w, age))
+        })
+        .collect();
+
+    for (worktree, age) in &stale {
+        println!("{}\t{} days", worktree.path.display(), age);
+    }
+    std::io::stdout().flush()?;
+
+    if !remove {
+        return Ok(());
+    }
+    let default_branch = bare_head_branch(repo_root);
+    let cwd = std::env::current_dir().and_then(|d| d.canonicalize()).ok();
+    let mut targets = Vec::new();
+    for (worktree, _) in &stale {
+        match stale_removal_blocker(worktree, default_branch.as_deref(), cwd.as_deref()) {
+            Some(reason) => eprintln!("Keeping {} ({})", worktree.path.display(), reason),
+            None => targets.push(*worktree),
+        }
+    }
+    if targets.is_empty() {
+        return Ok(());
+    }
+    let question = format!("Remove {} stale worktree(s)?", targets.len());
+    if !yes && !confirm(&question)? {
+        println!("Aborted");
+        return Ok(());
     }
 
+    let _lock = RepoLock::acquire(repo_root)?;
+    let mut failed = Vec::new();
+    for worktree in &targets {
+        let label_key = super::labels::key(repo_root, &worktree.path);
+        match remove_worktree(repo_root, &worktree.path, false) {
+            Ok(()) => super::labels::set(repo_root, &label_key, None)?,
+            Err(e) => {
+                eprintln!("{}", e);
+                failed.push(worktree.path.display().to_string());
+            }
+        }
+    }
+    eprintln!(
+        "Done! Removed {} stale worktree(s)",
+        targets.len() - failed.len()
+    );
+    if !failed.is_empty() {
+        return Err(GhbareError::WorktreeError(format!(
+            "Could not remove {} worktree(s): {}",
+            failed.len(),
+            failed.join(", ")
+        ))
+        .into());
+    }
     Ok(())
 }
 
-fn run_post_add_commands(commands: &str, working_dir: &Path) -> Result<(), GhbareError> {
-    if commands.trim().is_empty() {
-        return Ok(());
+/// Why `bw list --stale --remove` keeps `worktree`: it has the default branch
+/// (`default_branch`, a full ref) checked out, or contains `cwd`.
+fn stale_removal_blocker(
+    worktree: &WorktreeEntry,
+    default_branch: Option<&str>,
+    cwd: Option<&Path>,
+) -> Option<&'static str> {
+    if default_branch.is_some() && worktree.branch.as_deref() == default_branch {
+        return Some("default branch");
     }
-    eprintln!("Running post-add commands...");
-    let status = Command::new("sh")
-        .arg("-c")
-        .arg(commands)
-        .current_dir(working_dir)
+    let path = worktree.path.canonicalize().ok()?;
+    cwd.is_some_and(|cwd| cwd.starts_with(&path))
+        .then_some("current directory")
+}
+
+/// Full ref HEAD of the bare repository points at, i.e. the default branch.
+fn bare_head_branch(repo_root: &Path) -> Option<String> {
+    let repo = Repository::open(repo_root).ok()?;
+    let head = repo.find_reference("HEAD").ok()?;
+    head.symbolic_target().map(str::to_string)
+}
+
+/// Committer time of HEAD in `worktree_path`, in Unix seconds.
+fn last_commit_time(worktree_path: &Path) -> Option<u64> {
+    let output = git_cmd()
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(worktree_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+fn age_in_days(timestamp: u64, now: u64) -> u64 {
+    now.saturating_sub(timestamp) / SECONDS_PER_DAY
+}
+
+/// Terminates each path with `\n`, or `\0` for `xargs -0` when `null` is set.
+fn format_paths(paths: &[PathBuf], null: bool) -> String {
+    let terminator = if null { '\0' } else { '\n' };
+    paths
+        .iter()
+        .map(|p| format!("{}{}", p.display(), terminator))
+        .collect()
+}
+
+pub fn execute_shell() -> anyhow::Result<()> {
+    let repo_root = find_repo_root()?;
+    let worktrees = list_worktrees(&repo_root)?;
+
+    let Some(path) = select_worktree(&worktrees)? else {
+        return Ok(());
+    };
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    eprintln!("Entering {} (exit to return)", path.display());
+
+    let status = Command::new(&shell)
+        .current_dir(&path)
         .status()
-        .map_err(|e| GhbareError::WorktreeError(format!("Failed to execute: {}", e)))?;
+        .map_err(|e| GhbareError::WorktreeError(format!("Failed to spawn {}: {}", shell, e)))?;
+
     if !status.success() {
+        eprintln!("Shell exited with non-zero status");
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct WorktreeEntry {
+    pub(crate) path: PathBuf,
+    pub(crate) head: Option<String>,
+    pub(crate) branch: Option<String>,
+    pub(crate) bare: bool,
+    pub(crate) detached: bool,
+    /// Note set with `bw add --label` / `bw label` (filled in by `labels::attach`)
+    pub(crate) label: Option<String>,
+}
+
+impl WorktreeEntry {
+    /// Branch name without the `refs/heads/` prefix.
+    pub(crate) fn short_branch(&self) -> Option<&str> {
+        self.branch
+            .as_deref()
+            .map(|b| b.strip_prefix("refs/heads/").unwrap_or(b))
+    }
+
+    /// First 8 characters of the HEAD commit, for `bw list --show-head`.
+    fn short_head(&self) -> Option<&str> {
+        self.head.as_deref().map(|h| &h[..h.len().min(8)])
+    }
+
+    /// Branch name, or the abbreviated HEAD for detached worktrees.
+    fn branch_or_short_head(&self) -> String {
+        match (self.short_branch(), self.head.as_deref()) {
+            (Some(branch), _) => branch.to_string(),
+            (None, Some(head)) => head.chars().take(7).collect(),
+            (None, None) => "(unknown)".to_string(),
+        }
+    }
+}
+
+/// `branch_to_dirname` flattens `/`, so `feature/x` and `feature-x` share a
+/// directory. Fails when `dirname` belongs to a worktree of another branch.
+fn check_dirname_collision(
+    worktrees: &[WorktreeEntry],
+    dirname: &str,
+    branch: &str,
+) -> Result<(), GhbareError> {
+    let existing = worktrees
+        .iter()
+        .filter(|w| !w.bare && w.path.file_name() == Some(dirname.as_ref()))
+        .find(|w| w.short_branch() != Some(branch));
+    match existing {
+        Some(w) => Err(GhbareError::DirnameCollision {
+            dirname: dirname.to_string(),
+            branch: branch.to_string(),
+            existing: w
+                .short_branch()
+                .map(str::to_string)
+                .unwrap_or_else(|| "(detached HEAD)".to_string()),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Worktrees in `git worktree list --porcelain` order. Read through git2, or
+/// from the git command when git2 cannot open the repository or a worktree
+/// (e.g. one whose directory was deleted).
+pub(crate) fn list_worktrees(repo_root: &Path) -> Result<Vec<WorktreeEntry>, GhbareError> {
+    match list_worktrees_git2(repo_root) {
+        Ok(entries) => Ok(entries),
+        Err(_) => list_worktrees_porcelain(repo_root),
+    }
+}
+
+fn list_worktrees_git2(repo_root: &Path) -> Result<Vec<WorktreeEntry>, git2::Error> {
+    let repo = Repository::open(repo_root)?;
+    let mut entries = vec![if repo.is_bare() {
+        WorktreeEntry {
+            path: repo.path().components().collect(),
+            bare: true,
+            ..Default::default()
+        }
+    } else {
+        let workdir = repo.workdir().unwrap_or(repo.path());
+        head_entry(&repo, workdir.components().collect())?
+    }];
+
+    let mut linked = Vec::new();
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        let worktree_repo = Repository::open_from_worktree(&worktree)?;
+        linked.push(head_entry(&worktree_repo, worktree.path().to_path_buf())?);
+    }
+    // git sorts linked worktrees by path, keeping the main one first
+    linked.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+    entries.extend(linked);
+    Ok(entries)
+}
+
+/// Entry for a non-bare worktree. An unborn branch reports the zero HEAD, like git.
+fn head_entry(repo: &Repository, path: PathBuf) -> Result<WorktreeEntry, git2::Error> {
+    let branch = repo
+        .find_reference("HEAD")?
+        .symbolic_target()
+        .map(str::to_string);
+    let head = repo.refname_to_id("HEAD").unwrap_or_else(|_| git2::Oid::zero());
+    Ok(WorktreeEntry {
+        path,
+        head: Some(head.to_string()),
+        detached: branch.is_none(),
+        branch,
+        ..Default::default()
+    })
+}
+
+fn list_worktrees_porcelain(repo_root: &Path) -> Result<Vec<WorktreeEntry>, GhbareError> {
+    let output = git_cmd()
+        .args(["worktree", "list", "--porcelain"])
+        .current_dir(repo_root)
+        .output()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+
+    if !output.status.success() {
         return Err(GhbareError::WorktreeError(
-            "Post-add commands failed".to_string(),
+            "git worktree list failed".to_string(),
         ));
     }
+
+    Ok(parse_worktree_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_worktree_porcelain(text: &str) -> Vec<WorktreeEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<WorktreeEntry> = None;
+
+    for line in text.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            if let Some(entry) = current.take() {
+                entries.push(entry);
+            }
+            current = Some(WorktreeEntry {
+                path: PathBuf::from(path),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some(entry) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(head) = line.strip_prefix("HEAD ") {
+            entry.head = Some(head.to_string());
+        } else if let Some(branch) = line.strip_prefix("branch ") {
+            entry.branch = Some(branch.to_string());
+        } else if line == "bare" {
+            entry.bare = true;
+        } else if line == "detached" {
+            entry.detached = true;
+        }
+    }
+
+    if let Some(entry) = current {
+        entries.push(entry);
+    }
+
+    entries
+}
+
+/// Lets the user pick a worktree with fzf. Returns `None` when the selection is cancelled.
+fn select_worktree(worktrees: &[WorktreeEntry]) -> Result<Option<PathBuf>, GhbareError> {
+    let config = crate::config::get_config().ok();
+    let picker = PickerOptions::layout_from(config.as_ref());
+    Ok(select_worktrees(worktrees, &picker)?.into_iter().next())
+}
+
+#[derive(Debug, Default)]
+struct PickerOptions {
+    multi: bool,
+    /// fzf `--preview` command
+    preview: Option<String>,
+    /// fzf `--height` (e.g., "40%"); full screen when unset
+    height: Option<String>,
+    /// fzf `--layout=reverse`
+    reverse: bool,
+    /// Add the abbreviated HEAD column after the branch
+    show_head: bool,
+}
+
+impl PickerOptions {
+    /// Height and layout from fzf_height / fzf_reverse in the global config.
+    fn layout_from(config: Option<&crate::config::Config>) -> Self {
+        Self {
+            height: config.and_then(|c| c.fzf_height.clone()),
+            reverse: config.is_some_and(|c| c.fzf_reverse),
+            ..Default::default()
+        }
+    }
+}
+
+/// `path<TAB>branch[<TAB>head][<TAB>label]`, as shown in fzf.
+fn worktree_line(w: &WorktreeEntry, show_head: bool) -> String {
+    let mut columns = vec![
+        w.path.display().to_string(),
+        w.short_branch().unwrap_or("(detached)").to_string(),
+    ];
+    if show_head {
+        columns.push(w.short_head().unwrap_or("-").to_string());
+    }
+    columns.extend(w.label.clone());
+    columns.join("\t")
+}
+
+/// Like `select_worktree`, but allows picking several worktrees when `multi` is set.
+fn select_worktrees(
+    worktrees: &[WorktreeEntry],
+    picker: &PickerOptions,
+) -> Result<Vec<PathBuf>, GhbareError> {
+    let lines: Vec<String> = worktrees
+        .iter()
+        .filter(|w| !w.bare)
+        .map(|w| worktree_line(w, picker.show_head))
+        .collect();
+
+    if lines.is_empty() {
+        return Err(GhbareError::WorktreeError("No worktrees found".to_string()));
+    }
+
+    Ok(parse_selection(&run_fzf(&lines, picker)?))
+}
+
+/// Pipes `lines` into fzf and returns its output; empty when nothing was selected.
+fn run_fzf(lines: &[String], picker: &PickerOptions) -> Result<String, GhbareError> {
+    let args = fzf_args(picker);
+
+    let mut child = Command::new("fzf")
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| GhbareError::WorktreeError(format!("Failed to run fzf: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // fzf may already have exited (e.g. Esc before reading all lines)
+        let _ = stdin.write_all(lines.join("\n").as_bytes());
+    }
+
+    let output = child.wait_with_output()?;
+    // fzf reads Ctrl-C (and Esc) as keys in raw mode and exits with 130; bw passes
+    // that on through the error path so callers still clean up
+    if output.status.code() == Some(FZF_INTERRUPTED) {
+        return Err(GhbareError::Interrupted);
+    }
+    if !output.status.success() {
+        return Ok(String::new());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Lets the user pick one of `items` with fzf (layout from the global config).
+/// `None` when nothing was picked.
+pub(crate) fn pick_one(items: &[String]) -> Result<Option<String>, GhbareError> {
+    let config = crate::config::get_config().ok();
+    let picker = PickerOptions::layout_from(config.as_ref());
+    let output = run_fzf(items, &picker)?;
+    let picked = output.lines().next().map(str::trim).unwrap_or("");
+    Ok((!picked.is_empty()).then(|| picked.to_string()))
+}
+
+/// Lets the user pick a base from local and remote branches. Returns `None`
+/// (use the configured base) when fzf is unavailable or nothing was picked;
+/// an interrupted fzf is an error.
+fn pick_base(repo_root: &Path) -> Result<Option<String>, GhbareError> {
+    let branches = base_candidates(repo_root);
+    if branches.is_empty() {
+        eprintln!("No branches to pick a base from; using the configured base");
+        return Ok(None);
+    }
+    match pick_one(&branches) {
+        Ok(Some(picked)) => Ok(Some(picked)),
+        Ok(None) => {
+            eprintln!("No base selected; using the configured base");
+            Ok(None)
+        }
+        Err(GhbareError::Interrupted) => Err(GhbareError::Interrupted),
+        Err(e) => {
+            eprintln!("Warning: {}; using the configured base", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Local branches followed by remote-tracking branches, without `<remote>/HEAD`.
+fn base_candidates(repo_root: &Path) -> Vec<String> {
+    let output = match git_cmd()
+        .args(["for-each-ref", "--format=%(refname)", "refs/heads", "refs/remotes"])
+        .current_dir(repo_root)
+        .output()
+    {
+        Ok(o) if o.status.success() => o,
+        _ => return Vec::new(),
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|r| !r.ends_with("/HEAD"))
+        .filter_map(|r| {
+            r.strip_prefix("refs/heads/")
+                .or_else(|| r.strip_prefix("refs/remotes/"))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// fzf's exit status for Ctrl-C and Esc (128 + SIGINT).
+const FZF_INTERRUPTED: i32 = 130;
+
+fn fzf_args(picker: &PickerOptions) -> Vec<String> {
+    let mut args = vec!["--delimiter".to_string(), "\t".to_string()];
+    if picker.multi {
+        args.push("--multi".to_string());
+    }
+    if let Some(preview) = &picker.preview {
+        args.push("--ansi".to_string());
+        args.push("--preview".to_string());
+        args.push(preview.clone());
+    }
+    if let Some(height) = &picker.height {
+        args.push(format!("--height={}", height));
+    }
+    if picker.reverse {
+        args.push("--layout=reverse".to_string());
+    }
+    args
+}
+
+/// Extracts the path column from fzf output lines (`<path>\t<branch>`).
+fn parse_selection(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .filter_map(|line| line.split('\t').next())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Runs `git worktree repair` to fix `.git` files and `.bare/worktrees/*/gitdir`
+/// after worktrees or the repository were moved by hand.
+pub fn execute_repair(paths: &[String]) -> anyhow::Result<()> {
+    let repo_root = find_repo_root()?;
+    let _lock = RepoLock::acquire(&repo_root)?;
+
+    let output = git_cmd()
+        .args(["worktree", "repair"])
+        .args(paths)
+        .current_dir(&repo_root)
+        .output()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+
+    // git reports each fix on stderr
+    let report = String::from_utf8_lossy(&output.stderr);
+    for line in report.lines() {
+        eprintln!("{}", line);
+    }
+    if !output.status.success() {
+        return Err(GhbareError::WorktreeError("git worktree repair failed".to_string()).into());
+    }
+
+    match count_repairs(&report) {
+        0 => eprintln!("Done! Nothing to repair"),
+        n => eprintln!("Done! Repaired {} worktree link(s)", n),
+    }
+
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn count_repairs(report: &str) -> usize {
+    report.lines().filter(|l| l.starts_with("repair: ")).count()
+}
 
-    #[test]
-    fn test_branch_to_dirname() {
-        assert_eq!(branch_to_dirname("feature/000"), "feature-000");
-        assert_eq!(branch_to_dirname("fix/bug-123"), "fix-bug-123");
-        assert_eq!(branch_to_dirname("main"), "main");
-        assert_eq!(
-            branch_to_dirname("feature/sub/deep"),
-            "feature-sub-deep"
+pub fn execute_prune(expire: Option<&str>) -> anyhow::Result<()> {
+    let repo_root = find_repo_root()?;
+    let _lock = RepoLock::acquire(&repo_root)?;
+
+    let mut args = vec!["worktree", "prune", "--verbose"];
+    if let Some(expire) = expire {
+        validate_expire(expire)?;
+        args.push("--expire");
+        args.push(expire);
+    }
+
+    let status = git_cmd()
+        .args(&args)
+        .current_dir(&repo_root)
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+
+    if !status.success() {
+        return Err(GhbareError::WorktreeError("git worktree prune failed".to_string()).into());
+    }
+
+    eprintln!("Done! Pruned stale worktree entries");
+
+    Ok(())
+}
+
+/// Loose check only; git itself parses the value (e.g., "2.weeks.ago", "now").
+fn validate_expire(expire: &str) -> Result<(), GhbareError> {
+    if expire.trim().is_empty() || expire.starts_with('-') {
+        return Err(GhbareError::WorktreeError(format!(
+            "Invalid --expire value: '{}'",
+            expire
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn find_repo_root() -> Result<PathBuf, GhbareError> {
+    find_repo_root_from(&std::env::current_dir()?)
+}
+
+/// Asks git for the shared git dir first (this honors `GIT_DIR`, e.g. inside git
+/// aliases, and worktrees outside the root), then walks up from `start` looking
+/// for `.bare`. The start is canonicalized first so that symlinked paths resolve
+/// to the real tree before walking parents.
+fn find_repo_root_from(start: &Path) -> Result<PathBuf, GhbareError> {
+    if let Some(root) = repo_root_from_git(start) {
+        return Ok(root);
+    }
+
+    let current = fs::canonicalize(start)?;
+    let mut dir = current.as_path();
+
+    loop {
+        let bare_path = dir.join(".bare");
+        if bare_path.exists() && bare_path.is_dir() {
+            return Ok(dir.to_path_buf());
+        }
+
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => return Err(GhbareError::RepoRootNotFound),
+        }
+    }
+}
+
+/// Parent of the common git dir of `start`, when that dir is a bw `.bare`.
+fn repo_root_from_git(start: &Path) -> Option<PathBuf> {
+    let output = git_cmd()
+        .args(["rev-parse", "--path-format=absolute", "--git-common-dir"])
+        .current_dir(start)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let common_dir = fs::canonicalize(String::from_utf8_lossy(&output.stdout).trim()).ok()?;
+    if common_dir.file_name()? != ".bare" {
+        return None;
+    }
+    common_dir.parent().map(Path::to_path_buf)
+}
+
+/// Finds repository roots (directories containing `.bare`) under `root`.
+/// Does not descend into a repository once found.
+pub(crate) fn find_bare_repos(root: &Path) -> Result<Vec<PathBuf>, GhbareError> {
+    let mut repos = Vec::new();
+    if !root.is_dir() {
+        return Ok(repos);
+    }
+    if root.join(".bare").is_dir() {
+        repos.push(root.to_path_buf());
+        return Ok(repos);
+    }
+
+    let mut children: Vec<PathBuf> = fs::read_dir(root)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.path())
+        .collect();
+    children.sort();
+    for child in children {
+        repos.extend(find_bare_repos(&child)?);
+    }
+    Ok(repos)
+}
+
+fn load_bw_config(repo_root: &Path) -> Result<BwConfig, GhbareError> {
+    let config_path = repo_root.join("bw.toml");
+
+    if !config_path.exists() {
+        return Ok(BwConfig::default());
+    }
+
+    let content = fs::read_to_string(&config_path)?;
+    let config: BwConfig = toml::from_str(&content)
+        .map_err(|e| GhbareError::ConfigParseError(e.to_string()))?;
+
+    Ok(config)
+}
+
+/// Longest dirname (in bytes) used as-is; filesystems commonly cap names at 255 bytes.
+const MAX_DIRNAME_LEN: usize = 200;
+
+/// Maps a branch to its worktree directory name. Names over `MAX_DIRNAME_LEN` are
+/// truncated and suffixed with a hash of the full branch, so the mapping stays
+/// unique and deterministic (`bw rm <branch>` resolves to the same directory).
+pub(crate) fn branch_to_dirname(branch: &str) -> String {
+    let dirname = branch.replace('/', "-");
+    if dirname.len() <= MAX_DIRNAME_LEN {
+        return dirname;
+    }
+
+    let hash = format!("{:08x}", fnv1a(branch.as_bytes()) as u32);
+    let mut end = MAX_DIRNAME_LEN - hash.len() - 1;
+    while !dirname.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}-{}", &dirname[..end], hash)
+}
+
+/// FNV-1a, used instead of `DefaultHasher` because its output must not change between builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn generate_wip_branch_name() -> String {
+    let output = Command::new("date")
+        .arg("+%m%d-%H%M%S")
+        .output()
+        .expect("Failed to execute date command");
+
+    let timestamp = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    format!("wip/{}", timestamp)
+}
+
+/// With `dry_run`, only reports that stale entries would be pruned.
+fn prune_worktrees_if_needed(repo_root: &Path, quiet: bool, dry_run: bool) {
+    // Check if pruning is needed (output may go to stdout or stderr)
+    let output = git_cmd()
+        .args(["worktree", "prune", "--dry-run"])
+        .current_dir(repo_root)
+        .output();
+
+    if let Ok(output) = output {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stdout.trim().is_empty() || !stderr.trim().is_empty() {
+            if dry_run {
+                eprintln!("Would prune stale worktree entries");
+                return;
+            }
+            if !quiet {
+                eprintln!("Pruning stale worktree entries...");
+            }
+            let _ = git_cmd()
+                .args(["worktree", "prune"])
+                .current_dir(repo_root)
+                .status();
+        }
+    }
+}
+
+pub(crate) fn branch_exists(repo_root: &Path, branch: &str) -> bool {
+    git_cmd()
+        .args([
+            "show-ref",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{}", branch),
+        ])
+        .current_dir(repo_root)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+pub(crate) fn remote_branch_exists(repo_root: &Path, branch: &str) -> bool {
+    git_cmd()
+        .args([
+            "show-ref",
+            "--verify",
+            "--quiet",
+            &format!("refs/remotes/origin/{}", branch),
+        ])
+        .current_dir(repo_root)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+fn has_any_commits(repo_root: &Path) -> bool {
+    git_cmd()
+        .args(["rev-parse", "HEAD"])
+        .current_dir(repo_root)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Extra switches for `git worktree add`.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct WorktreeAddFlags {
+    /// Disable git hooks (e.g., post-checkout) while creating the worktree
+    pub(crate) no_verify: bool,
+    /// Register the worktree without checking out any files
+    pub(crate) no_checkout: bool,
+    /// Set the base as upstream of the new branch
+    pub(crate) track: bool,
+    /// Pass --quiet to git worktree add
+    pub(crate) quiet: bool,
+}
+
+/// `git worktree add` with the flags applied; callers append the remaining arguments.
+fn worktree_add_cmd(repo_root: &Path, flags: &WorktreeAddFlags) -> Command {
+    let mut cmd = git_cmd();
+    if flags.no_verify {
+        cmd.args(["-c", "core.hooksPath=/dev/null"]);
+    }
+    // bw add の出力は stdout をパス/JSON 専用にするため、git の出力も stderr へ
+    cmd.args(["worktree", "add"])
+        .current_dir(repo_root)
+        .stdout(std::io::stderr());
+    if flags.no_checkout {
+        cmd.arg("--no-checkout");
+    }
+    if flags.track {
+        cmd.arg("--track");
+    }
+    if flags.quiet {
+        cmd.arg("--quiet");
+    }
+    cmd
+}
+
+fn add_orphan_worktree(
+    repo_root: &Path,
+    worktree_path: &Path,
+    branch_name: &str,
+    flags: &WorktreeAddFlags,
+) -> Result<(), GhbareError> {
+    let status = worktree_add_cmd(repo_root, flags)
+        .args(["-b", branch_name, "--orphan", worktree_path.to_str().unwrap()])
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+
+    if !status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "git worktree add --orphan failed for branch '{}'",
+            branch_name
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn add_worktree(
+    repo_root: &Path,
+    worktree_path: &Path,
+    branch_name: &str,
+    base_branch: &str,
+    flags: &WorktreeAddFlags,
+) -> Result<(), GhbareError> {
+    // コミットがない場合は orphan worktree を作成（チェックアウトするファイルもない）
+    if !has_any_commits(repo_root) {
+        let flags = WorktreeAddFlags {
+            no_checkout: false,
+            track: false,
+            ..*flags
+        };
+        return add_orphan_worktree(repo_root, worktree_path, branch_name, &flags);
+    }
+
+    let status = if branch_exists(repo_root, branch_name) {
+        // 既存ブランチ: git worktree add <path> <branch>
+        worktree_add_cmd(repo_root, flags)
+            .args([worktree_path.to_str().unwrap(), branch_name])
+            .status()
+    } else {
+        // 新規ブランチ: git worktree add -b <branch> <path> <base>
+        worktree_add_cmd(repo_root, flags)
+            .args([
+                "-b",
+                branch_name,
+                worktree_path.to_str().unwrap(),
+                base_branch,
+            ])
+            .status()
+    }
+    .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+
+    if !status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "git worktree add failed for branch '{}'",
+            branch_name
+        )));
+    }
+
+    Ok(())
+}
+
+fn apply_worktree_git_config(
+    worktree_path: &Path,
+    entries: &BTreeMap<String, String>,
+    quiet: bool,
+) -> Result<(), GhbareError> {
+    for (key, value) in entries {
+        if !quiet {
+            eprintln!("Setting git config: {} = {}", key, value);
+        }
+        let status = git_cmd()
+            .args(["config", key, value])
+            .current_dir(worktree_path)
+            .status()
+            .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+        if !status.success() {
+            return Err(GhbareError::WorktreeError(format!(
+                "git config failed for '{}'",
+                key
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Sets cone-mode sparse-checkout `patterns` in a `--no-checkout` worktree, then
+/// populates it unless `checkout` is false. `quiet` silences progress and git's output.
+fn apply_sparse_checkout(
+    worktree_path: &Path,
+    patterns: &[String],
+    checkout: bool,
+    quiet: bool,
+) -> Result<(), GhbareError> {
+    if !quiet {
+        eprintln!("Setting sparse-checkout: {}", patterns.join(" "));
+    }
+    let steps: [(&str, Vec<&str>); 2] = [
+        ("init", vec!["sparse-checkout", "init", "--cone"]),
+        (
+            "set",
+            ["sparse-checkout", "set"]
+                .into_iter()
+                .chain(patterns.iter().map(|p| p.as_str()))
+                .collect(),
+        ),
+    ];
+    for (name, args) in steps {
+        let status = git_cmd()
+            .args(&args)
+            .current_dir(worktree_path)
+            .stdout(progress_output(quiet))
+            .status()
+            .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+        if !status.success() {
+            return Err(GhbareError::WorktreeError(format!(
+                "git sparse-checkout {} failed",
+                name
+            )));
+        }
+    }
+
+    if checkout {
+        let status = git_cmd()
+            .arg("checkout")
+            .args(quiet.then_some("-q"))
+            .current_dir(worktree_path)
+            .stdout(progress_output(quiet))
+            .status()
+            .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+        if !status.success() {
+            return Err(GhbareError::WorktreeError("git checkout failed".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Picks the worktree on `base_branch`, or else the first other non-bare worktree.
+fn find_source_worktree<'a>(
+    worktrees: &'a [WorktreeEntry],
+    base_branch: &str,
+    exclude: &Path,
+) -> Option<&'a Path> {
+    let candidates: Vec<&WorktreeEntry> = worktrees
+        .iter()
+        .filter(|w| !w.bare && w.path != exclude)
+        .collect();
+    candidates
+        .iter()
+        .find(|w| w.short_branch() == Some(base_branch))
+        .or_else(|| candidates.first())
+        .map(|w| w.path.as_path())
+}
+
+/// Copies git-ignored files matching `patterns` from `source` into `dest`.
+/// Tracked files are left alone since they come from the branch itself.
+fn copy_ignored_files(
+    source: &Path,
+    dest: &Path,
+    patterns: &[String],
+    quiet: bool,
+) -> Result<(), GhbareError> {
+    let patterns: Vec<glob::Pattern> = patterns
+        .iter()
+        .map(|p| {
+            glob::Pattern::new(p)
+                .map_err(|e| GhbareError::ConfigParseError(format!("Invalid pattern '{}': {}", p, e)))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let output = git_cmd()
+        .args(["ls-files", "--others", "--ignored", "--exclude-standard"])
+        .current_dir(source)
+        .output()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GhbareError::WorktreeError(
+            "git ls-files failed".to_string(),
+        ));
+    }
+
+    // `*` stays within a directory, so ".env*" does not reach into ignored
+    // directories like node_modules; "**/" opts into that
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    for file in String::from_utf8_lossy(&output.stdout).lines() {
+        if !patterns.iter().any(|p| p.matches_with(file, options)) {
+            continue;
+        }
+
+        let target = dest.join(file);
+        if target.exists() {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source.join(file), &target)?;
+        if !quiet {
+            eprintln!("Copied {}", file);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ResolvedBase {
+    /// The base resolves as given
+    Local,
+    /// Only `origin/<base>` exists
+    Remote(String),
+}
+
+/// How `resolve_base` may look for a base that is missing locally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BaseLookup {
+    /// Use existing refs; suggest `--fetch` on failure
+    Local,
+    /// Fetch `<base>` from origin when `origin/<base>` is missing too
+    Fetch,
+    /// Use existing refs; never suggest contacting origin
+    Offline,
+    /// Like `Fetch`, but only report the fetch (`--fetch --dry-run`)
+    WouldFetch,
+}
+
+/// Checks that `base` resolves to a commit, falling back to `origin/<base>`
+/// (fetched first with `BaseLookup::Fetch`, assumed to be fetchable with
+/// `BaseLookup::WouldFetch`; `quiet` fetches silently).
+fn resolve_base(
+    repo_root: &Path,
+    base: &str,
+    lookup: BaseLookup,
+    quiet: bool,
+) -> Result<ResolvedBase, GhbareError> {
+    if commit_exists(repo_root, base) {
+        return Ok(ResolvedBase::Local);
+    }
+
+    let remote = format!("origin/{}", base);
+    if !commit_exists(repo_root, &remote) && lookup == BaseLookup::WouldFetch {
+        eprintln!("Would fetch {} from origin", remote);
+        return Ok(ResolvedBase::Remote(remote));
+    }
+    if !commit_exists(repo_root, &remote) && lookup == BaseLookup::Fetch {
+        if !quiet {
+            eprintln!("Fetching {} from origin...", base);
+        }
+        let status = git_cmd()
+            .arg("fetch")
+            .args(quiet.then_some("-q"))
+            .args(["origin", base])
+            .current_dir(repo_root)
+            .status()
+            .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+        if !status.success() {
+            return Err(GhbareError::WorktreeError(format!(
+                "git fetch origin {} failed",
+                base
+            )));
+        }
+    }
+    if commit_exists(repo_root, &remote) {
+        return Ok(ResolvedBase::Remote(remote));
+    }
+
+    Err(GhbareError::WorktreeError(format!(
+        "Base '{}' not found locally or as {}.{}",
+        base,
+        remote,
+        match lookup {
+            BaseLookup::Local => " Pass --fetch to fetch it from origin first.",
+            BaseLookup::Fetch | BaseLookup::WouldFetch => "",
+            BaseLookup::Offline => " It has to be fetched, which --offline does not do.",
+        }
+    )))
+}
+
+fn commit_exists(repo_root: &Path, rev: &str) -> bool {
+    git_cmd()
+        .args(["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])
+        .current_dir(repo_root)
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+fn check_existing_branch(
+    branch: &str,
+    is_new_branch: bool,
+    policy: OnExistingBranch,
+) -> Result<(), GhbareError> {
+    if is_new_branch || policy == OnExistingBranch::Reuse {
+        return Ok(());
+    }
+    Err(GhbareError::BranchAlreadyExists(branch.to_string()))
+}
+
+/// Commit SHA checked out in `dir`, so branches can start from local-only commits.
+fn resolve_head(dir: &Path) -> Result<String, GhbareError> {
+    let output = git_cmd()
+        .args(["rev-parse", "--verify", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "Could not resolve HEAD in {} (run bw add from inside a worktree)",
+            dir.display()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Per-worktree git directory of `dir` (e.g., `.bare/worktrees/<name>`).
+fn git_dir(dir: &Path) -> Option<PathBuf> {
+    let output = git_cmd()
+        .args(["rev-parse", "--absolute-git-dir"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Names the rebase/merge/cherry-pick/revert/bisect left unfinished in `git_dir`.
+fn in_progress_operation(git_dir: &Path) -> Option<&'static str> {
+    const MARKERS: [(&str, &str); 7] = [
+        ("rebase-merge", "rebase"),
+        ("rebase-apply", "rebase"),
+        ("MERGE_HEAD", "merge"),
+        ("CHERRY_PICK_HEAD", "cherry-pick"),
+        ("REVERT_HEAD", "revert"),
+        ("BISECT_LOG", "bisect"),
+        ("BISECT_START", "bisect"),
+    ];
+    MARKERS
+        .iter()
+        .find(|(marker, _)| git_dir.join(marker).exists())
+        .map(|(_, operation)| *operation)
+}
+
+/// Commits `message` with HEAD's tree. The index is not used, since it is empty
+/// after `--no-checkout` and committing it would delete every file. An unborn
+/// HEAD (orphan branch) has nothing to lose, so it gets a plain empty commit.
+fn create_empty_commit(worktree_path: &Path, message: &str, quiet: bool) -> Result<(), GhbareError> {
+    if !quiet {
+        eprintln!("Creating empty commit: {}", message);
+    }
+    let Ok(head) = resolve_head(worktree_path) else {
+        let status = git_cmd()
+            .args(["commit", "--allow-empty", "-q", "-m", message])
+            .current_dir(worktree_path)
+            .status()
+            .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+        if !status.success() {
+            return Err(GhbareError::WorktreeError("git commit --allow-empty failed".to_string()));
+        }
+        return Ok(());
+    };
+
+    let output = git_cmd()
+        .args(["commit-tree", &format!("{}^{{tree}}", head), "-p", &head, "-m", message])
+        .current_dir(worktree_path)
+        .output()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "git commit-tree failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let status = git_cmd()
+        .args(["update-ref", "-m", &format!("commit: {}", message), "HEAD", &commit, &head])
+        .current_dir(worktree_path)
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::WorktreeError("git update-ref HEAD failed".to_string()));
+    }
+    Ok(())
+}
+
+fn has_upstream(dir: &Path, branch: &str) -> bool {
+    git_cmd()
+        .args(["rev-parse", "-q", "--verify", &format!("{}@{{upstream}}", branch)])
+        .current_dir(dir)
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+/// Runs `git push -u origin <branch>` in `dir`. Returns false when the push failed.
+fn push_upstream(dir: &Path, branch: &str, quiet: bool) -> bool {
+    if !quiet {
+        eprintln!("Pushing {} to origin...", branch);
+    }
+    git_cmd()
+        .args(["push", "-u"])
+        .args(quiet.then_some("-q"))
+        .args(["origin", branch])
+        .current_dir(dir)
+        .stdout(std::io::stderr())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Stashes uncommitted changes in `dir`. Returns false when there was nothing to stash.
+fn stash_push(dir: &Path, message: &str, quiet: bool) -> Result<bool, GhbareError> {
+    let before = stash_top(dir);
+    if !quiet {
+        eprintln!("Stashing changes in {}", dir.display());
+    }
+    let status = git_cmd()
+        .args(["stash", "push"])
+        .args(quiet.then_some("-q"))
+        .args(["-m", message])
+        .current_dir(dir)
+        .stdout(std::io::stderr())
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::WorktreeError("git stash push failed".to_string()));
+    }
+    let stashed = stash_top(dir) != before;
+    if !stashed && !quiet {
+        eprintln!("No local changes to stash");
+    }
+    Ok(stashed)
+}
+
+fn stash_top(dir: &Path) -> Option<String> {
+    let output = git_cmd()
+        .args(["rev-parse", "-q", "--verify", "refs/stash"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Where git's progress output goes: stderr, or nowhere with `--quiet`.
+fn progress_output(quiet: bool) -> Stdio {
+    if quiet {
+        Stdio::null()
+    } else {
+        std::io::stderr().into()
+    }
+}
+
+/// Pops the latest stash in `dir`. git keeps the stash entry when it fails to apply.
+fn stash_pop(dir: &Path, quiet: bool) -> bool {
+    git_cmd()
+        .args(["stash", "pop"])
+        .args(quiet.then_some("-q"))
+        .current_dir(dir)
+        .stdout(std::io::stderr())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// `stash@{n}` for a bare index, as `git stash` accepts both.
+fn stash_ref(stash: &str) -> String {
+    if !stash.is_empty() && stash.bytes().all(|b| b.is_ascii_digit()) {
+        format!("stash@{{{}}}", stash)
+    } else {
+        stash.to_string()
+    }
+}
+
+/// Commit the stash was made on (its first parent), like `git stash branch`.
+fn stash_base(repo_root: &Path, stash: &str) -> Result<String, GhbareError> {
+    let output = git_cmd()
+        .args(["rev-parse", "-q", "--verify", &format!("{}^1", stash)])
+        .current_dir(repo_root)
+        .output()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "Stash not found: {} (see git stash list)",
+            stash
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Pops `stash` in `dir` restoring its staged changes too. On conflicts git
+/// leaves the conflict markers in `dir` and keeps the stash entry.
+fn stash_pop_ref(dir: &Path, stash: &str, quiet: bool) -> bool {
+    git_cmd()
+        .args(["stash", "pop", "--index"])
+        .args(quiet.then_some("-q"))
+        .arg(stash)
+        .current_dir(dir)
+        .stdout(std::io::stderr())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Runs `commands` in the new worktree with `BW_BRANCH`, `BW_BASE_BRANCH` and
+/// `BW_WORKTREE_PATH` set, e.g. for `git diff "$BW_BASE_BRANCH"...HEAD`. With
+/// `offline`, also `BW_OFFLINE=1` and `GIT_NO_LAZY_FETCH=1`.
+fn run_post_add_commands(
+    commands: &str,
+    working_dir: &Path,
+    branch: &str,
+    base_branch: &str,
+    offline: bool,
+    quiet: bool,
+) -> Result<(), GhbareError> {
+    if commands.trim().is_empty() {
+        return Ok(());
+    }
+    if !quiet {
+        eprintln!("Running post-add commands...");
+    }
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(commands)
+        .current_dir(working_dir)
+        .env("BW_BRANCH", branch)
+        .env("BW_BASE_BRANCH", base_branch)
+        .env("BW_WORKTREE_PATH", working_dir);
+    if offline {
+        cmd.env("BW_OFFLINE", "1").env("GIT_NO_LAZY_FETCH", "1");
+    }
+    let status = cmd
+        .stdout(std::io::stderr())
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(format!("Failed to execute: {}", e)))?;
+    if !status.success() {
+        return Err(GhbareError::WorktreeError(
+            "Post-add commands failed".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_support::init_repo_with_commit;
+
+    #[test]
+    fn test_branch_to_dirname() {
+        assert_eq!(branch_to_dirname("feature/000"), "feature-000");
+        assert_eq!(branch_to_dirname("fix/bug-123"), "fix-bug-123");
+        assert_eq!(branch_to_dirname("main"), "main");
+        assert_eq!(
+            branch_to_dirname("feature/sub/deep"),
+            "feature-sub-deep"
+        );
+    }
+
+    #[test]
+    fn test_add_output_json() {
+        let output = AddOutput {
+            branch: "feature/login".to_string(),
+            dirname: "feature-login".to_string(),
+            path: "/repos/github.com/user/repo/feature-login".to_string(),
+            base: "main".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&output).unwrap(),
+            r#"{"branch":"feature/login","dirname":"feature-login","path":"/repos/github.com/user/repo/feature-login","base":"main"}"#
+        );
+    }
+
+    #[test]
+    fn test_list_worktrees_git2_matches_porcelain() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        let flags = WorktreeAddFlags::default();
+        add_worktree(repo_root, &repo_root.join("main"), "main", "main", &flags).unwrap();
+        add_worktree(repo_root, &repo_root.join("feature-x"), "feature/x", "main", &flags).unwrap();
+        git_cmd()
+            .args(["worktree", "add", "-q", "--detach", "detached", "main"])
+            .current_dir(repo_root)
+            .status()
+            .unwrap();
+
+        let git2_entries = list_worktrees_git2(repo_root).unwrap();
+        assert_eq!(git2_entries.len(), 4);
+        assert_eq!(git2_entries, list_worktrees_porcelain(repo_root).unwrap());
+    }
+
+    #[test]
+    fn test_check_worktree_limit() {
+        assert!(check_worktree_limit(2, 3, false).is_ok());
+        assert!(matches!(
+            check_worktree_limit(3, 3, false),
+            Err(GhbareError::TooManyWorktrees { count: 3, max: 3 })
+        ));
+        assert!(check_worktree_limit(5, 3, true).is_ok());
+    }
+
+    #[test]
+    fn test_load_bw_config_max_worktrees() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(load_bw_config(tmp.path()).unwrap().max_worktrees, None);
+        fs::write(tmp.path().join("bw.toml"), "max_worktrees = 10\n").unwrap();
+        assert_eq!(load_bw_config(tmp.path()).unwrap().max_worktrees, Some(10));
+    }
+
+    #[test]
+    fn test_worktree_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path().join("repo");
+        fs::create_dir(&repo_root).unwrap();
+        init_repo_with_commit(&repo_root);
+        let external = tmp.path().join("review");
+        add_worktree(&repo_root, &external, "feature/x", "main", &Default::default()).unwrap();
+
+        assert_eq!(
+            worktree_target(&repo_root, external.to_str().unwrap()),
+            external.canonicalize().unwrap()
+        );
+        // An existing directory that is not a worktree falls back to the name
+        let other = tmp.path().join("other");
+        fs::create_dir(&other).unwrap();
+        assert_eq!(
+            worktree_target(&repo_root, other.to_str().unwrap()),
+            repo_root.join(branch_to_dirname(other.to_str().unwrap()))
+        );
+        assert_eq!(worktree_target(&repo_root, "feature/y"), repo_root.join("feature-y"));
+    }
+
+    #[test]
+    fn test_check_dirname_collision() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &Default::default()).unwrap();
+        let worktrees = list_worktrees(repo_root).unwrap();
+
+        let err = check_dirname_collision(&worktrees, "feature-x", "feature-x").unwrap_err();
+        assert!(matches!(
+            &err,
+            GhbareError::DirnameCollision { existing, .. } if existing == "feature/x"
+        ));
+        assert!(check_dirname_collision(&worktrees, "feature-x", "feature/x").is_ok());
+        assert!(check_dirname_collision(&worktrees, "other", "other").is_ok());
+    }
+
+    #[test]
+    fn test_list_worktrees_falls_back_for_missing_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &Default::default()).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+
+        assert!(list_worktrees_git2(repo_root).is_err());
+        let entries = list_worktrees(repo_root).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].short_branch(), Some("feature/x"));
+    }
+
+    #[test]
+    fn test_parse_worktree_porcelain() {
+        let text = "worktree /repos/repo/.bare
+bare
+
+worktree /repos/repo/main
+HEAD 1234567890abcdef1234567890abcdef12345678
+branch refs/heads/main
+
+worktree /repos/repo/review
+HEAD abcdef1234567890abcdef1234567890abcdef12
+detached
+";
+        let entries = parse_worktree_porcelain(text);
+        assert_eq!(entries.len(), 3);
+
+        assert!(entries[0].bare);
+        assert_eq!(entries[0].path, PathBuf::from("/repos/repo/.bare"));
+
+        assert_eq!(entries[1].path, PathBuf::from("/repos/repo/main"));
+        assert_eq!(entries[1].short_branch(), Some("main"));
+        assert!(!entries[1].detached);
+
+        assert!(entries[2].detached);
+        assert_eq!(entries[2].branch, None);
+        assert_eq!(entries[1].branch_or_short_head(), "main");
+        assert_eq!(entries[2].branch_or_short_head(), "abcdef1");
+        assert_eq!(
+            entries[2].head.as_deref(),
+            Some("abcdef1234567890abcdef1234567890abcdef12")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_repo_root_through_symlink() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("real").join("repo");
+        fs::create_dir_all(repo.join(".bare")).unwrap();
+        fs::create_dir_all(repo.join("main").join("src")).unwrap();
+
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(tmp.path().join("real"), &link).unwrap();
+
+        let root = find_repo_root_from(&link.join("repo").join("main").join("src")).unwrap();
+        assert_eq!(root, fs::canonicalize(&repo).unwrap());
+        assert!(root.join(".bare").is_dir());
+    }
+
+    #[test]
+    fn test_find_repo_root_from_worktree_subdir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path().join("repo");
+        fs::create_dir(&repo_root).unwrap();
+        init_repo_with_commit(&repo_root);
+
+        let inside = repo_root.join("feature-x");
+        add_worktree(&repo_root, &inside, "feature/x", "main", &Default::default()).unwrap();
+        fs::create_dir_all(inside.join("src/deep")).unwrap();
+        // A worktree outside the root has no `.bare` among its ancestors
+        let outside = tmp.path().join("elsewhere");
+        add_worktree(&repo_root, &outside, "feature/y", "main", &Default::default()).unwrap();
+        fs::create_dir(outside.join("src")).unwrap();
+
+        let expected = fs::canonicalize(&repo_root).unwrap();
+        assert_eq!(find_repo_root_from(&inside.join("src/deep")).unwrap(), expected);
+        assert_eq!(find_repo_root_from(&outside.join("src")).unwrap(), expected);
+        assert_eq!(find_repo_root_from(&repo_root).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_find_repo_root_not_found() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            find_repo_root_from(tmp.path()),
+            Err(GhbareError::RepoRootNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_apply_worktree_git_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let worktree_path = repo_root.join("feature-x");
+        add_worktree(repo_root, &worktree_path, "feature/x", "main", &Default::default()).unwrap();
+
+        let mut entries = BTreeMap::new();
+        entries.insert("user.email".to_string(), "me@work.example".to_string());
+        apply_worktree_git_config(&worktree_path, &entries, false).unwrap();
+
+        let output = git_cmd()
+            .args(["config", "--get", "user.email"])
+            .current_dir(&worktree_path)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "me@work.example");
+    }
+
+    #[test]
+    fn test_age_in_days() {
+        let now = 1_700_000_000;
+        assert_eq!(age_in_days(now, now), 0);
+        assert_eq!(age_in_days(now - SECONDS_PER_DAY + 1, now), 0);
+        assert_eq!(age_in_days(now - 31 * SECONDS_PER_DAY, now), 31);
+        assert_eq!(age_in_days(now + 10, now), 0);
+    }
+
+    #[test]
+    fn test_last_commit_time() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let main_path = repo_root.join("main");
+        add_worktree(repo_root, &main_path, "main", "main", &Default::default()).unwrap();
+
+        let time = last_commit_time(&main_path).unwrap();
+        assert_eq!(age_in_days(time, crate::index::now_unix()), 0);
+        assert_eq!(last_commit_time(&repo_root.join("missing")), None);
+    }
+
+    #[test]
+    fn test_list_stale_remove() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        for branch in ["main", "a", "b"] {
+            let base = if branch == "main" { "main" } else { "HEAD" };
+            add_worktree(repo_root, &repo_root.join(branch), branch, base, &Default::default())
+                .unwrap();
+        }
+        super::super::labels::set(repo_root, "a", Some("old")).unwrap();
+        // An untracked file makes git worktree remove refuse b
+        fs::write(repo_root.join("b/scratch.txt"), "x").unwrap();
+
+        let worktrees = list_worktrees(repo_root).unwrap();
+        let err = list_stale(repo_root, &worktrees, 0, true, true).unwrap_err();
+
+        assert!(err.to_string().contains("Could not remove 1 worktree(s)"), "{}", err);
+        assert!(!repo_root.join("a").exists());
+        assert!(repo_root.join("b").exists());
+        // The default branch's worktree is kept
+        assert!(repo_root.join("main").exists());
+        assert!(super::super::labels::load(repo_root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stale_removal_blocker() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().canonicalize().unwrap();
+        let worktree = WorktreeEntry {
+            path: path.clone(),
+            branch: Some("refs/heads/feature".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(stale_removal_blocker(&worktree, Some("refs/heads/main"), None), None);
+        assert_eq!(
+            stale_removal_blocker(&worktree, Some("refs/heads/feature"), None),
+            Some("default branch")
+        );
+        assert_eq!(
+            stale_removal_blocker(&worktree, None, Some(&path.join("src"))),
+            Some("current directory")
+        );
+        assert_eq!(stale_removal_blocker(&worktree, None, Some(Path::new("/"))), None);
+    }
+
+    #[test]
+    fn test_add_worktree_no_checkout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let main_path = repo_root.join("main");
+        add_worktree(repo_root, &main_path, "main", "main", &Default::default()).unwrap();
+        fs::write(main_path.join("README.md"), "hello").unwrap();
+        git_cmd().args(["add", "README.md"]).current_dir(&main_path).status().unwrap();
+        git_cmd()
+            .args(["-c", "user.name=bw", "-c", "user.email=bw@example.com"])
+            .args(["commit", "-q", "-m", "readme"])
+            .current_dir(&main_path)
+            .status()
+            .unwrap();
+
+        let flags = WorktreeAddFlags {
+            no_checkout: true,
+            ..Default::default()
+        };
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &flags).unwrap();
+        assert!(path.join(".git").exists());
+        assert!(!path.join("README.md").exists());
+    }
+
+    #[test]
+    fn test_apply_sparse_checkout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let main_path = repo_root.join("main");
+        add_worktree(repo_root, &main_path, "main", "main", &Default::default()).unwrap();
+        for dir in ["apps/web", "apps/api"] {
+            fs::create_dir_all(main_path.join(dir)).unwrap();
+            fs::write(main_path.join(dir).join("index.txt"), dir).unwrap();
+        }
+        git_cmd().args(["add", "."]).current_dir(&main_path).status().unwrap();
+        git_cmd()
+            .args(["-c", "user.name=bw", "-c", "user.email=bw@example.com"])
+            .args(["commit", "-q", "-m", "apps"])
+            .current_dir(&main_path)
+            .status()
+            .unwrap();
+
+        let flags = WorktreeAddFlags {
+            no_checkout: true,
+            ..Default::default()
+        };
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &flags).unwrap();
+        apply_sparse_checkout(&path, &["apps/web".to_string()], true, false).unwrap();
+
+        assert!(path.join("apps/web/index.txt").exists());
+        assert!(!path.join("apps/api").exists());
+    }
+
+    #[test]
+    fn test_load_bw_config_default_sparse_patterns() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("bw.toml"),
+            "default_sparse_patterns = [\"apps/web\", \"libs\"]\n",
+        )
+        .unwrap();
+        let config = load_bw_config(tmp.path()).unwrap();
+        assert_eq!(config.default_sparse_patterns, vec!["apps/web", "libs"]);
+    }
+
+    #[test]
+    fn test_branch_from_detached_head() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let detached = repo_root.join("detached");
+        git_cmd()
+            .args(["worktree", "add", "--detach", detached.to_str().unwrap(), "main"])
+            .current_dir(repo_root)
+            .output()
+            .unwrap();
+        git_cmd()
+            .args(["-c", "user.name=bw", "-c", "user.email=bw@example.com"])
+            .args(["commit", "--allow-empty", "-q", "-m", "local only"])
+            .current_dir(&detached)
+            .status()
+            .unwrap();
+
+        let head = resolve_head(&detached).unwrap();
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", &head, &Default::default()).unwrap();
+        assert_eq!(resolve_head(&path).unwrap(), head);
+
+        assert!(resolve_head(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn test_in_progress_operation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let main_path = repo_root.join("main");
+        add_worktree(repo_root, &main_path, "main", "main", &Default::default()).unwrap();
+        let dir = git_dir(&main_path).unwrap();
+        assert_eq!(dir, repo_root.join(".bare/worktrees/main").canonicalize().unwrap());
+        assert_eq!(in_progress_operation(&dir), None);
+
+        fs::create_dir(dir.join("rebase-merge")).unwrap();
+        assert_eq!(in_progress_operation(&dir), Some("rebase"));
+        fs::remove_dir(dir.join("rebase-merge")).unwrap();
+
+        fs::write(dir.join("BISECT_LOG"), "").unwrap();
+        assert_eq!(in_progress_operation(&dir), Some("bisect"));
+    }
+
+    #[test]
+    fn test_count_repairs() {
+        let report = "repair: gitdir incorrect: /r/.bare/worktrees/main/gitdir\n\
+                      error: not a valid path: /gone\n\
+                      repair: .git file broken: /r/feature-x\n";
+        assert_eq!(count_repairs(report), 2);
+        assert_eq!(count_repairs(""), 0);
+    }
+
+    #[test]
+    fn test_remote_branch_exists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        git_cmd()
+            .args(["update-ref", "refs/remotes/origin/feature/x", "main"])
+            .current_dir(repo_root)
+            .status()
+            .unwrap();
+
+        assert!(remote_branch_exists(repo_root, "feature/x"));
+        assert!(!remote_branch_exists(repo_root, "main"));
+        assert!(!remote_branch_exists(repo_root, "feature"));
+    }
+
+    #[test]
+    fn test_resolve_base() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        git_cmd()
+            .args(["update-ref", "refs/remotes/origin/develop", "main"])
+            .current_dir(repo_root)
+            .status()
+            .unwrap();
+
+        // local-only
+        assert_eq!(resolve_base(repo_root, "main", BaseLookup::Local, false).unwrap(), ResolvedBase::Local);
+        // remote-only
+        assert_eq!(
+            resolve_base(repo_root, "develop", BaseLookup::Local, false).unwrap(),
+            ResolvedBase::Remote("origin/develop".to_string())
+        );
+        // nowhere
+        let err = resolve_base(repo_root, "release", BaseLookup::Local, false).unwrap_err();
+        assert!(err.to_string().contains("--fetch"));
+
+        // offline: remote-tracking refs still count, but never suggest fetching
+        assert_eq!(
+            resolve_base(repo_root, "develop", BaseLookup::Offline, false).unwrap(),
+            ResolvedBase::Remote("origin/develop".to_string())
+        );
+        let err = resolve_base(repo_root, "release", BaseLookup::Offline, false).unwrap_err();
+        assert!(err.to_string().contains("--offline"));
+        assert!(!err.to_string().contains("Pass --fetch"));
+
+        // --fetch --dry-run: no origin is configured, so a real fetch would fail
+        assert_eq!(
+            resolve_base(repo_root, "release", BaseLookup::WouldFetch, false).unwrap(),
+            ResolvedBase::Remote("origin/release".to_string())
+        );
+        assert_eq!(
+            resolve_base(repo_root, "main", BaseLookup::WouldFetch, false).unwrap(),
+            ResolvedBase::Local
+        );
+    }
+
+    #[test]
+    fn test_add_worktree_tracks_remote_base() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        git_cmd()
+            .args(["update-ref", "refs/remotes/origin/develop", "main"])
+            .current_dir(repo_root)
+            .status()
+            .unwrap();
+
+        for (key, value) in [
+            ("remote.origin.url", "https://example.com/user/repo.git"),
+            ("remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"),
+        ] {
+            git_cmd().args(["config", key, value]).current_dir(repo_root).status().unwrap();
+        }
+
+        let flags = WorktreeAddFlags {
+            track: true,
+            ..Default::default()
+        };
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "origin/develop", &flags).unwrap();
+
+        let output = git_cmd()
+            .args(["rev-parse", "--abbrev-ref", "feature/x@{upstream}"])
+            .current_dir(&path)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "origin/develop");
+    }
+
+    #[test]
+    fn test_push_upstream() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path().join("repo");
+        std::fs::create_dir(&repo_root).unwrap();
+        init_repo_with_commit(&repo_root);
+        let remote = tmp.path().join("remote.git");
+        git_cmd()
+            .args(["init", "-q", "--bare"])
+            .arg(&remote)
+            .status()
+            .unwrap();
+        git_cmd()
+            .args(["remote", "add", "origin"])
+            .arg(&remote)
+            .current_dir(&repo_root)
+            .status()
+            .unwrap();
+
+        let path = repo_root.join("feature-x");
+        add_worktree(&repo_root, &path, "feature/x", "main", &WorktreeAddFlags::default()).unwrap();
+        assert!(!has_upstream(&path, "feature/x"));
+
+        assert!(push_upstream(&path, "feature/x", false));
+        assert!(has_upstream(&path, "feature/x"));
+        assert!(branch_exists(&remote, "feature/x"));
+    }
+
+    #[test]
+    fn test_push_upstream_without_remote_fails() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &WorktreeAddFlags::default()).unwrap();
+
+        assert!(!push_upstream(&path, "feature/x", false));
+    }
+
+    #[test]
+    fn test_should_delete_branch_precedence() {
+        let mut config = BwConfig::default();
+        assert!(!should_delete_branch(None, &config));
+        assert!(should_delete_branch(Some(true), &config));
+
+        config.remove_deletes_branch = true;
+        assert!(should_delete_branch(None, &config));
+        // --keep-branch overrides remove_deletes_branch
+        assert!(!should_delete_branch(Some(false), &config));
+    }
+
+    #[test]
+    fn test_load_bw_config_remove_deletes_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(!load_bw_config(tmp.path()).unwrap().remove_deletes_branch);
+
+        fs::write(tmp.path().join("bw.toml"), "remove_deletes_branch = true\n").unwrap();
+        assert!(load_bw_config(tmp.path()).unwrap().remove_deletes_branch);
+    }
+
+    #[test]
+    fn test_remove_worktree_and_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &WorktreeAddFlags::default()).unwrap();
+
+        let branch = worktree_branch(repo_root, &path);
+        assert_eq!(branch.as_deref(), Some("feature/x"));
+        remove_worktree(repo_root, &path, false).unwrap();
+        delete_local_branch(repo_root, "feature/x", false).unwrap();
+        assert!(!branch_exists(repo_root, "feature/x"));
+    }
+
+    #[test]
+    fn test_delete_unmerged_branch_needs_force() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &WorktreeAddFlags::default()).unwrap();
+        git_cmd()
+            .args(["-c", "user.name=bw", "-c", "user.email=bw@example.com"])
+            .args(["commit", "-q", "--allow-empty", "-m", "unmerged"])
+            .current_dir(&path)
+            .status()
+            .unwrap();
+        remove_worktree(repo_root, &path, false).unwrap();
+
+        let err = delete_local_branch(repo_root, "feature/x", false).unwrap_err();
+        assert!(err.to_string().contains("--force-branch"));
+        assert!(branch_exists(repo_root, "feature/x"));
+        delete_local_branch(repo_root, "feature/x", true).unwrap();
+        assert!(!branch_exists(repo_root, "feature/x"));
+    }
+
+    #[test]
+    fn test_check_existing_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let exists = branch_exists(repo_root, "main");
+        assert!(exists);
+        assert!(matches!(
+            check_existing_branch("main", !exists, OnExistingBranch::Fail),
+            Err(GhbareError::BranchAlreadyExists(_))
+        ));
+        assert!(check_existing_branch("main", !exists, OnExistingBranch::Reuse).is_ok());
+
+        let exists = branch_exists(repo_root, "feature/new");
+        assert!(!exists);
+        assert!(check_existing_branch("feature/new", !exists, OnExistingBranch::Fail).is_ok());
+    }
+
+    #[test]
+    fn test_load_bw_config_on_existing_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(load_bw_config(tmp.path()).unwrap().on_existing_branch, OnExistingBranch::Fail);
+
+        fs::write(tmp.path().join("bw.toml"), "on_existing_branch = \"reuse\"\n").unwrap();
+        assert_eq!(load_bw_config(tmp.path()).unwrap().on_existing_branch, OnExistingBranch::Reuse);
+
+        fs::write(tmp.path().join("bw.toml"), "on_existing_branch = \"maybe\"\n").unwrap();
+        assert!(load_bw_config(tmp.path()).is_err());
+    }
+
+    #[test]
+    fn test_create_empty_commit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let path = repo_root.join("feature-x");
+        add_worktree(repo_root, &path, "feature/x", "main", &Default::default()).unwrap();
+        for (key, value) in [("user.name", "bw"), ("user.email", "bw@example.com")] {
+            git_cmd().args(["config", key, value]).current_dir(&path).status().unwrap();
+        }
+        create_empty_commit(&path, "start feature/x", false).unwrap();
+
+        let output = git_cmd()
+            .args(["log", "--format=%s", "main..feature/x"])
+            .current_dir(&path)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "start feature/x");
+
+        // Without a checkout the index is empty, but the commit keeps every file
+        let flags = WorktreeAddFlags {
+            no_checkout: true,
+            ..Default::default()
+        };
+        let path = repo_root.join("feature-y");
+        add_worktree(repo_root, &path, "feature/y", "main", &flags).unwrap();
+        for (key, value) in [("user.name", "bw"), ("user.email", "bw@example.com")] {
+            git_cmd().args(["config", key, value]).current_dir(&path).status().unwrap();
+        }
+        create_empty_commit(&path, "start feature/y", false).unwrap();
+        let output = git_cmd()
+            .args(["diff", "--name-only", "main", "feature/y"])
+            .current_dir(&path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    }
+
+    #[test]
+    fn test_prune_worktrees_if_needed_dry_run() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        let path = repo_root.join("stale");
+        add_worktree(repo_root, &path, "stale", "main", &Default::default()).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        let admin = repo_root.join(".bare/worktrees/stale");
+
+        prune_worktrees_if_needed(repo_root, true, true);
+        assert!(admin.exists());
+        prune_worktrees_if_needed(repo_root, true, false);
+        assert!(!admin.exists());
+    }
+
+    #[test]
+    fn test_stash_moves_changes_to_new_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let main_path = repo_root.join("main");
+        add_worktree(repo_root, &main_path, "main", "main", &Default::default()).unwrap();
+        for (key, value) in [("user.name", "bw"), ("user.email", "bw@example.com")] {
+            git_cmd().args(["config", key, value]).current_dir(&main_path).status().unwrap();
+        }
+
+        assert!(!stash_push(&main_path, "nothing", false).unwrap());
+
+        fs::write(main_path.join("wip.txt"), "wip").unwrap();
+        git_cmd().args(["add", "wip.txt"]).current_dir(&main_path).status().unwrap();
+        assert!(stash_push(&main_path, "bw add --stash: feature/x", false).unwrap());
+        assert!(!main_path.join("wip.txt").exists());
+
+        let new_path = repo_root.join("feature-x");
+        add_worktree(repo_root, &new_path, "feature/x", "main", &Default::default()).unwrap();
+        assert!(stash_pop(&new_path, false));
+        assert_eq!(fs::read_to_string(new_path.join("wip.txt")).unwrap(), "wip");
+        assert_eq!(stash_top(&new_path), None);
+    }
+
+    #[test]
+    fn test_from_stash_branches_from_stash_base() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let main_path = repo_root.join("main");
+        add_worktree(repo_root, &main_path, "main", "main", &Default::default()).unwrap();
+        for (key, value) in [("user.name", "bw"), ("user.email", "bw@example.com")] {
+            git_cmd().args(["config", key, value]).current_dir(&main_path).status().unwrap();
+        }
+        let base = resolve_head(&main_path).unwrap();
+
+        fs::write(main_path.join("staged.txt"), "staged").unwrap();
+        git_cmd().args(["add", "staged.txt"]).current_dir(&main_path).status().unwrap();
+        assert!(stash_push(&main_path, "forgotten", false).unwrap());
+        git_cmd()
+            .args(["commit", "--allow-empty", "-m", "moved on"])
+            .current_dir(&main_path)
+            .status()
+            .unwrap();
+
+        assert_eq!(stash_ref("0"), "stash@{0}");
+        assert_eq!(stash_base(repo_root, "stash@{0}").unwrap(), base);
+        assert!(stash_base(repo_root, "stash@{1}").is_err());
+
+        let new_path = repo_root.join("rescued");
+        add_worktree(repo_root, &new_path, "rescued", &base, &Default::default()).unwrap();
+        assert!(stash_pop_ref(&new_path, "stash@{0}", false));
+        let staged = git_cmd()
+            .args(["diff", "--cached", "--name-only"])
+            .current_dir(&new_path)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&staged.stdout), "staged.txt\n");
+        assert_eq!(stash_top(&new_path), None);
+    }
+
+    #[test]
+    fn test_load_bw_config_worktree_git_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("bw.toml"),
+            "[worktree_git_config]\n\"user.email\" = \"me@work.example\"\n",
+        )
+        .unwrap();
+        let config = load_bw_config(tmp.path()).unwrap();
+        assert_eq!(
+            config.worktree_git_config.get("user.email").map(String::as_str),
+            Some("me@work.example")
+        );
+    }
+
+    #[test]
+    fn test_validate_expire() {
+        assert!(validate_expire("2.weeks.ago").is_ok());
+        assert!(validate_expire("now").is_ok());
+        assert!(validate_expire("").is_err());
+        assert!(validate_expire("--verbose").is_err());
+    }
+
+    #[test]
+    fn test_copy_ignored_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        fs::create_dir_all(repo_root.join(".bare").join("info")).unwrap();
+        fs::write(repo_root.join(".bare").join("info").join("exclude"), ".env\n").unwrap();
+
+        let main_path = repo_root.join("main");
+        add_worktree(repo_root, &main_path, "main", "main", &Default::default()).unwrap();
+        fs::write(main_path.join(".env"), "SECRET=1\n").unwrap();
+        fs::write(main_path.join("notes.txt"), "untracked\n").unwrap();
+        fs::create_dir_all(main_path.join("node_modules/pkg")).unwrap();
+        fs::write(main_path.join(".gitignore"), "node_modules/\n").unwrap();
+        fs::write(main_path.join("node_modules/pkg/.env"), "PKG=1\n").unwrap();
+        fs::write(main_path.join("node_modules/pkg/.env.example"), "PKG=\n").unwrap();
+
+        let feature_path = repo_root.join("feature-x");
+        add_worktree(repo_root, &feature_path, "feature/x", "main", &Default::default()).unwrap();
+
+        let worktrees = list_worktrees(repo_root).unwrap();
+        let source = find_source_worktree(&worktrees, "main", &feature_path).unwrap();
+        assert_eq!(
+            fs::canonicalize(source).unwrap(),
+            fs::canonicalize(&main_path).unwrap()
+        );
+
+        copy_ignored_files(source, &feature_path, &[ENV_FILE_PATTERN.to_string()], false).unwrap();
+        assert_eq!(
+            fs::read_to_string(feature_path.join(".env")).unwrap(),
+            "SECRET=1\n"
+        );
+        assert!(!feature_path.join("notes.txt").exists());
+        assert!(!feature_path.join("node_modules").exists());
+
+        // Nested files only with an explicit "**/"
+        copy_ignored_files(source, &feature_path, &["**/.env".to_string()], false).unwrap();
+        assert!(feature_path.join("node_modules/pkg/.env").exists());
+        assert!(!feature_path.join("node_modules/pkg/.env.example").exists());
+    }
+
+    #[test]
+    fn test_branch_to_dirname_long_branch() {
+        let branch = format!("feature/{}", "a".repeat(300));
+        let dirname = branch_to_dirname(&branch);
+        assert_eq!(dirname.len(), MAX_DIRNAME_LEN);
+        assert!(dirname.starts_with("feature-aaaa"));
+        assert_eq!(dirname, branch_to_dirname(&branch));
+
+        let other = format!("feature/{}b", "a".repeat(300));
+        assert_ne!(dirname, branch_to_dirname(&other));
+    }
+
+    #[test]
+    fn test_branch_to_dirname_long_multibyte_branch() {
+        let branch = format!("feature/{}", "あ".repeat(100));
+        let dirname = branch_to_dirname(&branch);
+        assert!(dirname.len() <= MAX_DIRNAME_LEN);
+        assert!(dirname.starts_with("feature-あ"));
+    }
+
+    #[test]
+    fn test_format_paths_null_terminated() {
+        let paths = vec![PathBuf::from("/repos/repo/main"), PathBuf::from("/repos/repo/feature-x")];
+        assert_eq!(
+            format_paths(&paths, true),
+            "/repos/repo/main\0/repos/repo/feature-x\0"
+        );
+        assert_eq!(
+            format_paths(&paths, false),
+            "/repos/repo/main\n/repos/repo/feature-x\n"
+        );
+        assert_eq!(format_paths(&[], true), "");
+    }
+
+    #[test]
+    fn test_fzf_args_preview() {
+        let picker = PickerOptions {
+            preview: Some(DEFAULT_PREVIEW_COMMAND.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            fzf_args(&picker),
+            [
+                "--delimiter",
+                "\t",
+                "--ansi",
+                "--preview",
+                "git -C {1} -c color.ui=always diff --stat"
+            ]
+        );
+        assert_eq!(fzf_args(&PickerOptions::default()), ["--delimiter", "\t"]);
+    }
+
+    #[test]
+    fn test_fzf_args_layout() {
+        let picker = PickerOptions {
+            multi: true,
+            height: Some("40%".to_string()),
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fzf_args(&picker),
+            ["--delimiter", "\t", "--multi", "--height=40%", "--layout=reverse"]
+        );
+    }
+
+    #[test]
+    fn test_run_post_add_commands_env() {
+        let tmp = tempfile::tempdir().unwrap();
+        run_post_add_commands(
+            "echo \"$BW_BRANCH $BW_BASE_BRANCH $BW_WORKTREE_PATH\" > env.txt",
+            tmp.path(),
+            "feature/x",
+            "origin/develop",
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("env.txt")).unwrap().trim(),
+            format!("feature/x origin/develop {}", tmp.path().display())
+        );
+
+        run_post_add_commands(
+            "echo \"$BW_OFFLINE $GIT_NO_LAZY_FETCH\" > env.txt",
+            tmp.path(),
+            "feature/x",
+            "origin/develop",
+            true,
+            false,
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(tmp.path().join("env.txt")).unwrap().trim(), "1 1");
+    }
+
+    #[test]
+    fn test_count_worktrees() {
+        let output = "worktree /repo/.bare\nbare\n\n\
+                      worktree /repo/main\nHEAD abc\nbranch refs/heads/main\n\n\
+                      worktree /repo/fix\nHEAD def\ndetached\n";
+        assert_eq!(count_worktrees(&parse_worktree_porcelain(output)), 2);
+        assert_eq!(count_worktrees(&[]), 0);
+    }
+
+    #[test]
+    fn test_branch_filter() {
+        let glob = BranchFilter::new("feature/*", false).unwrap();
+        assert!(glob.matches("feature/login"));
+        assert!(!glob.matches("fix/login"));
+
+        let regex = BranchFilter::new("^(feature|fix)/log", true).unwrap();
+        assert!(regex.matches("fix/login"));
+        assert!(!regex.matches("main"));
+
+        assert!(BranchFilter::new("feature/[", false).is_err());
+        assert!(BranchFilter::new("(", true).is_err());
+    }
+
+    #[test]
+    fn test_all_repos_output() {
+        let main = WorktreeEntry {
+            path: PathBuf::from("/root/github.com/a/x/main"),
+            branch: Some("refs/heads/main".to_string()),
+            head: Some("abc123".to_string()),
+            ..Default::default()
+        };
+        let bare = WorktreeEntry {
+            path: PathBuf::from("/root/github.com/a/y/.bare"),
+            bare: true,
+            ..Default::default()
+        };
+        let repos = vec![
+            (PathBuf::from("github.com/a/x"), vec![main]),
+            (PathBuf::from("github.com/a/y"), vec![bare]),
+        ];
+
+        assert_eq!(
+            format_all_repos(&repos, false),
+            "github.com/a/x\n  /root/github.com/a/x/main\tmain\ngithub.com/a/y\n"
+        );
+        assert_eq!(
+            format_all_repos(&repos, true),
+            "github.com/a/x\n  /root/github.com/a/x/main\tmain\tabc123\ngithub.com/a/y\n"
+        );
+        assert_eq!(
+            all_repos_json(Path::new("/root"), &repos, false, false).unwrap(),
+            r#"{"version":1,"repos":[{"path":"/root/github.com/a/x","worktrees":[{"path":"/root/github.com/a/x/main","branch":"main","head":"abc123","detached":false}]},{"path":"/root/github.com/a/y","worktrees":[]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_worktrees_json() {
+        let worktrees = vec![
+            WorktreeEntry {
+                path: PathBuf::from("/repo/.bare"),
+                bare: true,
+                ..Default::default()
+            },
+            WorktreeEntry {
+                path: PathBuf::from("/repo/main"),
+                head: Some("abc123".to_string()),
+                branch: Some("refs/heads/main".to_string()),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            worktrees_json(&worktrees, false, false).unwrap(),
+            r#"{"version":1,"worktrees":[{"path":"/repo/main","branch":"main","head":"abc123","detached":false}]}"#
+        );
+        assert_eq!(
+            worktrees_json(&worktrees, true, false).unwrap(),
+            "{\n  \"version\": 1,\n  \"worktrees\": [\n    {\n      \"path\": \"/repo/main\",\n      \"branch\": \"main\",\n      \"head\": \"abc123\",\n      \"detached\": false\n    }\n  ]\n}"
+        );
+
+        let mut labeled = worktrees.clone();
+        labeled[1].label = Some("review PR 123".to_string());
+        assert!(worktrees_json(&labeled, false, false)
+            .unwrap()
+            .ends_with(r#""detached":false,"label":"review PR 123"}]}"#));
+    }
+
+    #[test]
+    fn test_show_head() {
+        let detached = WorktreeEntry {
+            path: PathBuf::from("/repo/review"),
+            head: Some("0123456789abcdef".to_string()),
+            detached: true,
+            label: Some("PR 12".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(worktree_line(&detached, false), "/repo/review\t(detached)\tPR 12");
+        assert_eq!(
+            worktree_line(&detached, true),
+            "/repo/review\t(detached)\t01234567\tPR 12"
+        );
+        let worktrees = vec![detached];
+        assert!(worktrees_json(&worktrees, false, true)
+            .unwrap()
+            .contains(r#""head":"0123456789abcdef","head_short":"01234567","detached":true"#));
+        assert!(!worktrees_json(&worktrees, false, false).unwrap().contains("head_short"));
+    }
+
+    #[test]
+    fn test_base_candidates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+        for (name, target) in [
+            ("refs/heads/develop", "main"),
+            ("refs/remotes/origin/release", "main"),
+        ] {
+            git_cmd().args(["update-ref", name, target]).current_dir(repo_root).status().unwrap();
+        }
+        git_cmd()
+            .args(["symbolic-ref", "refs/remotes/origin/HEAD", "refs/remotes/origin/release"])
+            .current_dir(repo_root)
+            .status()
+            .unwrap();
+
+        assert_eq!(base_candidates(repo_root), vec!["develop", "main", "origin/release"]);
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        let output = "/repos/repo/main\tmain\n/repos/repo/feature-x\tfeature/x\n";
+        assert_eq!(
+            parse_selection(output),
+            vec![PathBuf::from("/repos/repo/main"), PathBuf::from("/repos/repo/feature-x")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_worktree_no_verify_skips_hooks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = tmp.path();
+        init_repo_with_commit(repo_root);
+
+        let hooks_dir = repo_root.join(".bare").join("hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        let hook = hooks_dir.join("post-checkout");
+        fs::write(&hook, "#!/bin/sh\ntouch \"$(pwd)/hook-ran\"\n").unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let flags = WorktreeAddFlags {
+            no_verify: true,
+            ..Default::default()
+        };
+        let quiet_path = repo_root.join("quiet");
+        add_worktree(repo_root, &quiet_path, "quiet", "main", &flags).unwrap();
+        assert!(!quiet_path.join("hook-ran").exists());
+
+        let hooked_path = repo_root.join("hooked");
+        add_worktree(repo_root, &hooked_path, "hooked", "main", &Default::default()).unwrap();
+        assert!(hooked_path.join("hook-ran").exists());
+    }
+
+    #[test]
+    fn test_base_rules() {
+        let config: BwConfig = toml::from_str(
+            r#"
+base_branch = "main"
+
+[[base_rules]]
+prefix = "hotfix/"
+base = "main"
+
+[[base_rules]]
+prefix = "feature/"
+base = "develop"
+
+[[base_rules]]
+prefix = "feature/legacy-"
+base = "legacy"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.base_for("hotfix/crash"), "main");
+        assert_eq!(config.base_for("feature/login"), "develop");
+        // first match wins even when a later rule is more specific
+        assert_eq!(config.base_for("feature/legacy-api"), "develop");
+        assert_eq!(config.base_for("chore/deps"), "main");
+    }
+
+    #[test]
+    fn test_base_rules_fallthrough_to_default() {
+        let config = BwConfig::default();
+        assert_eq!(config.base_for("feature/login"), "main");
+    }
+
+    #[test]
+    fn test_find_bare_repos() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("github.com/alice/one/.bare")).unwrap();
+        fs::create_dir_all(root.join("github.com/alice/one/main/.bare")).unwrap();
+        fs::create_dir_all(root.join("github.com/bob/two.work/.bare")).unwrap();
+        fs::create_dir_all(root.join("github.com/bob/not-a-repo")).unwrap();
+
+        assert_eq!(
+            find_bare_repos(root).unwrap(),
+            vec![
+                root.join("github.com/alice/one"),
+                root.join("github.com/bob/two.work"),
+            ]
         );
     }
 
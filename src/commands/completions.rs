@@ -0,0 +1,192 @@
+use crate::error::GhbareError;
+use clap_complete::Shell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prints the completion script for `shell` (`bw completions zsh > _bw`).
+pub fn execute(shell: &str, mut cmd: clap::Command) -> anyhow::Result<()> {
+    print!("{}", script(parse_shell(shell)?, &mut cmd));
+    Ok(())
+}
+
+/// `bw completions install [shell] [--dir <path>]`: writes the script where the
+/// shell looks for completions and prints the rc-file line when one is needed.
+pub fn execute_install(
+    shell: Option<&str>,
+    dir: Option<&Path>,
+    mut cmd: clap::Command,
+) -> anyhow::Result<()> {
+    let shell = match shell {
+        Some(shell) => parse_shell(shell)?,
+        None => detect_shell()?,
+    };
+    let dirs = Dirs::from_env()?;
+    let dir = match dir {
+        Some(dir) => std::path::absolute(dir)?,
+        None => default_dir(shell, &dirs),
+    };
+    let path = dir.join(file_name(shell));
+
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, script(shell, &mut cmd))?;
+    println!("Installed {} completions: {}", shell, path.display());
+
+    if let Some(line) = rc_line(shell, &dir, &dirs) {
+        println!(
+            "Add this to {} (then restart the shell):\n  {}",
+            rc_file(shell),
+            line
+        );
+    }
+    Ok(())
+}
+
+fn script(shell: Shell, cmd: &mut clap::Command) -> String {
+    let mut buf = Vec::new();
+    clap_complete::generate(shell, cmd, "bw", &mut buf);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+fn parse_shell(shell: &str) -> Result<Shell, GhbareError> {
+    match shell {
+        "bash" => Ok(Shell::Bash),
+        "zsh" => Ok(Shell::Zsh),
+        "fish" => Ok(Shell::Fish),
+        other => Err(GhbareError::ConfigParseError(format!(
+            "Unsupported shell: {} (expected bash, zsh or fish)",
+            other
+        ))),
+    }
+}
+
+/// The shell named by the last component of `$SHELL`.
+fn detect_shell() -> Result<Shell, GhbareError> {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let name = Path::new(&shell)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    parse_shell(&name).map_err(|_| {
+        GhbareError::ConfigParseError(format!(
+            "Cannot detect a supported shell from $SHELL ('{}'); pass bash, zsh or fish",
+            shell
+        ))
+    })
+}
+
+/// Home and XDG base directories the default locations derive from.
+struct Dirs {
+    home: PathBuf,
+    data: PathBuf,
+    config: PathBuf,
+}
+
+impl Dirs {
+    fn from_env() -> Result<Self, GhbareError> {
+        let home = dirs::home_dir().ok_or(GhbareError::ConfigNotFound(
+            "Could not determine home directory".to_string(),
+        ))?;
+        let xdg = |var: &str, default: &str| {
+            std::env::var_os(var)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(default))
+        };
+        Ok(Self {
+            data: xdg("XDG_DATA_HOME", ".local/share"),
+            config: xdg("XDG_CONFIG_HOME", ".config"),
+            home,
+        })
+    }
+}
+
+/// Where each shell picks up completions without extra setup, except zsh,
+/// whose directory has to be added to `fpath`.
+fn default_dir(shell: Shell, dirs: &Dirs) -> PathBuf {
+    match shell {
+        Shell::Zsh => dirs.home.join(".zsh/completions"),
+        Shell::Fish => dirs.config.join("fish/completions"),
+        _ => dirs.data.join("bash-completion/completions"),
+    }
+}
+
+fn file_name(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Zsh => "_bw",
+        Shell::Fish => "bw.fish",
+        _ => "bw",
+    }
+}
+
+fn rc_file(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Zsh => "~/.zshrc, before compinit",
+        Shell::Fish => "~/.config/fish/config.fish",
+        _ => "~/.bashrc",
+    }
+}
+
+/// Line that makes the shell find `dir`; `None` when it already looks there.
+fn rc_line(shell: Shell, dir: &Path, dirs: &Dirs) -> Option<String> {
+    if shell != Shell::Zsh && dir == default_dir(shell, dirs) {
+        return None;
+    }
+    Some(match shell {
+        Shell::Zsh => format!("fpath=({} $fpath)", dir.display()),
+        Shell::Fish => format!("set -p fish_complete_path {}", dir.display()),
+        _ => format!("source {}", dir.join(file_name(shell)).display()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dirs() -> Dirs {
+        Dirs {
+            home: PathBuf::from("/home/u"),
+            data: PathBuf::from("/home/u/.local/share"),
+            config: PathBuf::from("/home/u/.config"),
+        }
+    }
+
+    #[test]
+    fn test_default_locations() {
+        let dirs = dirs();
+        let path = |shell| default_dir(shell, &dirs).join(file_name(shell));
+        assert_eq!(path(Shell::Zsh), Path::new("/home/u/.zsh/completions/_bw"));
+        assert_eq!(path(Shell::Fish), Path::new("/home/u/.config/fish/completions/bw.fish"));
+        assert_eq!(
+            path(Shell::Bash),
+            Path::new("/home/u/.local/share/bash-completion/completions/bw")
+        );
+    }
+
+    #[test]
+    fn test_rc_line() {
+        let dirs = dirs();
+        let default = |shell| default_dir(shell, &dirs);
+        assert_eq!(
+            rc_line(Shell::Zsh, &default(Shell::Zsh), &dirs).unwrap(),
+            "fpath=(/home/u/.zsh/completions $fpath)"
+        );
+        assert_eq!(rc_line(Shell::Fish, &default(Shell::Fish), &dirs), None);
+        assert_eq!(rc_line(Shell::Bash, &default(Shell::Bash), &dirs), None);
+
+        let custom = Path::new("/opt/completions");
+        assert_eq!(
+            rc_line(Shell::Bash, custom, &dirs).unwrap(),
+            "source /opt/completions/bw"
+        );
+        assert_eq!(
+            rc_line(Shell::Fish, custom, &dirs).unwrap(),
+            "set -p fish_complete_path /opt/completions"
+        );
+    }
+
+    #[test]
+    fn test_parse_shell() {
+        assert_eq!(parse_shell("zsh").unwrap(), Shell::Zsh);
+        assert!(parse_shell("tcsh").is_err());
+    }
+}
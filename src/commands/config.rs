@@ -1,8 +1,14 @@
-use crate::config::{default_config_content, get_config_dir, get_config_path};
+use crate::config::{
+    default_config_content, expand_tilde, get_config, get_config_dir, get_config_path,
+    resolve_config_path, Config,
+};
 use crate::error::GhbareError;
 use std::fs;
 use std::process::Command;
 
+/// Top-level scalar keys that `bw config set` may modify.
+const SETTABLE_KEYS: &[&str] = &["root", "clone_method", "suffix"];
+
 pub fn execute() -> anyhow::Result<()> {
     let config_dir = get_config_dir()?;
     let config_path = get_config_path()?;
@@ -26,7 +32,7 @@ pub fn execute() -> anyhow::Result<()> {
     let status = Command::new(&editor)
         .arg(&config_path)
         .status()
-        .map_err(|e| GhbareError::IoError(e))?;
+        .map_err(GhbareError::IoError)?;
 
     if !status.success() {
         eprintln!("Editor exited with non-zero status");
@@ -34,3 +40,149 @@ pub fn execute() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+pub fn execute_get(key: &str) -> anyhow::Result<()> {
+    let config_path = resolve_config_path()?;
+    let content = fs::read_to_string(&config_path)
+        .map_err(|_| GhbareError::ConfigNotFound(config_path.display().to_string()))?;
+
+    match get_value(&content, key)? {
+        Some(value) => println!("{}", value),
+        None => return Err(GhbareError::UnknownConfigKey(key.to_string()).into()),
+    }
+
+    Ok(())
+}
+
+pub fn execute_set(key: &str, value: &str) -> anyhow::Result<()> {
+    let config_path = get_config_path()?;
+    let content = if config_path.exists() {
+        fs::read_to_string(&config_path)?
+    } else {
+        fs::create_dir_all(get_config_dir()?)?;
+        default_config_content().to_string()
+    };
+
+    let updated = set_value(&content, key, value)?;
+    fs::write(&config_path, updated)?;
+    eprintln!("Set {} = {:?} in {}", key, value, config_path.display());
+
+    Ok(())
+}
+
+/// Prints the effective config (defaults filled in, root made absolute).
+pub fn execute_dump(format: &str) -> anyhow::Result<()> {
+    let config = get_config()?;
+    print!("{}", dump(config, format)?);
+    Ok(())
+}
+
+fn dump(mut config: Config, format: &str) -> Result<String, GhbareError> {
+    config.root = expand_tilde(&config.root).display().to_string();
+    match format {
+        "toml" => toml::to_string(&config)
+            .map_err(|e| GhbareError::ConfigParseError(e.to_string())),
+        "json" => serde_json::to_string_pretty(&config)
+            .map(|json| json + "\n")
+            .map_err(|e| GhbareError::ConfigParseError(e.to_string())),
+        other => Err(GhbareError::ConfigParseError(format!(
+            "Unknown dump format: {} (expected toml or json)",
+            other
+        ))),
+    }
+}
+
+fn get_value(content: &str, key: &str) -> Result<Option<String>, GhbareError> {
+    let doc: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e: toml_edit::TomlError| GhbareError::ConfigParseError(e.to_string()))?;
+
+    Ok(doc.get(key).and_then(|item| item.as_value()).map(|value| {
+        match value.as_str() {
+            Some(s) => s.to_string(),
+            None => value.to_string().trim().to_string(),
+        }
+    }))
+}
+
+/// Sets a top-level key while keeping comments and layout, then checks the result still parses.
+pub(crate) fn set_value(content: &str, key: &str, value: &str) -> Result<String, GhbareError> {
+    if !SETTABLE_KEYS.contains(&key) {
+        return Err(GhbareError::UnknownConfigKey(format!(
+            "{} (settable keys: {})",
+            key,
+            SETTABLE_KEYS.join(", ")
+        )));
+    }
+
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e: toml_edit::TomlError| GhbareError::ConfigParseError(e.to_string()))?;
+    doc[key] = toml_edit::value(value);
+
+    let updated = doc.to_string();
+    toml::from_str::<Config>(&updated)
+        .map_err(|e| GhbareError::ConfigParseError(e.to_string()))?;
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_value() {
+        let content = "root = \"~/repos\"\nclone_method = \"ssh\"\n";
+        assert_eq!(get_value(content, "root").unwrap().as_deref(), Some("~/repos"));
+        assert_eq!(get_value(content, "suffix").unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_value_preserves_comments() {
+        let content = "# my root\nroot = \"~/repos\"\n";
+        let updated = set_value(content, "root", "/data/repos").unwrap();
+        assert_eq!(updated, "# my root\nroot = \"/data/repos\"\n");
+    }
+
+    #[test]
+    fn test_set_value_adds_missing_key() {
+        let updated = set_value(default_config_content(), "suffix", ".work").unwrap();
+        assert_eq!(get_value(&updated, "suffix").unwrap().as_deref(), Some(".work"));
+    }
+
+    #[test]
+    fn test_set_value_unknown_key() {
+        let result = set_value("root = \"~/repos\"\n", "unknown", "x");
+        assert!(matches!(result, Err(GhbareError::UnknownConfigKey(_))));
+    }
+
+    #[test]
+    fn test_dump_round_trips() {
+        let content = "root = \"/data/repos\"\n\n[[mirror]]\nfrom = \"github.com\"\nto = \"mirror.example\"\n";
+        let config: Config = toml::from_str(content).unwrap();
+
+        let dumped = dump(config, "toml").unwrap();
+        let reparsed: Config = toml::from_str(&dumped).unwrap();
+        assert_eq!(reparsed.root, "/data/repos");
+        assert_eq!(reparsed.git_command, "git");
+        assert_eq!(reparsed.mirror[0].to, "mirror.example");
+        assert!(dumped.contains("post_clone_commands"));
+
+        let config: Config = toml::from_str(content).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&dump(config, "json").unwrap()).unwrap();
+        assert_eq!(json["root"], "/data/repos");
+        assert_eq!(json["maintain_index"], false);
+    }
+
+    #[test]
+    fn test_dump_expands_root() {
+        let config: Config = toml::from_str("root = \"~/repos\"\n").unwrap();
+        let dumped = dump(config, "json").unwrap();
+        assert!(!dumped.contains("~/repos"));
+        assert!(matches!(
+            dump(toml::from_str("root = \"/r\"").unwrap(), "yaml"),
+            Err(GhbareError::ConfigParseError(_))
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+use super::bw::find_repo_root;
+use super::get::write_gitdir_file;
+use super::lock::RepoLock;
+use crate::color::{paint, Stream, Style};
+use crate::config::{default_config_content, get_config, get_config_path, resolve_config_path};
+use crate::error::GhbareError;
+use crate::git::git_cmd;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Problem {
+    /// No config file; `--fix` writes the default one here
+    MissingConfig(PathBuf),
+    /// The config file exists but cannot be loaded
+    InvalidConfig(String),
+    /// `<repo>/.git` does not point at `.bare`
+    GitFile,
+    /// `git worktree prune --dry-run` output, one line per stale entry
+    StaleWorktrees(Vec<String>),
+    /// Worktrees whose `.git` file does not point back at `.bare/worktrees/<name>`
+    BrokenLinks(Vec<PathBuf>),
+}
+
+impl Problem {
+    fn describe(&self) -> String {
+        match self {
+            Problem::MissingConfig(path) => format!("Config not found: {}", path.display()),
+            Problem::InvalidConfig(e) => format!("Config cannot be loaded: {}", e),
+            Problem::GitFile => ".git does not point at .bare".to_string(),
+            Problem::StaleWorktrees(lines) => {
+                format!("{} stale worktree entry(ies):\n  {}", lines.len(), lines.join("\n  "))
+            }
+            Problem::BrokenLinks(paths) => format!(
+                "{} worktree(s) with broken gitdir links:\n  {}",
+                paths.len(),
+                paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n  ")
+            ),
+        }
+    }
+}
+
+/// `bw doctor [--fix]`: checks the config and, inside a bw repository, its
+/// worktree links. With `fix`, repairs what it can and checks again.
+pub fn execute(fix: bool) -> anyhow::Result<()> {
+    let repo_root = find_repo_root().ok();
+    let _lock = match (&repo_root, fix) {
+        (Some(root), true) => Some(RepoLock::acquire(root)?),
+        _ => None,
+    };
+
+    let mut problems = diagnose(repo_root.as_deref())?;
+    report(&problems, repo_root.as_deref());
+
+    if fix && !problems.is_empty() {
+        println!();
+        let mut fixed = 0;
+        for problem in &problems {
+            if repair(problem, repo_root.as_deref(), &mut confirm)? {
+                fixed += 1;
+            }
+        }
+        println!("\nFixed {} of {} problem(s). Checking again...\n", fixed, problems.len());
+        problems = diagnose(repo_root.as_deref())?;
+        report(&problems, repo_root.as_deref());
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    if !fix {
+        println!("\nRun 'bw doctor --fix' to repair what can be fixed automatically.");
+    }
+    Err(GhbareError::DoctorFoundProblems(problems.len()).into())
+}
+
+fn diagnose(repo_root: Option<&Path>) -> Result<Vec<Problem>, GhbareError> {
+    let mut problems = Vec::new();
+
+    let config_path = resolve_config_path()?;
+    if !config_path.exists() {
+        problems.push(Problem::MissingConfig(get_config_path()?));
+    } else if let Err(e) = get_config() {
+        problems.push(Problem::InvalidConfig(e.to_string()));
+    }
+
+    if let Some(repo_root) = repo_root {
+        problems.extend(diagnose_repo(repo_root)?);
+    }
+    Ok(problems)
+}
+
+fn diagnose_repo(repo_root: &Path) -> Result<Vec<Problem>, GhbareError> {
+    let mut problems = Vec::new();
+    if !git_file_points_at_bare(repo_root) {
+        problems.push(Problem::GitFile);
+    }
+    let stale = stale_worktrees(repo_root)?;
+    if !stale.is_empty() {
+        problems.push(Problem::StaleWorktrees(stale));
+    }
+    let broken = broken_links(repo_root)?;
+    if !broken.is_empty() {
+        problems.push(Problem::BrokenLinks(broken));
+    }
+    Ok(problems)
+}
+
+fn report(problems: &[Problem], repo_root: Option<&Path>) {
+    match repo_root {
+        Some(root) => println!("Repository: {}", root.display()),
+        None => println!("Not inside a bw repository; only the config was checked"),
+    }
+    if problems.is_empty() {
+        println!("No problems found");
+    }
+    for problem in problems {
+        println!("{} {}", paint("[!!]", Style::Red, Stream::Stdout), problem.describe());
+    }
+}
+
+/// Fixes `problem`, asking `confirm` first before dropping worktree entries.
+/// Returns whether it was fixed.
+fn repair(
+    problem: &Problem,
+    repo_root: Option<&Path>,
+    confirm: &mut impl FnMut(&str) -> Result<bool, GhbareError>,
+) -> Result<bool, GhbareError> {
+    match (problem, repo_root) {
+        (Problem::MissingConfig(path), _) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, default_config_content())?;
+            println!("Created config file: {}", path.display());
+            Ok(true)
+        }
+        (Problem::InvalidConfig(_), _) => {
+            println!("Config must be fixed by hand (bw config)");
+            Ok(false)
+        }
+        (Problem::GitFile, Some(root)) => {
+            write_gitdir_file(root)?;
+            println!("Rewrote {}", root.join(".git").display());
+            Ok(true)
+        }
+        (Problem::StaleWorktrees(lines), Some(root)) => {
+            let question = format!("Prune {} stale worktree entry(ies)?", lines.len());
+            if !confirm(&question)? {
+                println!("Skipped pruning");
+                return Ok(false);
+            }
+            run_git(root, &["worktree", "prune"])?;
+            println!("Pruned stale worktree entries");
+            Ok(true)
+        }
+        (Problem::BrokenLinks(_), Some(root)) => {
+            // Without paths, git walks .bare/worktrees and rewrites each .git file
+            run_git(root, &["worktree", "repair"])?;
+            println!("Repaired worktree links");
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Asks on the terminal; without one, answers no.
+pub(crate) fn confirm(question: &str) -> Result<bool, GhbareError> {
+    if !io::stdin().is_terminal() {
+        println!("{} Skipped: stdin is not a terminal", question);
+        return Ok(false);
+    }
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(matches!(line.trim(), "y" | "Y" | "yes"))
+}
+
+fn run_git(repo_root: &Path, args: &[&str]) -> Result<(), GhbareError> {
+    let status = git_cmd()
+        .args(args)
+        .current_dir(repo_root)
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "git {} failed",
+            args.join(" ")
+        )));
+    }
+    Ok(())
+}
+
+fn git_file_points_at_bare(repo_root: &Path) -> bool {
+    gitdir_of(&repo_root.join(".git"))
+        .is_some_and(|gitdir| same_path(&gitdir, &repo_root.join(".bare")))
+}
+
+fn stale_worktrees(repo_root: &Path) -> Result<Vec<String>, GhbareError> {
+    let output = git_cmd()
+        .args(["worktree", "prune", "--dry-run", "--verbose"])
+        .current_dir(repo_root)
+        .output()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    // git reports on stderr or stdout depending on the version
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Worktrees that still exist but whose `.git` file no longer points at their
+/// entry in `.bare/worktrees` (missing ones are stale and left to prune).
+fn broken_links(repo_root: &Path) -> Result<Vec<PathBuf>, GhbareError> {
+    let admin_root = repo_root.join(".bare").join("worktrees");
+    if !admin_root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut broken = Vec::new();
+    for entry in fs::read_dir(&admin_root)? {
+        let admin_dir = entry?.path();
+        let Ok(dot_git) = fs::read_to_string(admin_dir.join("gitdir")) else {
+            continue;
+        };
+        let dot_git = PathBuf::from(dot_git.trim());
+        let Some(worktree) = dot_git.parent().filter(|p| p.is_dir()) else {
+            continue;
+        };
+        if !gitdir_of(&dot_git).is_some_and(|gitdir| same_path(&gitdir, &admin_dir)) {
+            broken.push(worktree.to_path_buf());
+        }
+    }
+    broken.sort();
+    Ok(broken)
+}
+
+/// Target of a `gitdir: <path>` file, resolved against the file's directory.
+fn gitdir_of(git_file: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(git_file).ok()?;
+    let target = content.trim().strip_prefix("gitdir:")?.trim();
+    Some(git_file.parent()?.join(target))
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bw::add_worktree;
+    use crate::commands::test_support::init_repo_with_commit;
+
+    #[test]
+    fn test_diagnose_repo_healthy() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        add_worktree(tmp.path(), &tmp.path().join("main"), "main", "main", &Default::default())
+            .unwrap();
+        assert_eq!(diagnose_repo(tmp.path()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_diagnose_and_repair_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        init_repo_with_commit(root);
+        let flags = Default::default();
+        add_worktree(root, &root.join("main"), "main", "main", &flags).unwrap();
+        add_worktree(root, &root.join("gone"), "gone", "main", &flags).unwrap();
+        fs::remove_dir_all(root.join("gone")).unwrap();
+        fs::write(root.join("main/.git"), "gitdir: /nowhere\n").unwrap();
+        fs::write(root.join(".git"), "gitdir: elsewhere\n").unwrap();
+
+        let problems = diagnose_repo(root).unwrap();
+        assert_eq!(problems.len(), 3);
+        assert_eq!(problems[0], Problem::GitFile);
+        assert!(matches!(&problems[1], Problem::StaleWorktrees(lines) if lines.len() == 1));
+        let main = fs::canonicalize(root.join("main")).unwrap();
+        assert!(matches!(&problems[2], Problem::BrokenLinks(paths)
+            if paths.iter().map(|p| fs::canonicalize(p).unwrap()).eq([main.clone()])));
+
+        // Pruning is declined
+        for problem in &problems {
+            repair(problem, Some(root), &mut |_| Ok(false)).unwrap();
+        }
+        let remaining = diagnose_repo(root).unwrap();
+        assert!(matches!(&remaining[..], [Problem::StaleWorktrees(_)]), "{:?}", remaining);
+    }
+}
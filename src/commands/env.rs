@@ -0,0 +1,61 @@
+use crate::error::GhbareError;
+
+const POSIX_SNIPPET: &str = r#"bwcd() {
+  local dir
+  dir="$(bw list "$@")" && [ -n "$dir" ] && cd "$dir"
+}
+
+bwadd() {
+  local dir
+  dir="$(bw add --print-path "$@")" && [ -n "$dir" ] && cd "$dir"
+}
+"#;
+
+const FISH_SNIPPET: &str = r#"function bwcd
+    set -l dir (bw list $argv); and test -n "$dir"; and cd $dir
+end
+
+function bwadd
+    set -l dir (bw add --print-path $argv); and test -n "$dir"; and cd $dir
+end
+"#;
+
+/// Prints shell functions for `eval "$(bw env zsh)"`: `bwcd` (cd into a worktree
+/// picked with `bw list`) and `bwadd` (create a worktree and cd into it).
+pub fn execute(shell: &str) -> anyhow::Result<()> {
+    print!("{}", snippet(shell)?);
+    Ok(())
+}
+
+fn snippet(shell: &str) -> Result<&'static str, GhbareError> {
+    match shell {
+        "bash" | "zsh" => Ok(POSIX_SNIPPET),
+        "fish" => Ok(FISH_SNIPPET),
+        other => Err(GhbareError::ConfigParseError(format!(
+            "Unsupported shell: {} (expected bash, zsh or fish)",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_snippet() {
+        assert!(snippet("zsh").unwrap().contains("bw add --print-path \"$@\""));
+        assert!(snippet("fish").unwrap().contains("function bwcd"));
+        assert!(snippet("tcsh").is_err());
+    }
+
+    #[test]
+    fn test_posix_snippet_parses() {
+        let status = Command::new("sh")
+            .args(["-n", "-c", POSIX_SNIPPET])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
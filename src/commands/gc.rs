@@ -0,0 +1,85 @@
+use super::bw::find_repo_root;
+use crate::error::GhbareError;
+use crate::git::git_cmd;
+use std::fs;
+use std::path::Path;
+
+pub fn execute(aggressive: bool) -> anyhow::Result<()> {
+    let repo_root = find_repo_root()?;
+    let bare_dir = repo_root.join(".bare");
+
+    let before = dir_size(&bare_dir)?;
+    eprintln!("Running git gc in {}...", bare_dir.display());
+
+    let mut args = vec!["gc"];
+    if aggressive {
+        args.push("--aggressive");
+    }
+    let status = git_cmd()
+        .args(&args)
+        .current_dir(&bare_dir)
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::WorktreeError("git gc failed".to_string()).into());
+    }
+
+    let after = dir_size(&bare_dir)?;
+    eprintln!(
+        "\nDone! .bare: {} -> {}",
+        format_size(before),
+        format_size(after)
+    );
+
+    Ok(())
+}
+
+fn dir_size(path: &Path) -> Result<u64, GhbareError> {
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a"), [0u8; 100]).unwrap();
+        fs::create_dir(tmp.path().join("sub")).unwrap();
+        fs::write(tmp.path().join("sub").join("b"), [0u8; 50]).unwrap();
+        assert_eq!(dir_size(tmp.path()).unwrap(), 150);
+    }
+}
@@ -1,59 +1,811 @@
-use crate::config;
+use super::bw::{add_worktree, branch_to_dirname, list_worktrees, pick_one, WorktreeEntry};
+use super::metadata::{self, CloneMetadata};
+use super::summary::Summary;
+use crate::config::{self, HostSettings, MirrorRule};
 use crate::error::GhbareError;
 use crate::git;
+use crate::index::{self, IndexEntry};
 use crate::url::{parse_repo_url, RepoInfo};
+use git2::Repository;
 use std::fs;
-use std::path::Path;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub fn execute(repo: &str, ssh: bool, https: bool, suffix: Option<String>) -> anyhow::Result<()> {
-    let repo_info = parse_repo_url(repo)?;
+const DEFAULT_HOST: &str = "github.com";
+pub(crate) const TAG_DIR: &str = "by-tag";
+
+#[derive(Debug, Default)]
+pub struct GetOptions {
+    /// SSH clone (default)
+    pub ssh: bool,
+    /// HTTPS clone
+    pub https: bool,
+    /// Suffix for directory name (overrides config)
+    pub suffix: Option<String>,
+    /// Fetch only the remote HEAD branch
+    pub single_branch: bool,
+    /// Fetch only this branch and make it the clone's HEAD
+    pub branch: Option<String>,
+    /// Host to clone from instead of the repository's host (overrides [[mirror]])
+    pub mirror_to: Option<String>,
+    /// Initialize submodules in the created worktrees
+    pub recursive: bool,
+    /// Fetch submodules with depth 1 (requires `recursive`)
+    pub shallow_submodules: bool,
+    /// Parallel jobs for `git submodule update` (requires `recursive`)
+    pub submodule_jobs: Option<u32>,
+    /// Host for specs without one (overrides default_host)
+    pub host: Option<String>,
+    /// Owner for specs without one (overrides default_owner)
+    pub owner: Option<String>,
+    /// Hide clone transfer progress but keep step messages
+    pub quiet_clone: bool,
+    /// Shell command to run in the HEAD worktree after cloning
+    pub then: Option<String>,
+    /// Absolute project directory used instead of root/host/owner/repo
+    pub into: Option<String>,
+    /// Topics linked under `<root>/by-tag/<tag>/` and stored in the index
+    pub tags: Vec<String>,
+    /// Local repository to borrow objects from
+    pub reference: Option<String>,
+    /// Copy borrowed objects instead of keeping the reference
+    pub dissociate: bool,
+    /// Fetch only the tip commit of the HEAD branch, without tags
+    pub single_commit: bool,
+    /// Fetch only this many commits of history
+    pub depth: Option<u32>,
+    /// Clone in a staging directory that a rerun resumes after a failure
+    pub resumable: bool,
+    /// Accept any TLS certificate (also enabled per host by `[[host]] insecure`)
+    pub insecure: bool,
+}
+
+pub fn execute(repo: Option<&str>, opts: GetOptions) -> anyhow::Result<()> {
+    // A bad --tag must not leave a finished clone behind an error
+    for tag in &opts.tags {
+        validate_tag(tag)?;
+    }
+    let cfg = config::get_config()?;
+    let default_host = opts.host.as_deref().or(cfg.default_host.as_deref());
+    let default_owner = opts.owner.as_deref().or(cfg.default_owner.as_deref());
+
+    let repo_info = match repo {
+        Some("-") => {
+            let spec = read_spec(&mut io::stdin().lock())?;
+            parse_spec(&spec, &cfg.host, default_host, default_owner)?
+        }
+        Some(repo) => parse_spec(repo, &cfg.host, default_host, default_owner)?,
+        None if io::stdin().is_terminal() => {
+            prompt_repo_info(&mut io::stdin().lock(), &mut io::stderr())?
+        }
+        None => {
+            return Err(GhbareError::UrlParseError(
+                "Repository argument is required when stdin is not a terminal".to_string(),
+            )
+            .into())
+        }
+    };
     println!(
         "Repository: {}/{}/{}",
         repo_info.host, repo_info.owner, repo_info.repo
     );
 
-    let cfg = config::get_config()?;
-    let clone_url = determine_clone_url(&repo_info, ssh, https)?;
+    let clone_info = apply_mirror(&repo_info, &cfg.mirror, opts.mirror_to.as_deref());
+    if clone_info.host != repo_info.host {
+        println!("Mirror: {} -> {}", repo_info.host, clone_info.host);
+    }
+    let clone_url = determine_clone_url(&clone_info, opts.ssh, opts.https)?;
     println!("Clone URL: {}", clone_url);
 
-    let root = config::get_root()?;
+    let project_dir = match &opts.into {
+        // --into bypasses root and the host/owner/repo layout
+        Some(into) => resolve_into(into)?,
+        None => {
+            // Determine suffix: CLI option > config > none
+            let effective_suffix = opts.suffix.clone().or(cfg.suffix.clone());
+            config::expand_tilde(&cfg.root).join(local_path(
+                &cfg,
+                &repo_info,
+                effective_suffix.as_deref(),
+            )?)
+        }
+    };
+    let bare_dir = project_dir.join(".bare");
 
-    // Determine suffix: CLI option > config > none
-    let effective_suffix = suffix.or(cfg.suffix.clone());
+    check_project_dir(&project_dir, &clone_info.host)?;
+    let dir_mode = cfg.dir_mode.as_deref().map(parse_dir_mode).transpose()?;
 
-    let local_path = match &effective_suffix {
-        Some(s) => format!("{}{}", repo_info.to_local_path(), s),
-        None => repo_info.to_local_path(),
-    };
+    let mut clone_opts = clone_options(&opts);
+    clone_opts.insecure |= cfg.is_insecure_host(&clone_info.host);
+    clone_opts.ca_bundle = cfg.ca_bundle_for(&clone_info.host);
+    if cfg.ca_bundle.is_some() {
+        eprintln!(
+            "Warning: the top-level ca_bundle is ignored; set it in the [[host]] entry \
+             of the host that needs it"
+        );
+    }
+    if clone_opts.insecure {
+        eprintln!(
+            "WARNING: TLS certificate verification is DISABLED for {}.\n\
+             WARNING: The connection can be intercepted; use this only for trusted internal hosts.",
+            clone_info.host
+        );
+    }
+    if let Some(depth) = clone_opts.depth {
+        println!("Shallow clone (depth: {})", depth);
+    }
+    let repo = if opts.resumable {
+        resumable_clone(&clone_url, &project_dir, &clone_opts)?
+    } else {
+        fs::create_dir_all(&project_dir)?;
+        println!("Created: {}", project_dir.display());
 
-    let project_dir = root.join(&local_path);
-    let bare_dir = project_dir.join(".bare");
+        println!("Cloning into {}...", bare_dir.display());
+        git::bare_clone(&clone_url, &bare_dir, &clone_opts)?
+    };
 
-    if project_dir.exists() {
-        return Err(GhbareError::RepositoryAlreadyExists(project_dir.display().to_string()).into());
+    if let Some(mode) = dir_mode {
+        apply_dir_mode(&[&project_dir, &bare_dir], mode)?;
     }
 
-    fs::create_dir_all(&project_dir)?;
-    println!("Created: {}", project_dir.display());
+    // With --single-branch, keep later fetches limited to the cloned branch
+    let single_branch = if clone_opts.single_branch {
+        repo.head()
+            .ok()
+            .and_then(|h| h.shorthand().map(|s| s.to_string()))
+    } else {
+        None
+    };
+    let refspec = git::fetch_refspec(single_branch.as_deref());
 
-    println!("Cloning into {}...", bare_dir.display());
-    git::bare_clone(&clone_url, &bare_dir)?;
+    write_gitdir_file(&project_dir)?;
 
     // Run post_clone_commands in project directory
-    run_post_clone_commands(&cfg.post_clone_commands, &project_dir)?;
+    run_post_clone_commands(&cfg.post_clone_commands, &project_dir, &refspec)?;
+
+    if let Some(branch) = &single_branch {
+        // Re-assert the refspec in case post_clone_commands overwrote it, and drop
+        // what they fetched with a wider one (commands not using $BW_FETCH_REFSPEC).
+        // The file is reopened: repo.config() caches the value from before the
+        // commands and would skip the write as unchanged.
+        git2::Config::open(&bare_dir.join("config"))
+            .and_then(|mut c| c.set_str("remote.origin.fetch", &refspec))
+            .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+        let pruned = prune_other_remote_branches(&repo, branch)
+            .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+        if pruned > 0 {
+            println!(
+                "Removed {} remote-tracking branch(es) outside --single-branch",
+                pruned
+            );
+        }
+    }
+
+    // Custom post_clone_commands decide for themselves whether to create worktrees
+    if cfg.has_default_post_clone_commands() {
+        ensure_worktree(&project_dir)?;
+    }
+
+    if opts.recursive {
+        init_submodules(&project_dir, opts.shallow_submodules, opts.submodule_jobs)?;
+    }
 
     // Create empty .envrc
     let envrc_path = project_dir.join(".envrc");
     fs::write(&envrc_path, "")?;
     println!("Created .envrc");
 
+    if !opts.tags.is_empty() {
+        let tag_root = config::get_root()?.join(TAG_DIR);
+        for tag in &opts.tags {
+            link_tag(&tag_root, tag, &repo_info, &project_dir)?;
+        }
+    }
+
+    // Tags live in the index, so record tagged clones even without maintain_index
+    if cfg.maintain_index || !opts.tags.is_empty() {
+        let entry = IndexEntry {
+            host: repo_info.host.clone(),
+            owner: repo_info.owner.clone(),
+            repo: repo_info.repo.clone(),
+            path: project_dir.display().to_string(),
+            cloned_at: index::now_unix(),
+            tags: opts.tags.clone(),
+        };
+        index::append_entry(&index::get_index_path()?, &entry)?;
+    }
+
+    if cfg.write_clone_metadata {
+        let metadata = CloneMetadata {
+            host: repo_info.host.clone(),
+            owner: repo_info.owner.clone(),
+            repo: repo_info.repo.clone(),
+            clone_url: clone_url.clone(),
+            clone_method: if clone_url.starts_with("https://") { "https" } else { "ssh" }
+                .to_string(),
+            cloned_at: index::now_unix(),
+        };
+        metadata::write(&project_dir, &metadata)?;
+    }
+
     println!("\nDone! Repository cloned to: {}", project_dir.display());
 
+    let worktrees = list_worktrees(&project_dir).unwrap_or_default();
+    let branches: Vec<String> = worktrees
+        .iter()
+        .filter_map(|w| w.short_branch().map(|b| b.to_string()))
+        .collect();
+    let mut summary = Summary::new("bw get");
+    summary.add("project", project_dir.display().to_string());
+    summary.add("clone url", &clone_url);
+    summary.add(
+        "worktrees",
+        if branches.is_empty() {
+            "(none)".to_string()
+        } else {
+            branches.join(", ")
+        },
+    );
+    summary.print();
+
+    if let Some(command) = &opts.then {
+        let head = repo
+            .head()
+            .ok()
+            .and_then(|h| h.shorthand().map(|s| s.to_string()));
+        let Some(dir) = head_worktree(&worktrees, head.as_deref()) else {
+            return Err(GhbareError::PostCloneCommandError(
+                "No worktree to run --then in".to_string(),
+            )
+            .into());
+        };
+        run_then_command(command, &dir)?;
+    }
+
+    Ok(())
+}
+
+/// The worktree checked out on `head`, or the first one when HEAD has none.
+fn head_worktree(worktrees: &[WorktreeEntry], head: Option<&str>) -> Option<PathBuf> {
+    let candidates: Vec<&WorktreeEntry> = worktrees.iter().filter(|w| !w.bare).collect();
+    candidates
+        .iter()
+        .find(|w| head.is_some() && w.short_branch() == head)
+        .or_else(|| candidates.first())
+        .map(|w| w.path.clone())
+}
+
+fn run_then_command(command: &str, working_dir: &Path) -> Result<(), GhbareError> {
+    println!("Running '{}' in {}", command, working_dir.display());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(working_dir)
+        .status()
+        .map_err(|e| GhbareError::PostCloneCommandError(format!("Failed to execute: {}", e)))?;
+    if !status.success() {
+        return Err(GhbareError::PostCloneCommandError(format!(
+            "--then command failed: {}",
+            command
+        )));
+    }
+    Ok(())
+}
+
+/// Parses `spec` after completing it with the default host and owner. Without
+/// a default host, the host of an `owner/repo` spec is picked from `[[host]]`.
+pub(crate) fn parse_spec(
+    spec: &str,
+    hosts: &[HostSettings],
+    default_host: Option<&str>,
+    default_owner: Option<&str>,
+) -> Result<RepoInfo, GhbareError> {
+    let picked = match default_host {
+        Some(_) => None,
+        None => choose_host(spec, hosts, default_owner.is_some(), io::stdin().is_terminal())?,
+    };
+    let host = default_host.or(picked.as_deref());
+    parse_repo_url(&expand_short_spec(spec, host, default_owner)?)
+}
+
+/// Host for a spec that lacks one when two or more `[[host]]` entries make it
+/// ambiguous: picked with fzf on a terminal, an error otherwise. `None` when
+/// the spec has a host or there is nothing to choose from.
+fn choose_host(
+    spec: &str,
+    hosts: &[HostSettings],
+    has_default_owner: bool,
+    interactive: bool,
+) -> Result<Option<String>, GhbareError> {
+    let spec = spec.trim();
+    let needs_host = !spec.contains(':')
+        && !spec.starts_with("git@")
+        && match spec.split('/').count() {
+            1 => has_default_owner,
+            2 => true,
+            _ => false,
+        };
+    if !needs_host || hosts.len() < 2 {
+        return Ok(None);
+    }
+
+    let names: Vec<String> = hosts.iter().map(|h| h.name.clone()).collect();
+    if !interactive {
+        return Err(GhbareError::UrlParseError(format!(
+            "'{}' has no host and several hosts are configured ({}): pass --host or set default_host",
+            spec,
+            names.join(", ")
+        )));
+    }
+    match pick_one(&names)? {
+        Some(host) => Ok(Some(host)),
+        None => Err(GhbareError::UrlParseError("No host selected".to_string())),
+    }
+}
+
+/// Completes `repo` and `owner/repo` specs with the default host and owner.
+/// URLs and full `host/owner/repo` specs are returned unchanged.
+pub(crate) fn expand_short_spec(
+    spec: &str,
+    host: Option<&str>,
+    owner: Option<&str>,
+) -> Result<String, GhbareError> {
+    let spec = spec.trim();
+    if spec.contains(':') || spec.starts_with("git@") {
+        return Ok(spec.to_string());
+    }
+
+    match spec.split('/').count() {
+        1 => match (host, owner) {
+            (Some(host), Some(owner)) => Ok(format!("{}/{}/{}", host, owner, spec)),
+            _ => Err(GhbareError::UrlParseError(format!(
+                "'{}' has no owner: pass --host/--owner or set default_host/default_owner",
+                spec
+            ))),
+        },
+        2 => match host {
+            Some(host) => Ok(format!("{}/{}", host, spec)),
+            None => Ok(spec.to_string()),
+        },
+        _ => Ok(spec.to_string()),
+    }
+}
+
+/// Reads exactly one repository spec (ignoring blank lines) for `bw get -`.
+fn read_spec<R: BufRead>(input: &mut R) -> Result<String, GhbareError> {
+    let mut specs = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            specs.push(line.to_string());
+        }
+    }
+
+    match specs.len() {
+        0 => Err(GhbareError::UrlParseError(
+            "No repository spec on stdin".to_string(),
+        )),
+        1 => Ok(specs.remove(0)),
+        n => Err(GhbareError::UrlParseError(format!(
+            "Expected one repository spec on stdin, got {}",
+            n
+        ))),
+    }
+}
+
+/// Asks for host, owner and repo one by one. An empty host falls back to github.com.
+fn prompt_repo_info<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+) -> Result<RepoInfo, GhbareError> {
+    let host = prompt(input, output, &format!("Host [{}]: ", DEFAULT_HOST))?;
+    let host = if host.is_empty() {
+        DEFAULT_HOST.to_string()
+    } else {
+        host
+    };
+
+    let owner = prompt(input, output, "Owner: ")?;
+    if owner.is_empty() {
+        return Err(GhbareError::UrlParseError("Owner is required".to_string()));
+    }
+
+    let repo = prompt(input, output, "Repository: ")?;
+    let repo = repo.strip_suffix(".git").unwrap_or(&repo).to_string();
+    if repo.is_empty() {
+        return Err(GhbareError::UrlParseError(
+            "Repository is required".to_string(),
+        ));
+    }
+
+    Ok(RepoInfo {
+        host,
+        owner,
+        repo,
+        ssh_port: None,
+        https_port: None,
+    })
+}
+
+fn prompt<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    message: &str,
+) -> Result<String, GhbareError> {
+    write!(output, "{}", message)?;
+    output.flush()?;
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// Deletes `refs/remotes/origin/*` other than `origin/<branch>`, and
+/// `origin/HEAD` when it points at one of them. Returns how many were deleted.
+fn prune_other_remote_branches(repo: &Repository, branch: &str) -> Result<usize, git2::Error> {
+    let keep = format!("refs/remotes/origin/{}", branch);
+    let mut names = Vec::new();
+    for reference in repo.references_glob("refs/remotes/origin/*")? {
+        let reference = reference?;
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        let stale = if name == "refs/remotes/origin/HEAD" {
+            reference.symbolic_target().is_some_and(|t| t != keep)
+        } else {
+            name != keep
+        };
+        if stale {
+            names.push(name.to_string());
+        }
+    }
+    for name in &names {
+        repo.find_reference(name)?.delete()?;
+    }
+    Ok(names.iter().filter(|n| !n.ends_with("/HEAD")).count())
+}
+
+/// Makes sure the clone has at least one worktree. When the default
+/// post_clone_commands created none (no remote HEAD, or its branch could not be
+/// checked out), falls back to main, master or the first branch; an empty
+/// repository only gets a warning.
+fn ensure_worktree(project_dir: &Path) -> Result<(), GhbareError> {
+    if list_worktrees(project_dir)?.iter().any(|w| !w.bare) {
+        return Ok(());
+    }
+
+    let output = git::git_cmd()
+        .args([
+            "for-each-ref",
+            "--format=%(refname)",
+            "refs/remotes/origin",
+            "refs/heads",
+        ])
+        .current_dir(project_dir)
+        .output()
+        .map_err(|e| GhbareError::PostCloneCommandError(e.to_string()))?;
+    let refs = String::from_utf8_lossy(&output.stdout);
+
+    let Some(branch) = choose_default_branch(refs.lines()) else {
+        eprintln!(
+            "Warning: the repository has no branches, so no worktree was created.\n\
+             Run 'bw add <branch>' in {} to create the first one.",
+            project_dir.display()
+        );
+        return Ok(());
+    };
+
+    println!(
+        "No worktree was created for the remote HEAD; creating one for '{}'",
+        branch
+    );
+    let worktree_path = project_dir.join(branch_to_dirname(&branch));
+    add_worktree(
+        project_dir,
+        &worktree_path,
+        &branch,
+        &format!("origin/{}", branch),
+        &Default::default(),
+    )
+    .map_err(|e| GhbareError::PostCloneCommandError(e.to_string()))
+}
+
+/// Picks main or master when present, otherwise the first branch by name.
+fn choose_default_branch<'a>(refs: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut branches: Vec<String> = refs
+        .filter_map(|r| {
+            r.strip_prefix("refs/remotes/origin/")
+                .or_else(|| r.strip_prefix("refs/heads/"))
+        })
+        .filter(|b| *b != "HEAD")
+        .map(|b| b.to_string())
+        .collect();
+    // origin/<b> and a local <b> are the same branch, wherever they sort
+    branches.sort();
+    branches.dedup();
+
+    ["main", "master"]
+        .iter()
+        .find_map(|preferred| branches.iter().find(|b| b == preferred).cloned())
+        .or_else(|| branches.into_iter().next())
+}
+
+fn init_submodules(
+    project_dir: &Path,
+    shallow: bool,
+    jobs: Option<u32>,
+) -> Result<(), GhbareError> {
+    let worktrees = list_worktrees(project_dir)?;
+    for worktree in worktrees.iter().filter(|w| !w.bare) {
+        println!("Initializing submodules in {}...", worktree.path.display());
+        let status = git::git_cmd()
+            .args(submodule_update_args(shallow, jobs))
+            .current_dir(&worktree.path)
+            .status()
+            .map_err(|e| GhbareError::CloneError(format!("Failed to execute: {}", e)))?;
+        if !status.success() {
+            return Err(GhbareError::CloneError(
+                "git submodule update failed".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn submodule_update_args(shallow: bool, jobs: Option<u32>) -> Vec<String> {
+    let mut args: Vec<String> = ["submodule", "update", "--init", "--recursive"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if shallow {
+        args.extend(["--depth".to_string(), "1".to_string()]);
+    }
+    if let Some(jobs) = jobs {
+        args.extend(["--jobs".to_string(), jobs.to_string()]);
+    }
+    args
+}
+
+/// Project directory relative to root: `path_template` if set, else host/owner/repo + suffix.
+pub(crate) fn local_path(
+    cfg: &config::Config,
+    repo_info: &RepoInfo,
+    suffix: Option<&str>,
+) -> Result<String, GhbareError> {
+    if let Some(template) = &cfg.path_template {
+        return render_path_template(template, repo_info, suffix);
+    }
+    let path = if cfg.omit_host {
+        format!("{}/{}", repo_info.owner, repo_info.repo)
+    } else {
+        repo_info.to_local_path()
+    };
+    Ok(format!("{}{}", path, suffix.unwrap_or("")))
+}
+
+/// Expands `path_template`. A suffix is appended when the template has no `{suffix}`.
+fn render_path_template(
+    template: &str,
+    repo_info: &RepoInfo,
+    suffix: Option<&str>,
+) -> Result<String, GhbareError> {
+    if !template.contains("{repo}") {
+        return Err(GhbareError::ConfigParseError(format!(
+            "path_template must contain {{repo}}: {}",
+            template
+        )));
+    }
+
+    let suffix = suffix.unwrap_or("");
+    let mut path = template
+        .replace("{host}", &repo_info.host)
+        .replace("{owner}", &repo_info.owner)
+        .replace("{repo}", &repo_info.repo);
+    if template.contains("{suffix}") {
+        path = path.replace("{suffix}", suffix);
+    } else {
+        path.push_str(suffix);
+    }
+
+    if let Some(start) = path.find('{').filter(|&i| path[i..].contains('}')) {
+        let end = start + path[start..].find('}').unwrap();
+        return Err(GhbareError::ConfigParseError(format!(
+            "Unknown placeholder {} in path_template",
+            &path[start..=end]
+        )));
+    }
+    if path.starts_with('/') || path.split('/').any(|p| p == "..") {
+        return Err(GhbareError::ConfigParseError(format!(
+            "path_template must stay under root: {}",
+            template
+        )));
+    }
+    Ok(path)
+}
+
+/// A tag is one directory name under the tag root.
+fn validate_tag(tag: &str) -> Result<(), GhbareError> {
+    if tag.is_empty() || tag.contains('/') || tag.starts_with('.') {
+        return Err(GhbareError::CloneError(format!("Invalid tag name: '{}'", tag)));
+    }
+    Ok(())
+}
+
+/// Creates `<tag_root>/<tag>/<owner>/<repo>` pointing at `project_dir`.
+fn link_tag(
+    tag_root: &Path,
+    tag: &str,
+    repo_info: &RepoInfo,
+    project_dir: &Path,
+) -> Result<(), GhbareError> {
+    validate_tag(tag)?;
+    let link = tag_root.join(tag).join(&repo_info.owner).join(&repo_info.repo);
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if link.symlink_metadata().is_ok() {
+        fs::remove_file(&link)?;
+    }
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(project_dir, &link)?;
+        println!("Tagged: {} -> {}", link.display(), project_dir.display());
+    }
+    #[cfg(not(unix))]
+    eprintln!(
+        "Warning: symlinks are not supported here; tag '{}' is only stored in the index",
+        tag
+    );
     Ok(())
 }
 
-fn run_post_clone_commands(commands: &str, working_dir: &Path) -> Result<(), GhbareError> {
+/// Fetch attempts per step of a `--resumable` clone.
+const RESUME_ATTEMPTS: u32 = 3;
+
+/// Staging directory of a `--resumable` clone, next to `project_dir`.
+fn partial_dir(project_dir: &Path) -> PathBuf {
+    let name = project_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    project_dir.with_file_name(format!(".{}.bw-partial", name))
+}
+
+/// Fetches into `partial_dir` (resuming what an earlier run left there) and
+/// moves it to `project_dir` only once the clone is complete.
+fn resumable_clone(
+    url: &str,
+    project_dir: &Path,
+    opts: &git::CloneOptions,
+) -> Result<Repository, GhbareError> {
+    let staging = partial_dir(project_dir);
+    fs::create_dir_all(&staging)?;
+    println!("Cloning into {} (resumable)...", staging.join(".bare").display());
+
+    if let Err(e) = git::resume_bare_clone(url, &staging.join(".bare"), opts, RESUME_ATTEMPTS) {
+        eprintln!(
+            "Partial clone kept in {}; run the same command again to resume.",
+            staging.display()
+        );
+        return Err(e);
+    }
+
+    fs::rename(&staging, project_dir)?;
+    println!("Created: {}", project_dir.display());
+    Repository::open_bare(project_dir.join(".bare"))
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))
+}
+
+/// `--single-commit` is shorthand for `--depth 1 --single-branch --no-tags`,
+/// and `--branch` implies `--single-branch`.
+fn clone_options(opts: &GetOptions) -> git::CloneOptions {
+    git::CloneOptions {
+        single_branch: opts.single_branch || opts.single_commit || opts.branch.is_some(),
+        branch: opts.branch.clone(),
+        quiet_progress: opts.quiet_clone,
+        reference: opts.reference.as_deref().map(config::expand_tilde),
+        dissociate: opts.dissociate,
+        depth: opts.depth.or(opts.single_commit.then_some(1)),
+        no_tags: opts.single_commit,
+        insecure: opts.insecure,
+        ca_bundle: None,
+    }
+}
+
+fn resolve_into(into: &str) -> Result<PathBuf, GhbareError> {
+    let path = config::expand_tilde(into);
+    if !path.is_absolute() {
+        return Err(GhbareError::CloneError(format!(
+            "--into must be an absolute path: {}",
+            into
+        )));
+    }
+    Ok(path)
+}
+
+/// Fails when `project_dir` is taken. Layouts without the host (omit_host,
+/// path_template) can map two hosts to the same directory, so an existing clone
+/// of another host is reported as a collision rather than as already cloned.
+fn check_project_dir(project_dir: &Path, host: &str) -> Result<(), GhbareError> {
+    if !project_dir.exists() {
+        return Ok(());
+    }
+    let bare_dir = project_dir.join(".bare");
+    if bare_dir.is_dir() {
+        match origin_host(&bare_dir) {
+            Some(existing) if existing != host => Err(GhbareError::HostCollision {
+                path: project_dir.display().to_string(),
+                existing,
+                requested: host.to_string(),
+            }),
+            _ => Err(GhbareError::RepositoryAlreadyExists(
+                project_dir.display().to_string(),
+            )),
+        }
+    } else {
+        Err(GhbareError::PathOccupied(project_dir.display().to_string()))
+    }
+}
+
+/// Parses `dir_mode` from bw config, an octal string such as "0750".
+fn parse_dir_mode(mode: &str) -> Result<u32, GhbareError> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|m| *m <= 0o7777)
+        .ok_or_else(|| {
+            GhbareError::ConfigParseError(format!(
+                "dir_mode must be an octal mode like \"0750\": {}",
+                mode
+            ))
+        })
+}
+
+fn apply_dir_mode(dirs: &[&Path], mode: u32) -> Result<(), GhbareError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        for dir in dirs {
+            fs::set_permissions(dir, fs::Permissions::from_mode(mode))?;
+        }
+        println!("Set permissions {:o} on {} directories", mode, dirs.len());
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (dirs, mode);
+        eprintln!("Warning: dir_mode is only supported on Unix; ignoring it");
+    }
+    Ok(())
+}
+
+fn origin_host(bare_dir: &Path) -> Option<String> {
+    let repo = Repository::open_bare(bare_dir).ok()?;
+    let remote = repo.find_remote("origin").ok()?;
+    parse_repo_url(remote.url()?).ok().map(|info| info.host)
+}
+
+/// Writes `<project>/.git` as `gitdir: .bare`, unless it already points at `.bare`.
+pub(crate) fn write_gitdir_file(project_dir: &Path) -> Result<(), GhbareError> {
+    let git_file = project_dir.join(".git");
+    let current = fs::read_to_string(&git_file)
+        .ok()
+        .and_then(|content| content.trim().strip_prefix("gitdir:").map(|p| p.trim().to_string()));
+    let points_at_bare = current.is_some_and(|gitdir| {
+        let bare_dir = project_dir.join(".bare");
+        fs::canonicalize(project_dir.join(gitdir)).ok() == fs::canonicalize(&bare_dir).ok()
+    });
+    if !points_at_bare {
+        fs::write(&git_file, "gitdir: .bare\n")?;
+    }
+    Ok(())
+}
+
+fn run_post_clone_commands(
+    commands: &str,
+    working_dir: &Path,
+    fetch_refspec: &str,
+) -> Result<(), GhbareError> {
     if commands.trim().is_empty() {
         return Ok(());
     }
@@ -62,6 +814,7 @@ fn run_post_clone_commands(commands: &str, working_dir: &Path) -> Result<(), Ghb
         .arg("-c")
         .arg(commands)
         .current_dir(working_dir)
+        .env("BW_FETCH_REFSPEC", fetch_refspec)
         .status()
         .map_err(|e| GhbareError::PostCloneCommandError(format!("Failed to execute: {}", e)))?;
     if !status.success() {
@@ -72,6 +825,27 @@ fn run_post_clone_commands(commands: &str, working_dir: &Path) -> Result<(), Ghb
     Ok(())
 }
 
+/// Returns the repository info to clone from. The local path keeps using the original host.
+fn apply_mirror(repo_info: &RepoInfo, rules: &[MirrorRule], mirror_to: Option<&str>) -> RepoInfo {
+    let host = mirror_to.or_else(|| {
+        rules
+            .iter()
+            .find(|r| r.from == repo_info.host)
+            .map(|r| r.to.as_str())
+    });
+
+    match host {
+        // The mirror listens on its own port
+        Some(host) => RepoInfo {
+            host: host.to_string(),
+            ssh_port: None,
+            https_port: None,
+            ..repo_info.clone()
+        },
+        None => repo_info.clone(),
+    }
+}
+
 fn determine_clone_url(repo_info: &RepoInfo, ssh: bool, https: bool) -> Result<String, GhbareError> {
     match (ssh, https) {
         (true, true) => Err(GhbareError::UrlParseError(
@@ -93,6 +867,8 @@ mod tests {
             host: "github.com".to_string(),
             owner: "user".to_string(),
             repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
         };
         let url = determine_clone_url(&info, true, false).unwrap();
         assert_eq!(url, "git@github.com:user/repo.git");
@@ -104,6 +880,8 @@ mod tests {
             host: "github.com".to_string(),
             owner: "user".to_string(),
             repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
         };
         let url = determine_clone_url(&info, false, true).unwrap();
         assert_eq!(url, "https://github.com/user/repo.git");
@@ -115,17 +893,480 @@ mod tests {
             host: "github.com".to_string(),
             owner: "user".to_string(),
             repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
         };
         let url = determine_clone_url(&info, false, false).unwrap();
         assert_eq!(url, "git@github.com:user/repo.git");
     }
 
+    #[test]
+    fn test_determine_clone_url_port_of_other_scheme() {
+        // An HTTPS port is not the SSH port, and vice versa
+        let info = parse_repo_url("https://git.internal:8443/g/p").unwrap();
+        assert_eq!(
+            determine_clone_url(&info, false, false).unwrap(),
+            "git@git.internal:g/p.git"
+        );
+        assert_eq!(
+            determine_clone_url(&info, false, true).unwrap(),
+            "https://git.internal:8443/g/p.git"
+        );
+
+        let info = parse_repo_url("ssh://git@git.internal:2222/g/p.git").unwrap();
+        assert_eq!(
+            determine_clone_url(&info, false, true).unwrap(),
+            "https://git.internal/g/p.git"
+        );
+    }
+
+    #[test]
+    fn test_prompt_repo_info() {
+        let mut input = io::Cursor::new("gitlab.com\nuser\nrepo\n");
+        let mut output = Vec::new();
+        let info = prompt_repo_info(&mut input, &mut output).unwrap();
+        assert_eq!(info.host, "gitlab.com");
+        assert_eq!(info.owner, "user");
+        assert_eq!(info.repo, "repo");
+    }
+
+    #[test]
+    fn test_prompt_repo_info_default_host() {
+        let mut input = io::Cursor::new("\nuser\nrepo.git\n");
+        let mut output = Vec::new();
+        let info = prompt_repo_info(&mut input, &mut output).unwrap();
+        assert_eq!(info.host, "github.com");
+        assert_eq!(info.repo, "repo");
+    }
+
+    #[test]
+    fn test_prompt_repo_info_missing_owner() {
+        let mut input = io::Cursor::new("\n\nrepo\n");
+        let mut output = Vec::new();
+        assert!(prompt_repo_info(&mut input, &mut output).is_err());
+    }
+
+    #[test]
+    fn test_apply_mirror_rewrites_clone_host_only() {
+        let info = parse_repo_url("github.com/user/repo").unwrap();
+        let rules = vec![MirrorRule {
+            from: "github.com".to_string(),
+            to: "git.internal.example.com".to_string(),
+        }];
+        let clone_info = apply_mirror(&info, &rules, None);
+        assert_eq!(
+            determine_clone_url(&clone_info, false, false).unwrap(),
+            "git@git.internal.example.com:user/repo.git"
+        );
+        assert_eq!(info.to_local_path(), "github.com/user/repo");
+    }
+
+    #[test]
+    fn test_apply_mirror_no_matching_rule() {
+        let info = parse_repo_url("gitlab.com/user/repo").unwrap();
+        let rules = vec![MirrorRule {
+            from: "github.com".to_string(),
+            to: "git.internal.example.com".to_string(),
+        }];
+        assert_eq!(apply_mirror(&info, &rules, None), info);
+    }
+
+    #[test]
+    fn test_apply_mirror_cli_overrides_rules() {
+        let info = parse_repo_url("github.com/user/repo").unwrap();
+        let rules = vec![MirrorRule {
+            from: "github.com".to_string(),
+            to: "git.internal.example.com".to_string(),
+        }];
+        let clone_info = apply_mirror(&info, &rules, Some("mirror.example.com"));
+        assert_eq!(
+            determine_clone_url(&clone_info, false, true).unwrap(),
+            "https://mirror.example.com/user/repo.git"
+        );
+    }
+
+    #[test]
+    fn test_submodule_update_args() {
+        assert_eq!(
+            submodule_update_args(false, None),
+            ["submodule", "update", "--init", "--recursive"]
+        );
+        assert_eq!(
+            submodule_update_args(true, None),
+            ["submodule", "update", "--init", "--recursive", "--depth", "1"]
+        );
+        assert_eq!(
+            submodule_update_args(false, Some(8)),
+            ["submodule", "update", "--init", "--recursive", "--jobs", "8"]
+        );
+    }
+
+    #[test]
+    fn test_choose_default_branch() {
+        let refs = [
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/develop",
+            "refs/remotes/origin/master",
+        ];
+        assert_eq!(choose_default_branch(refs.into_iter()).as_deref(), Some("master"));
+
+        let refs = ["refs/remotes/origin/feature/x", "refs/remotes/origin/develop"];
+        assert_eq!(choose_default_branch(refs.into_iter()).as_deref(), Some("develop"));
+
+        assert_eq!(choose_default_branch(std::iter::empty()), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_tag() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project_dir = tmp.path().join("github.com/user/repo");
+        fs::create_dir_all(&project_dir).unwrap();
+        let repo_info = RepoInfo {
+            host: "github.com".to_string(),
+            owner: "user".to_string(),
+            repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
+        };
+        let tag_root = tmp.path().join(TAG_DIR);
+
+        link_tag(&tag_root, "rust", &repo_info, &project_dir).unwrap();
+        // Re-tagging replaces the existing link
+        link_tag(&tag_root, "rust", &repo_info, &project_dir).unwrap();
+        let link = tag_root.join("rust/user/repo");
+        assert_eq!(fs::read_link(&link).unwrap(), project_dir);
+
+        assert!(link_tag(&tag_root, "a/b", &repo_info, &project_dir).is_err());
+    }
+
+    #[test]
+    fn test_render_path_template() {
+        let info = RepoInfo {
+            host: "github.com".to_string(),
+            owner: "user".to_string(),
+            repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
+        };
+        assert_eq!(
+            render_path_template("{owner}/{repo}@{host}", &info, None).unwrap(),
+            "user/repo@github.com"
+        );
+        assert_eq!(
+            render_path_template("{host}/{owner}/{repo}{suffix}", &info, Some(".work")).unwrap(),
+            "github.com/user/repo.work"
+        );
+        assert_eq!(
+            render_path_template("src/{repo}", &info, Some(".work")).unwrap(),
+            "src/repo.work"
+        );
+
+        assert!(render_path_template("{owner}", &info, None).is_err());
+        assert!(render_path_template("{org}/{repo}", &info, None).is_err());
+        assert!(render_path_template("../{repo}", &info, None).is_err());
+    }
+
+    #[test]
+    fn test_path_template_dir_is_still_checked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let info = RepoInfo {
+            host: "github.com".to_string(),
+            owner: "user".to_string(),
+            repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
+        };
+        let local_path = render_path_template("{owner}/{repo}@{host}", &info, None).unwrap();
+        let project_dir = tmp.path().join(local_path);
+        fs::create_dir_all(project_dir.join(".bare")).unwrap();
+        assert!(matches!(
+            check_project_dir(&project_dir, "github.com"),
+            Err(GhbareError::RepositoryAlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_into() {
+        assert_eq!(resolve_into("/tmp/review").unwrap(), PathBuf::from("/tmp/review"));
+        assert!(resolve_into("review").is_err());
+    }
+
+    #[test]
+    fn test_read_spec() {
+        let mut input = io::Cursor::new("\n  github.com/user/repo  \n\n");
+        assert_eq!(read_spec(&mut input).unwrap(), "github.com/user/repo");
+
+        let mut input = io::Cursor::new("\n");
+        assert!(read_spec(&mut input).unwrap_err().to_string().contains("No repository spec"));
+
+        let mut input = io::Cursor::new("user/a\nuser/b\n");
+        assert!(read_spec(&mut input).unwrap_err().to_string().contains("got 2"));
+    }
+
+    #[test]
+    fn test_head_worktree() {
+        let worktrees = vec![
+            WorktreeEntry {
+                path: PathBuf::from("/p/.bare"),
+                bare: true,
+                ..Default::default()
+            },
+            WorktreeEntry {
+                path: PathBuf::from("/p/develop"),
+                branch: Some("refs/heads/develop".to_string()),
+                ..Default::default()
+            },
+            WorktreeEntry {
+                path: PathBuf::from("/p/main"),
+                branch: Some("refs/heads/main".to_string()),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(head_worktree(&worktrees, Some("main")), Some(PathBuf::from("/p/main")));
+        assert_eq!(head_worktree(&worktrees, Some("gone")), Some(PathBuf::from("/p/develop")));
+        assert_eq!(head_worktree(&worktrees, None), Some(PathBuf::from("/p/develop")));
+        assert_eq!(head_worktree(&worktrees[..1], Some("main")), None);
+    }
+
+    #[test]
+    fn test_write_gitdir_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project_dir = tmp.path();
+        fs::create_dir(project_dir.join(".bare")).unwrap();
+        let git_file = project_dir.join(".git");
+
+        write_gitdir_file(project_dir).unwrap();
+        assert_eq!(fs::read_to_string(&git_file).unwrap(), "gitdir: .bare\n");
+
+        // An existing file pointing at .bare is left untouched
+        let absolute = format!("gitdir: {}\n", project_dir.join(".bare").display());
+        fs::write(&git_file, &absolute).unwrap();
+        write_gitdir_file(project_dir).unwrap();
+        assert_eq!(fs::read_to_string(&git_file).unwrap(), absolute);
+
+        fs::write(&git_file, "gitdir: elsewhere\n").unwrap();
+        write_gitdir_file(project_dir).unwrap();
+        assert_eq!(fs::read_to_string(&git_file).unwrap(), "gitdir: .bare\n");
+    }
+
+    #[test]
+    fn test_prune_other_remote_branches() {
+        let tmp = tempfile::tempdir().unwrap();
+        crate::commands::test_support::init_repo_with_commit(tmp.path());
+        let repo = Repository::open(tmp.path().join(".bare")).unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        for name in ["main", "develop", "feature/x"] {
+            repo.reference(&format!("refs/remotes/origin/{}", name), head, true, "test")
+                .unwrap();
+        }
+        repo.reference_symbolic("refs/remotes/origin/HEAD", "refs/remotes/origin/develop", true, "test")
+            .unwrap();
+
+        assert_eq!(prune_other_remote_branches(&repo, "main").unwrap(), 2);
+        let left: Vec<String> = repo
+            .references_glob("refs/remotes/*")
+            .unwrap()
+            .map(|r| r.unwrap().name().unwrap().to_string())
+            .collect();
+        assert_eq!(left, vec!["refs/remotes/origin/main"]);
+        assert!(repo.find_reference("refs/heads/main").is_ok());
+    }
+
+    #[test]
+    fn test_ensure_worktree_with_empty_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.git");
+        git2::Repository::init_bare(&source).unwrap();
+
+        let project_dir = tmp.path().join("project");
+        fs::create_dir_all(&project_dir).unwrap();
+        let url = format!("file://{}", source.display());
+        git::bare_clone(&url, &project_dir.join(".bare"), &Default::default()).unwrap();
+        fs::write(project_dir.join(".git"), "gitdir: .bare\n").unwrap();
+
+        ensure_worktree(&project_dir).unwrap();
+        assert!(list_worktrees(&project_dir)
+            .unwrap()
+            .iter()
+            .all(|w| w.bare));
+    }
+
+    #[test]
+    fn test_expand_short_spec_one_segment() {
+        assert_eq!(
+            expand_short_spec("repo", Some("github.com"), Some("myorg")).unwrap(),
+            "github.com/myorg/repo"
+        );
+        assert!(expand_short_spec("repo", Some("github.com"), None).is_err());
+        assert!(expand_short_spec("repo", None, Some("myorg")).is_err());
+    }
+
+    #[test]
+    fn test_expand_short_spec_two_segments() {
+        assert_eq!(
+            expand_short_spec("user/repo", Some("gitlab.com"), None).unwrap(),
+            "gitlab.com/user/repo"
+        );
+        assert_eq!(expand_short_spec("user/repo", None, None).unwrap(), "user/repo");
+    }
+
+    #[test]
+    fn test_clone_options_depth() {
+        let opts = GetOptions {
+            https: true,
+            depth: Some(50),
+            ..Default::default()
+        };
+        let clone_opts = clone_options(&opts);
+        assert_eq!(clone_opts.depth, Some(50));
+        // Unlike --single-commit, all branches and tags are still fetched
+        assert!(!clone_opts.single_branch);
+        assert!(!clone_opts.no_tags);
+        assert_eq!(clone_options(&GetOptions::default()).depth, None);
+
+        let info = parse_repo_url("github.com/user/repo").unwrap();
+        assert_eq!(
+            determine_clone_url(&info, opts.ssh, opts.https).unwrap(),
+            "https://github.com/user/repo.git"
+        );
+    }
+
+    #[test]
+    fn test_choose_host() {
+        let cfg: config::Config = toml::from_str(
+            "root = \"/r\"\n[[host]]\nname = \"github.com\"\n[[host]]\nname = \"gitlab.com\"\n",
+        )
+        .unwrap();
+        let hosts = cfg.host;
+
+        let err = choose_host("user/repo", &hosts, false, false).unwrap_err();
+        assert!(err.to_string().contains("(github.com, gitlab.com): pass --host"));
+        assert!(choose_host("repo", &hosts, true, false).is_err());
+
+        // Nothing to choose: the spec has a host, or lacks an owner anyway
+        assert_eq!(choose_host("github.com/user/repo", &hosts, false, false).unwrap(), None);
+        assert_eq!(choose_host("git@github.com:user/repo", &hosts, false, false).unwrap(), None);
+        assert_eq!(choose_host("repo", &hosts, false, false).unwrap(), None);
+        // A single configured host is not ambiguous
+        assert_eq!(choose_host("user/repo", &hosts[..1], false, false).unwrap(), None);
+    }
+
+    #[test]
+    fn test_short_spec_resolution_with_default_host() {
+        let spec = expand_short_spec("~sircmpwn/scdoc", Some("git.sr.ht"), None).unwrap();
+        let info = parse_repo_url(&spec).unwrap();
+        assert_eq!(info.to_local_path(), "git.sr.ht/~sircmpwn/scdoc");
+
+        let spec = expand_short_spec("gitlab.example.com/team/app", Some("git.sr.ht"), None).unwrap();
+        let info = parse_repo_url(&spec).unwrap();
+        assert_eq!(info.host, "gitlab.example.com");
+
+        // Without default_host a two-segment spec still needs a host
+        let spec = expand_short_spec("user/repo", None, None).unwrap();
+        assert!(matches!(
+            parse_repo_url(&spec),
+            Err(GhbareError::InvalidRepoUrl {
+                reason: crate::error::UrlErrorReason::MissingHost,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_expand_short_spec_keeps_full_specs() {
+        assert_eq!(
+            expand_short_spec("github.com/user/repo", Some("gitlab.com"), Some("o")).unwrap(),
+            "github.com/user/repo"
+        );
+        assert_eq!(
+            expand_short_spec("git@github.com:user/repo.git", Some("gitlab.com"), None).unwrap(),
+            "git@github.com:user/repo.git"
+        );
+        assert_eq!(
+            expand_short_spec("https://github.com/user/repo", Some("gitlab.com"), None).unwrap(),
+            "https://github.com/user/repo"
+        );
+    }
+
+    #[test]
+    fn test_local_path_omit_host() {
+        let mut cfg: config::Config = toml::from_str("root = \"/repos\"\nomit_host = true\n").unwrap();
+        let info = parse_repo_url("github.com/user/repo").unwrap();
+        assert_eq!(local_path(&cfg, &info, None).unwrap(), "user/repo");
+        assert_eq!(local_path(&cfg, &info, Some(".work")).unwrap(), "user/repo.work");
+
+        // path_template takes precedence
+        cfg.path_template = Some("{host}/{repo}".to_string());
+        assert_eq!(local_path(&cfg, &info, None).unwrap(), "github.com/repo");
+    }
+
+    #[test]
+    fn test_parse_dir_mode() {
+        assert_eq!(parse_dir_mode("0750").unwrap(), 0o750);
+        assert_eq!(parse_dir_mode("2775").unwrap(), 0o2775);
+        assert!(parse_dir_mode("0790").is_err());
+        assert!(parse_dir_mode("rwx").is_err());
+        assert!(parse_dir_mode("17777").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_dir_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let tmp = tempfile::tempdir().unwrap();
+        let bare = tmp.path().join(".bare");
+        fs::create_dir(&bare).unwrap();
+
+        apply_dir_mode(&[tmp.path(), &bare], 0o750).unwrap();
+        for dir in [tmp.path(), bare.as_path()] {
+            assert_eq!(fs::metadata(dir).unwrap().permissions().mode() & 0o7777, 0o750);
+        }
+    }
+
+    #[test]
+    fn test_check_project_dir_host_collision() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project_dir = tmp.path().join("user/repo");
+        let repo = Repository::init_bare(project_dir.join(".bare")).unwrap();
+        repo.remote("origin", "https://gitlab.com/user/repo.git").unwrap();
+
+        assert!(matches!(
+            check_project_dir(&project_dir, "gitlab.com"),
+            Err(GhbareError::RepositoryAlreadyExists(_))
+        ));
+        let err = check_project_dir(&project_dir, "github.com").unwrap_err();
+        assert!(matches!(err, GhbareError::HostCollision { .. }));
+        assert!(err.to_string().contains("already holds a clone from gitlab.com"));
+    }
+
+    #[test]
+    fn test_check_project_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project_dir = tmp.path().join("repo");
+        assert!(check_project_dir(&project_dir, "github.com").is_ok());
+
+        fs::create_dir_all(&project_dir).unwrap();
+        assert!(matches!(
+            check_project_dir(&project_dir, "github.com"),
+            Err(GhbareError::PathOccupied(_))
+        ));
+
+        fs::create_dir_all(project_dir.join(".bare")).unwrap();
+        assert!(matches!(
+            check_project_dir(&project_dir, "github.com"),
+            Err(GhbareError::RepositoryAlreadyExists(_))
+        ));
+    }
+
     #[test]
     fn test_determine_clone_url_both_error() {
         let info = RepoInfo {
             host: "github.com".to_string(),
             owner: "user".to_string(),
             repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
         };
         let result = determine_clone_url(&info, true, true);
         assert!(result.is_err());
@@ -0,0 +1,195 @@
+use super::bw::find_repo_root;
+use super::get::{local_path, parse_spec};
+use super::metadata::{self, CloneMetadata};
+use crate::config::{self, Config};
+use crate::error::GhbareError;
+use crate::url::{parse_repo_url, RepoInfo};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+struct RepoSummary {
+    host: String,
+    owner: String,
+    repo: String,
+    ssh_url: String,
+    https_url: String,
+    path: String,
+    cloned: bool,
+    /// From `.bw.toml` when run inside a repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clone_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clone_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cloned_at: Option<u64>,
+}
+
+/// Resolves `spec` like `bw get` would, without touching the network or disk.
+/// Without `spec`, describes the current repository from its `.bw.toml`.
+pub fn execute(spec: Option<&str>, json: bool) -> anyhow::Result<()> {
+    let summary = match spec {
+        Some(spec) => resolve(spec)?,
+        None => {
+            let repo_root = find_repo_root()?;
+            let metadata = metadata::read(&repo_root)?.ok_or_else(|| {
+                GhbareError::ConfigNotFound(format!(
+                    "{}/.bw.toml (set write_clone_metadata = true before cloning, or pass a spec)",
+                    repo_root.display()
+                ))
+            })?;
+            from_metadata(&repo_root, metadata)
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string(&summary)?);
+    } else {
+        print!("{}", render(&summary));
+    }
+    Ok(())
+}
+
+fn resolve(spec: &str) -> anyhow::Result<RepoSummary> {
+    let cfg = config::get_config()?;
+    let repo_info = parse_spec(
+        spec,
+        &cfg.host,
+        cfg.default_host.as_deref(),
+        cfg.default_owner.as_deref(),
+    )?;
+    summarize(&cfg, &repo_info)
+}
+
+fn from_metadata(repo_root: &Path, metadata: CloneMetadata) -> RepoSummary {
+    // Ports come from the URL actually cloned, each kept for its own scheme
+    let ports = parse_repo_url(&metadata.clone_url).ok();
+    let repo_info = RepoInfo {
+        host: metadata.host,
+        owner: metadata.owner,
+        repo: metadata.repo,
+        ssh_port: ports.as_ref().and_then(|i| i.ssh_port),
+        https_port: ports.as_ref().and_then(|i| i.https_port),
+    };
+    RepoSummary {
+        ssh_url: repo_info.to_ssh_url(),
+        https_url: repo_info.to_https_url(),
+        host: repo_info.host,
+        owner: repo_info.owner,
+        repo: repo_info.repo,
+        path: repo_root.display().to_string(),
+        cloned: true,
+        clone_url: Some(metadata.clone_url),
+        clone_method: Some(metadata.clone_method),
+        cloned_at: Some(metadata.cloned_at),
+    }
+}
+
+fn summarize(cfg: &Config, repo_info: &RepoInfo) -> anyhow::Result<RepoSummary> {
+    let path = config::expand_tilde(&cfg.root).join(local_path(
+        cfg,
+        repo_info,
+        cfg.suffix.as_deref(),
+    )?);
+    Ok(RepoSummary {
+        host: repo_info.host.clone(),
+        owner: repo_info.owner.clone(),
+        repo: repo_info.repo.clone(),
+        ssh_url: repo_info.to_ssh_url(),
+        https_url: repo_info.to_https_url(),
+        cloned: Path::new(&path).join(".bare").is_dir(),
+        path: path.display().to_string(),
+        clone_url: None,
+        clone_method: None,
+        cloned_at: None,
+    })
+}
+
+fn render(summary: &RepoSummary) -> String {
+    let cloned_at = summary.cloned_at.map(|t| t.to_string());
+    let mut lines = vec![
+        ("host", summary.host.as_str()),
+        ("owner", &summary.owner),
+        ("repo", &summary.repo),
+        ("ssh", &summary.ssh_url),
+        ("https", &summary.https_url),
+        ("path", &summary.path),
+        ("cloned", if summary.cloned { "yes" } else { "no" }),
+    ];
+    lines.extend(
+        [
+            ("url", summary.clone_url.as_deref()),
+            ("method", summary.clone_method.as_deref()),
+            ("time", cloned_at.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(k, v)| v.map(|v| (k, v))),
+    );
+    lines
+        .iter()
+        .map(|(k, v)| format!("{:<7}{}\n", k, v))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let cfg: Config = toml::from_str("root = \"/repos\"\nsuffix = \".work\"\n").unwrap();
+        let info = parse_repo_url("git@github.com:user/repo.git").unwrap();
+        let summary = summarize(&cfg, &info).unwrap();
+
+        assert_eq!(summary.path, "/repos/github.com/user/repo.work");
+        assert_eq!(summary.ssh_url, "git@github.com:user/repo.git");
+        assert!(!summary.cloned);
+        assert_eq!(
+            render(&summary),
+            "host   github.com\nowner  user\nrepo   repo\nssh    git@github.com:user/repo.git\n\
+             https  https://github.com/user/repo.git\npath   /repos/github.com/user/repo.work\ncloned no\n"
+        );
+    }
+
+    #[test]
+    fn test_from_metadata() {
+        let metadata = CloneMetadata {
+            host: "github.com".to_string(),
+            owner: "user".to_string(),
+            repo: "repo".to_string(),
+            clone_url: "https://github.com/user/repo.git".to_string(),
+            clone_method: "https".to_string(),
+            cloned_at: 1_700_000_000,
+        };
+        let summary = from_metadata(Path::new("/repos/github.com/user/repo"), metadata);
+        assert!(summary.cloned);
+        assert!(render(&summary).ends_with(
+            "cloned yes\nurl    https://github.com/user/repo.git\nmethod https\ntime   1700000000\n"
+        ));
+    }
+
+    #[test]
+    fn test_from_metadata_port_stays_with_its_scheme() {
+        let metadata = CloneMetadata {
+            host: "git.internal".to_string(),
+            owner: "g".to_string(),
+            repo: "p".to_string(),
+            clone_url: "https://git.internal:8443/g/p.git".to_string(),
+            clone_method: "https".to_string(),
+            cloned_at: 1_700_000_000,
+        };
+        let summary = from_metadata(Path::new("/repos/git.internal/g/p"), metadata);
+        assert_eq!(summary.https_url, "https://git.internal:8443/g/p.git");
+        assert_eq!(summary.ssh_url, "git@git.internal:g/p.git");
+    }
+
+    #[test]
+    fn test_summarize_with_path_template() {
+        let cfg: Config =
+            toml::from_str("root = \"/repos\"\npath_template = \"{owner}/{repo}@{host}\"\n").unwrap();
+        let info = parse_repo_url("github.com/user/repo").unwrap();
+        let json = serde_json::to_value(summarize(&cfg, &info).unwrap()).unwrap();
+        assert_eq!(json["path"], "/repos/user/repo@github.com");
+        assert_eq!(json["https_url"], "https://github.com/user/repo.git");
+    }
+}
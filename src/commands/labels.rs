@@ -0,0 +1,145 @@
+use super::bw::{find_repo_root, worktree_target, WorktreeEntry};
+use super::lock::RepoLock;
+use crate::error::GhbareError;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LABELS_FILE: &str = "bw-labels.json";
+
+/// Label key (see [`key`]) -> label, stored in `.bare/bw-labels.json`.
+pub(crate) type Labels = BTreeMap<String, String>;
+
+fn labels_path(repo_root: &Path) -> PathBuf {
+    repo_root.join(".bare").join(LABELS_FILE)
+}
+
+/// Key of the worktree at `worktree_path`: its directory name when it sits
+/// directly under `repo_root`, else its absolute path, so that `bw add --path`
+/// worktrees sharing a last component don't share a label.
+pub(crate) fn key(repo_root: &Path, worktree_path: &Path) -> String {
+    let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let path = canonical(worktree_path);
+    match path.file_name() {
+        Some(name) if path.parent() == Some(canonical(repo_root).as_path()) => {
+            name.to_string_lossy().into_owned()
+        }
+        _ => path.display().to_string(),
+    }
+}
+
+/// Reads all labels. A missing file is treated as empty.
+pub(crate) fn load(repo_root: &Path) -> Result<Labels, GhbareError> {
+    let path = labels_path(repo_root);
+    if !path.exists() {
+        return Ok(Labels::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| GhbareError::ConfigParseError(format!("{}: {}", path.display(), e)))
+}
+
+/// Sets the label under `key`, or removes it when `label` is `None`.
+/// Callers must hold the `RepoLock`.
+pub(crate) fn set(repo_root: &Path, key: &str, label: Option<&str>) -> Result<(), GhbareError> {
+    let mut labels = load(repo_root)?;
+    let changed = match label {
+        Some(label) => {
+            labels
+                .insert(key.to_string(), label.to_string())
+                .as_deref()
+                != Some(label)
+        }
+        None => labels.remove(key).is_some(),
+    };
+    if !changed {
+        return Ok(());
+    }
+    let content = serde_json::to_string_pretty(&labels)
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    fs::write(labels_path(repo_root), content + "\n")?;
+    Ok(())
+}
+
+/// Fills `label` of each worktree from `labels`, matching on [`key`].
+pub(crate) fn attach(repo_root: &Path, labels: &Labels, worktrees: &mut [WorktreeEntry]) {
+    for worktree in worktrees.iter_mut().filter(|w| !w.bare) {
+        worktree.label = labels.get(&key(repo_root, &worktree.path)).cloned();
+    }
+}
+
+/// `bw label <name> [text]`: prints the label, or sets / clears it.
+pub fn execute(name: &str, text: Option<&str>, clear: bool) -> anyhow::Result<()> {
+    let repo_root = find_repo_root()?;
+    let worktree_path = worktree_target(&repo_root, name);
+    if !worktree_path.exists() {
+        return Err(GhbareError::WorktreeError(format!("Worktree not found: {}", name)).into());
+    }
+    let label_key = key(&repo_root, &worktree_path);
+
+    if text.is_none() && !clear {
+        if let Some(label) = load(&repo_root)?.get(&label_key) {
+            println!("{}", label);
+        }
+        return Ok(());
+    }
+
+    let _lock = RepoLock::acquire(&repo_root)?;
+    set(&repo_root, &label_key, text.filter(|t| !t.is_empty()))?;
+    match text {
+        Some(text) if !text.is_empty() => eprintln!("Labeled {}: {}", label_key, text),
+        _ => eprintln!("Cleared label of {}", label_key),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join(".bare")).unwrap();
+        assert!(load(tmp.path()).unwrap().is_empty());
+
+        set(tmp.path(), "feature-x", Some("review PR 123")).unwrap();
+        set(tmp.path(), "spike", Some("caching")).unwrap();
+        set(tmp.path(), "spike", None).unwrap();
+
+        let labels = load(tmp.path()).unwrap();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels["feature-x"], "review PR 123");
+    }
+
+    #[test]
+    fn test_attach() {
+        let labels = Labels::from([
+            ("feature-x".to_string(), "review".to_string()),
+            ("/a/feature-x".to_string(), "external".to_string()),
+        ]);
+        let mut worktrees = vec![
+            WorktreeEntry {
+                path: PathBuf::from("/repo/feature-x"),
+                ..Default::default()
+            },
+            WorktreeEntry {
+                path: PathBuf::from("/repo/main"),
+                ..Default::default()
+            },
+            WorktreeEntry {
+                path: PathBuf::from("/a/feature-x"),
+                ..Default::default()
+            },
+            WorktreeEntry {
+                path: PathBuf::from("/b/feature-x"),
+                ..Default::default()
+            },
+        ];
+        attach(Path::new("/repo"), &labels, &mut worktrees);
+        assert_eq!(worktrees[0].label.as_deref(), Some("review"));
+        assert_eq!(worktrees[1].label, None);
+        assert_eq!(worktrees[2].label.as_deref(), Some("external"));
+        assert_eq!(worktrees[3].label, None);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::error::GhbareError;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const LOCK_FILE: &str = "bw.lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Advisory lock (`flock`) on `.bare/bw.lock` serializing worktree changes across
+/// bw processes. Released when dropped, and by the OS when the process dies
+/// (Ctrl-C, kill), so a leftover file never blocks later commands. The file
+/// itself stays; removing it would let two processes lock different files.
+#[derive(Debug)]
+pub struct RepoLock {
+    _file: File,
+}
+
+impl RepoLock {
+    pub fn acquire(repo_root: &Path) -> Result<Self, GhbareError> {
+        Self::acquire_with_timeout(repo_root, LOCK_TIMEOUT)
+    }
+
+    fn acquire_with_timeout(repo_root: &Path, timeout: Duration) -> Result<Self, GhbareError> {
+        let path = repo_root.join(".bare").join(LOCK_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => {
+                    // Informational only: the PID of the current holder
+                    let _ = file.set_len(0);
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Ok(Self { _file: file });
+                }
+                Err(TryLockError::WouldBlock) => {
+                    if started.elapsed() >= timeout {
+                        return Err(GhbareError::RepoLocked(path.display().to_string()));
+                    }
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_lock_contention() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join(".bare")).unwrap();
+
+        let held = RepoLock::acquire(tmp.path()).unwrap();
+        let err = RepoLock::acquire_with_timeout(tmp.path(), Duration::from_millis(200))
+            .unwrap_err();
+        assert!(matches!(err, GhbareError::RepoLocked(_)));
+
+        let root = tmp.path().to_path_buf();
+        let waiter = std::thread::spawn(move || {
+            RepoLock::acquire_with_timeout(&root, Duration::from_secs(5)).map(|_| ())
+        });
+        std::thread::sleep(Duration::from_millis(300));
+        drop(held);
+        waiter.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_leftover_lock_file_is_not_held() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join(".bare")).unwrap();
+        // Left by a bw that was killed while holding the lock
+        fs::write(tmp.path().join(".bare").join(LOCK_FILE), "999999\n").unwrap();
+
+        RepoLock::acquire_with_timeout(tmp.path(), Duration::from_millis(200)).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use crate::error::GhbareError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Written next to `.bare`, outside every worktree, so it never shows up in
+/// `git status`. Not to be confused with the per-repository `bw.toml`.
+const METADATA_FILE: &str = ".bw.toml";
+
+/// How a repository was cloned, recorded by `bw get` when
+/// `write_clone_metadata = true`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CloneMetadata {
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    /// URL actually cloned from (after `[[mirror]]` / `--mirror-to`)
+    pub clone_url: String,
+    /// "ssh" or "https"
+    pub clone_method: String,
+    /// Unix timestamp (seconds)
+    pub cloned_at: u64,
+}
+
+fn metadata_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(METADATA_FILE)
+}
+
+pub(crate) fn write(project_dir: &Path, metadata: &CloneMetadata) -> Result<(), GhbareError> {
+    let content = toml::to_string(metadata)
+        .map_err(|e| GhbareError::ConfigParseError(e.to_string()))?;
+    fs::write(
+        metadata_path(project_dir),
+        format!("# Written by bw get\n{}", content),
+    )?;
+    Ok(())
+}
+
+/// Reads the metadata of `project_dir`; `None` when it was cloned without it.
+pub(crate) fn read(project_dir: &Path) -> Result<Option<CloneMetadata>, GhbareError> {
+    let path = metadata_path(project_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| GhbareError::ConfigParseError(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(read(tmp.path()).unwrap(), None);
+
+        let metadata = CloneMetadata {
+            host: "github.com".to_string(),
+            owner: "user".to_string(),
+            repo: "repo".to_string(),
+            clone_url: "git@github.com:user/repo.git".to_string(),
+            clone_method: "ssh".to_string(),
+            cloned_at: 1_700_000_000,
+        };
+        write(tmp.path(), &metadata).unwrap();
+        assert_eq!(read(tmp.path()).unwrap(), Some(metadata));
+    }
+}
@@ -1,3 +1,19 @@
 pub mod bw;
+pub mod completions;
 pub mod config;
+pub mod doctor;
+pub mod env;
+pub mod gc;
 pub mod get;
+pub mod info;
+pub mod labels;
+pub mod lock;
+pub mod metadata;
+pub mod mv_root;
+pub mod pager;
+pub mod reclone;
+pub mod repos;
+pub mod summary;
+pub mod tags;
+#[cfg(test)]
+pub mod test_support;
@@ -0,0 +1,425 @@
+use super::bw::{find_bare_repos, list_worktrees};
+use super::config::set_value;
+use super::get::TAG_DIR;
+use crate::config::{self, expand_tilde, resolve_config_path};
+use crate::error::GhbareError;
+use crate::git::git_cmd;
+use crate::index::{get_index_path, read_entries, write_entries, IndexEntry};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A repository directory to move, with the worktree paths to repair afterwards.
+#[derive(Debug, PartialEq, Eq)]
+struct RepoMove {
+    from: PathBuf,
+    to: PathBuf,
+    worktrees: Vec<PathBuf>,
+}
+
+pub fn execute(new_root: &str, dry_run: bool) -> anyhow::Result<()> {
+    let root = config::get_root()?;
+    let new_root_path = expand_tilde(new_root);
+
+    if !new_root_path.is_absolute() {
+        return Err(GhbareError::WorktreeError(format!(
+            "New root must be an absolute path: {}",
+            new_root
+        ))
+        .into());
+    }
+    if new_root_path.starts_with(&root) || root.starts_with(&new_root_path) {
+        return Err(GhbareError::WorktreeError(
+            "New root must not be inside the current root (or vice versa)".to_string(),
+        )
+        .into());
+    }
+
+    let mut moves = Vec::new();
+    for repo in find_bare_repos(&root)? {
+        let worktrees = list_worktrees(&repo)?
+            .into_iter()
+            .filter(|w| !w.bare)
+            .map(|w| w.path)
+            .collect();
+        moves.push(plan_move(&root, &new_root_path, &repo, worktrees));
+    }
+
+    let tag_root = root.join(TAG_DIR);
+    let new_tag_root = new_root_path.join(TAG_DIR);
+    let has_tags = tag_root.is_dir();
+    for to in moves.iter().map(|m| &m.to).chain(has_tags.then_some(&new_tag_root)) {
+        if to.exists() {
+            return Err(GhbareError::PathOccupied(to.display().to_string()).into());
+        }
+    }
+
+    for m in &moves {
+        eprintln!("{} -> {}", m.from.display(), m.to.display());
+    }
+    if has_tags {
+        eprintln!("{} -> {}", tag_root.display(), new_tag_root.display());
+    }
+    if dry_run {
+        eprintln!("\nDry run: {} repositories would be moved", moves.len());
+        return Ok(());
+    }
+
+    let tags = has_tags.then_some((tag_root.as_path(), new_tag_root.as_path()));
+    move_all(&moves, &root, &new_root_path, tags)?;
+    let index_path = get_index_path()?;
+    if index_path.exists() {
+        let mut entries = read_entries(&index_path)?;
+        if rewrite_index_paths(&mut entries, &moves) > 0 {
+            write_entries(&index_path, &entries)?;
+            eprintln!("Updated repository paths in {}", index_path.display());
+        }
+    }
+
+    let config_path = resolve_config_path()?;
+    let content = fs::read_to_string(&config_path)?;
+    fs::write(&config_path, set_value(&content, "root", new_root)?)?;
+    eprintln!("Updated root in {}", config_path.display());
+
+    eprintln!("\nDone! Moved {} repositories to {}", moves.len(), new_root_path.display());
+
+    Ok(())
+}
+
+/// Moves the repositories, then the tag directory (`tags`: from, to) with its
+/// links. A failure in either step puts everything back, so `root` stays valid.
+fn move_all(
+    moves: &[RepoMove],
+    root: &Path,
+    new_root: &Path,
+    tags: Option<(&Path, &Path)>,
+) -> Result<(), GhbareError> {
+    move_repos(moves, root, new_root)?;
+    let Some((tag_root, new_tag_root)) = tags else {
+        return Ok(());
+    };
+    let Err(e) = move_tags(tag_root, new_tag_root, moves) else {
+        return Ok(());
+    };
+    let mut left_behind = Vec::new();
+    if let Err(e) = put_tags_back(tag_root, new_tag_root, moves) {
+        left_behind.push(format!("  {}: {}", new_tag_root.display(), e));
+    }
+    left_behind.extend(put_back(moves, root, new_root));
+    Err(rollback_error(e, root, &left_behind))
+}
+
+/// Moves every repository, or none: after a failure the ones already moved
+/// are moved back, so `root` stays valid.
+fn move_repos(moves: &[RepoMove], root: &Path, new_root: &Path) -> Result<(), GhbareError> {
+    for (i, m) in moves.iter().enumerate() {
+        let Err(e) = move_repo(m) else {
+            continue;
+        };
+        // A failed repair leaves this one moved as well
+        let done = if m.to.exists() { i + 1 } else { i };
+        let left_behind = put_back(&moves[..done], root, new_root);
+        return Err(rollback_error(e, root, &left_behind));
+    }
+    Ok(())
+}
+
+/// Moves `moves` back from `new_root` to `root`; returns those that could not be.
+fn put_back(moves: &[RepoMove], root: &Path, new_root: &Path) -> Vec<String> {
+    let mut left_behind = Vec::new();
+    for m in moves.iter().rev() {
+        let back = plan_move(new_root, root, &m.to, m.worktrees.clone());
+        if let Err(e) = move_repo(&back) {
+            left_behind.push(format!("  {}: {}", m.to.display(), e));
+        }
+    }
+    left_behind
+}
+
+fn rollback_error(e: GhbareError, root: &Path, left_behind: &[String]) -> GhbareError {
+    if left_behind.is_empty() {
+        return GhbareError::WorktreeError(format!(
+            "{}\nMoved repositories were put back; root was not changed",
+            e
+        ));
+    }
+    GhbareError::WorktreeError(format!(
+        "{}\nThese could not be moved back to {} (root was not changed):\n{}",
+        e,
+        root.display(),
+        left_behind.join("\n")
+    ))
+}
+
+fn move_tags(tag_root: &Path, new_tag_root: &Path, moves: &[RepoMove]) -> Result<(), GhbareError> {
+    fs::rename(tag_root, new_tag_root)?;
+    relink_tags(new_tag_root, moves)
+}
+
+/// Undoes `move_tags`, including a partial relink.
+fn put_tags_back(
+    tag_root: &Path,
+    new_tag_root: &Path,
+    moves: &[RepoMove],
+) -> Result<(), GhbareError> {
+    // Still in place: the rename itself failed
+    if tag_root.exists() {
+        return Ok(());
+    }
+    let back: Vec<RepoMove> = moves
+        .iter()
+        .map(|m| RepoMove {
+            from: m.to.clone(),
+            to: m.from.clone(),
+            worktrees: Vec::new(),
+        })
+        .collect();
+    relink_tags(new_tag_root, &back)?;
+    fs::rename(new_tag_root, tag_root)?;
+    Ok(())
+}
+
+fn move_repo(m: &RepoMove) -> Result<(), GhbareError> {
+    if let Some(parent) = m.to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&m.from, &m.to).map_err(|e| {
+        GhbareError::WorktreeError(format!(
+            "Failed to move {} (moving across filesystems is not supported): {}",
+            m.from.display(),
+            e
+        ))
+    })?;
+    repair_worktrees(&m.to, &m.worktrees)
+}
+
+/// Points the `<tag>/<owner>/<repo>` links of `bw get --tag` at the moved repositories.
+fn relink_tags(tag_root: &Path, moves: &[RepoMove]) -> Result<(), GhbareError> {
+    for entry in fs::read_dir(tag_root)? {
+        let path = entry?.path();
+        let metadata = path.symlink_metadata()?;
+        if metadata.is_dir() {
+            relink_tags(&path, moves)?;
+            continue;
+        }
+        if !metadata.file_type().is_symlink() {
+            continue;
+        }
+        // Tag links are only created on Unix (see get::link_tag)
+        #[cfg(unix)]
+        {
+            let target = fs::read_link(&path)?;
+            if let Some(m) = moves.iter().find(|m| m.from == target) {
+                fs::remove_file(&path)?;
+                std::os::unix::fs::symlink(&m.to, &path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the paths of moved repositories in the index; returns how many changed.
+fn rewrite_index_paths(entries: &mut [IndexEntry], moves: &[RepoMove]) -> usize {
+    let mut changed = 0;
+    for entry in entries.iter_mut() {
+        if let Some(m) = moves.iter().find(|m| m.from == Path::new(&entry.path)) {
+            entry.path = m.to.display().to_string();
+            changed += 1;
+        }
+    }
+    changed
+}
+
+fn plan_move(root: &Path, new_root: &Path, repo: &Path, worktrees: Vec<PathBuf>) -> RepoMove {
+    let to = new_root.join(repo.strip_prefix(root).unwrap_or(repo));
+    // Worktrees inside the repo directory move along with it; external ones stay put
+    let worktrees = worktrees
+        .into_iter()
+        .map(|w| match w.strip_prefix(repo) {
+            Ok(rel) => to.join(rel),
+            Err(_) => w,
+        })
+        .collect();
+    RepoMove {
+        from: repo.to_path_buf(),
+        to,
+        worktrees,
+    }
+}
+
+/// Rewrites the absolute gitdir links between the bare repo and its worktrees.
+fn repair_worktrees(repo: &Path, worktrees: &[PathBuf]) -> Result<(), GhbareError> {
+    if worktrees.is_empty() {
+        return Ok(());
+    }
+    let status = git_cmd()
+        .args(["worktree", "repair"])
+        .args(worktrees)
+        .current_dir(repo)
+        .status()
+        .map_err(|e| GhbareError::WorktreeError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::WorktreeError(format!(
+            "git worktree repair failed in {}",
+            repo.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_support::init_repo_with_commit;
+    use std::process::Command;
+
+    #[test]
+    fn test_plan_move() {
+        let m = plan_move(
+            Path::new("/old"),
+            Path::new("/new"),
+            Path::new("/old/github.com/user/repo"),
+            vec![
+                PathBuf::from("/old/github.com/user/repo/main"),
+                PathBuf::from("/tmp/review"),
+            ],
+        );
+        assert_eq!(
+            m,
+            RepoMove {
+                from: PathBuf::from("/old/github.com/user/repo"),
+                to: PathBuf::from("/new/github.com/user/repo"),
+                worktrees: vec![
+                    PathBuf::from("/new/github.com/user/repo/main"),
+                    PathBuf::from("/tmp/review"),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_move_and_repair() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        let repo = root.join("old").join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        init_repo_with_commit(&repo);
+        let status = Command::new("git")
+            .args(["worktree", "add", "main", "main"])
+            .current_dir(&repo)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let m = plan_move(
+            &root.join("old"),
+            &root.join("new"),
+            &repo,
+            vec![repo.join("main")],
+        );
+        fs::create_dir_all(root.join("new")).unwrap();
+        fs::rename(&m.from, &m.to).unwrap();
+        repair_worktrees(&m.to, &m.worktrees).unwrap();
+
+        let output = Command::new("git")
+            .args(["rev-parse", "--abbrev-ref", "HEAD"])
+            .current_dir(m.to.join("main"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "main");
+    }
+
+    fn repo_move(from: &Path, to: &Path) -> RepoMove {
+        RepoMove {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            worktrees: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rewrite_index_paths() {
+        let entry = |path: &str| IndexEntry {
+            host: "github.com".to_string(),
+            owner: "user".to_string(),
+            repo: "repo".to_string(),
+            path: path.to_string(),
+            cloned_at: 0,
+            tags: Vec::new(),
+        };
+        let mut entries = vec![entry("/old/github.com/user/repo"), entry("/elsewhere/repo")];
+        let moves = [repo_move(
+            Path::new("/old/github.com/user/repo"),
+            Path::new("/new/github.com/user/repo"),
+        )];
+        assert_eq!(rewrite_index_paths(&mut entries, &moves), 1);
+        assert_eq!(entries[0].path, "/new/github.com/user/repo");
+        assert_eq!(entries[1].path, "/elsewhere/repo");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relink_tags() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let (from, to) = (root.join("old/repo"), root.join("new/repo"));
+        fs::create_dir_all(&to).unwrap();
+        let link = root.join("by-tag/work/user/repo");
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(&from, &link).unwrap();
+
+        relink_tags(&root.join("by-tag"), &[repo_move(&from, &to)]).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), to);
+    }
+
+    #[test]
+    fn test_move_repos_rolls_back_on_failure() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("old");
+        let new_root = tmp.path().join("new");
+        for repo in ["a/repo", "b/repo"] {
+            fs::create_dir_all(root.join(repo)).unwrap();
+        }
+        // b/repo cannot be moved: its destination parent is a file
+        fs::create_dir_all(&new_root).unwrap();
+        fs::write(new_root.join("b"), "").unwrap();
+
+        let moves = [
+            repo_move(&root.join("a/repo"), &new_root.join("a/repo")),
+            repo_move(&root.join("b/repo"), &new_root.join("b/repo")),
+        ];
+        let err = move_repos(&moves, &root, &new_root).unwrap_err();
+        assert!(err.to_string().contains("were put back"));
+        assert!(root.join("a/repo").is_dir());
+        assert!(root.join("b/repo").is_dir());
+        assert!(!new_root.join("a/repo").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_move_all_rolls_back_when_tags_fail() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("old");
+        let new_root = tmp.path().join("new");
+        let (from, to) = (root.join("a/repo"), new_root.join("a/repo"));
+        fs::create_dir_all(&from).unwrap();
+        let link = root.join("by-tag/work/a/repo");
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(&from, &link).unwrap();
+        // The tag directory cannot be moved onto a non-empty directory
+        fs::create_dir_all(new_root.join("by-tag/taken")).unwrap();
+
+        let err = move_all(
+            &[repo_move(&from, &to)],
+            &root,
+            &new_root,
+            Some((&root.join("by-tag"), &new_root.join("by-tag"))),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("were put back"), "{}", err);
+        assert!(from.is_dir());
+        assert!(!to.exists());
+        assert_eq!(fs::read_link(&link).unwrap(), from);
+    }
+}
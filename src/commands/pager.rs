@@ -0,0 +1,94 @@
+use std::fs::File;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+const DEFAULT_PAGER: &str = "less -FRX";
+
+/// Writes `text` to stdout, piping it through `$PAGER` when stdout is a
+/// terminal and the text is taller than the screen.
+pub fn print_paged(text: &str, no_pager: bool) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    if no_pager || !stdout.is_terminal() {
+        stdout.write_all(text.as_bytes())?;
+        return stdout.flush();
+    }
+
+    let height = terminal_height();
+    if !needs_paging(text, height) {
+        stdout.write_all(text.as_bytes())?;
+        return stdout.flush();
+    }
+
+    let pager = pager_command(std::env::var("PAGER").ok());
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(&pager)
+        .stdin(Stdio::piped())
+        .spawn();
+    let Ok(mut child) = child else {
+        stdout.write_all(text.as_bytes())?;
+        return stdout.flush();
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may quit before reading everything (e.g. `q` in less)
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// `$PAGER` if set and non-empty, otherwise `less -FRX`.
+fn pager_command(env: Option<String>) -> String {
+    env.filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string())
+}
+
+fn needs_paging(text: &str, height: Option<usize>) -> bool {
+    match height {
+        Some(height) => text.lines().count() > height,
+        None => false,
+    }
+}
+
+/// Rows of the controlling terminal, from `$LINES` or `stty size`.
+fn terminal_height() -> Option<usize> {
+    if let Some(lines) = std::env::var("LINES").ok().and_then(|l| l.parse().ok()) {
+        return Some(lines);
+    }
+
+    let tty = File::open("/dev/tty").ok()?;
+    let output = Command::new("stty")
+        .arg("size")
+        .stdin(tty)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pager_command() {
+        assert_eq!(pager_command(None), "less -FRX");
+        assert_eq!(pager_command(Some("  ".to_string())), "less -FRX");
+        assert_eq!(pager_command(Some("more".to_string())), "more");
+    }
+
+    #[test]
+    fn test_needs_paging() {
+        assert!(!needs_paging("a\nb\n", Some(2)));
+        assert!(needs_paging("a\nb\nc\n", Some(2)));
+        assert!(!needs_paging("a\nb\nc\n", None));
+    }
+}
@@ -0,0 +1,335 @@
+use super::bw::{
+    add_worktree, branch_exists, branch_to_dirname, find_repo_root, list_worktrees,
+    remote_branch_exists, WorktreeAddFlags,
+};
+use crate::git::git_cmd;
+use super::doctor::confirm;
+use super::get::{self, GetOptions};
+use super::lock::RepoLock;
+use super::metadata;
+use crate::error::GhbareError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `bw reclone [--yes] [--force]`: recovers a repository whose `.bare` is
+/// broken. Records the worktree branches, clones again into the same directory
+/// with `bw get`, and recreates a worktree for each branch. Branches that
+/// origin may not have in full need `force`; the old directory is then kept.
+pub fn execute(yes: bool, force: bool) -> anyhow::Result<()> {
+    let repo_root = find_repo_root()?;
+    let lock = RepoLock::acquire(&repo_root)?;
+    let clone_url = recorded_clone_url(&repo_root)?;
+    let branches = recorded_branches(&repo_root)?;
+    let at_risk: Vec<(String, String)> = branches
+        .iter()
+        .filter_map(|b| unpushed_reason(&repo_root, b).map(|reason| (b.clone(), reason)))
+        .collect();
+
+    println!("Repository: {}", repo_root.display());
+    println!("Clone URL: {}", clone_url);
+    println!("Worktree branches: {}", display_list(&branches));
+    if !at_risk.is_empty() {
+        eprintln!("\nThese branches may lose commits when cloned again:");
+        for (branch, reason) in &at_risk {
+            eprintln!("  {}: {}", branch, reason);
+        }
+        if !force {
+            return Err(GhbareError::WorktreeError(
+                "Push them first, or pass --force to reclone anyway \
+                 (the old directory is then kept)"
+                    .to_string(),
+            )
+            .into());
+        }
+    }
+    let question = format!(
+        "Delete {} and everything in it (uncommitted changes included) and clone again?",
+        repo_root.display()
+    );
+    if !yes && !confirm(&question)? {
+        println!("Aborted");
+        return Ok(());
+    }
+    drop(lock);
+
+    // Keep the old directory until the new clone succeeds
+    let backup = backup_path(&repo_root);
+    fs::rename(&repo_root, &backup)?;
+    let into = repo_root.display().to_string();
+    let opts = GetOptions {
+        https: clone_url.starts_with("https://"),
+        into: Some(into),
+        ..Default::default()
+    };
+    if let Err(e) = get::execute(Some(&clone_url), opts) {
+        if repo_root.exists() {
+            fs::remove_dir_all(&repo_root)?;
+        }
+        fs::rename(&backup, &repo_root)?;
+        eprintln!("Clone failed; restored {}", repo_root.display());
+        return Err(e);
+    }
+
+    let _lock = RepoLock::acquire(&repo_root)?;
+    restore_root_files(&backup, &repo_root)?;
+    let failed = recreate_worktrees(&repo_root, &branches)?;
+    if failed.is_empty() && at_risk.is_empty() {
+        fs::remove_dir_all(&backup)?;
+        println!("\nDone! Recreated worktrees for {} branch(es)", branches.len());
+        return Ok(());
+    }
+    if failed.is_empty() {
+        println!("\nDone! Recreated worktrees for {} branch(es)", branches.len());
+        eprintln!("The old directory was kept: {}", backup.display());
+        return Ok(());
+    }
+    eprintln!("\nCould not recreate {} worktree(s):", failed.len());
+    for (branch, reason) in &failed {
+        eprintln!("  {}: {}", branch, reason);
+    }
+    eprintln!("The old directory was kept: {}", backup.display());
+    Err(GhbareError::WorktreeError(format!(
+        "{} branch(es) were not recreated",
+        failed.len()
+    ))
+    .into())
+}
+
+/// Why `branch` may not come back in full from origin: it is missing there,
+/// has commits origin lacks, or cannot be compared (e.g. broken refs).
+fn unpushed_reason(repo_root: &Path, branch: &str) -> Option<String> {
+    let remote = format!("origin/{}", branch);
+    if !remote_branch_exists(repo_root, branch) {
+        return Some(format!("{} not found", remote));
+    }
+    if !branch_exists(repo_root, branch) {
+        return None;
+    }
+    let output = git_cmd()
+        .args([
+            "rev-list",
+            "--count",
+            &format!("refs/remotes/{}..refs/heads/{}", remote, branch),
+        ])
+        .current_dir(repo_root)
+        .output()
+        .ok()
+        .filter(|o| o.status.success());
+    let Some(output) = output else {
+        return Some(format!("cannot compare with {}", remote));
+    };
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "0" => None,
+        count => Some(format!("{} commit(s) not on {}", count, remote)),
+    }
+}
+
+/// Moves root-level files such as `bw.toml` from the old directory into the
+/// new clone. `.bare`, worktrees (directories with a `.git`) and anything the
+/// clone already created are left in the backup.
+fn restore_root_files(backup: &Path, repo_root: &Path) -> Result<(), GhbareError> {
+    for entry in fs::read_dir(backup)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let target = repo_root.join(name);
+        if name == ".bare" || target.exists() || path.join(".git").exists() {
+            continue;
+        }
+        fs::rename(&path, &target)?;
+        println!("Restored {}", target.display());
+    }
+    Ok(())
+}
+
+/// URL from `.bw.toml`, else `remote.origin.url` read straight from
+/// `.bare/config` so a broken object store does not matter.
+fn recorded_clone_url(repo_root: &Path) -> Result<String, GhbareError> {
+    if let Some(metadata) = metadata::read(repo_root)? {
+        return Ok(metadata.clone_url);
+    }
+    git2::Config::open(&repo_root.join(".bare").join("config"))
+        .and_then(|c| c.get_string("remote.origin.url"))
+        .map_err(|e| {
+            GhbareError::CloneError(format!(
+                "Cannot find the clone URL (remote.origin.url): {}",
+                e.message()
+            ))
+        })
+}
+
+/// Branches checked out in worktrees, read from `.bare/worktrees/*/HEAD`
+/// without git. Detached worktrees are skipped.
+fn recorded_branches(repo_root: &Path) -> Result<Vec<String>, GhbareError> {
+    let admin_root = repo_root.join(".bare").join("worktrees");
+    if !admin_root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut branches = Vec::new();
+    for entry in fs::read_dir(&admin_root)? {
+        let Ok(head) = fs::read_to_string(entry?.path().join("HEAD")) else {
+            continue;
+        };
+        if let Some(branch) = head.trim().strip_prefix("ref: refs/heads/") {
+            branches.push(branch.to_string());
+        }
+    }
+    branches.sort();
+    branches.dedup();
+    Ok(branches)
+}
+
+/// Sibling of `repo_root` that does not exist yet.
+fn backup_path(repo_root: &Path) -> PathBuf {
+    let name = repo_root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (0..)
+        .map(|i| repo_root.with_file_name(format!("{}.bw-reclone-{}", name, i)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// Adds a worktree for each branch that has none yet. Branches missing both
+/// locally and on origin (e.g. never pushed or deleted remotely) are returned
+/// with the reason.
+fn recreate_worktrees(
+    repo_root: &Path,
+    branches: &[String],
+) -> Result<Vec<(String, String)>, GhbareError> {
+    let existing: Vec<String> = list_worktrees(repo_root)?
+        .iter()
+        .filter_map(|w| w.short_branch().map(str::to_string))
+        .collect();
+    let mut failed = Vec::new();
+    for branch in branches.iter().filter(|b| !existing.contains(b)) {
+        let path = repo_root.join(branch_to_dirname(branch));
+        let remote = format!("origin/{}", branch);
+        let track = !branch_exists(repo_root, branch);
+        if track && !remote_branch_exists(repo_root, branch) {
+            failed.push((branch.clone(), format!("{} not found", remote)));
+            continue;
+        }
+        println!("Recreating worktree: {} (branch: {})", path.display(), branch);
+        let flags = WorktreeAddFlags {
+            track,
+            ..Default::default()
+        };
+        if let Err(e) = add_worktree(repo_root, &path, branch, &remote, &flags) {
+            failed.push((branch.clone(), e.to_string()));
+        }
+    }
+    Ok(failed)
+}
+
+fn display_list(items: &[String]) -> String {
+    if items.is_empty() {
+        "(none)".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_support::init_repo_with_commit;
+
+    #[test]
+    fn test_recorded_branches() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        init_repo_with_commit(root);
+        let flags = WorktreeAddFlags::default();
+        add_worktree(root, &root.join("main"), "main", "main", &flags).unwrap();
+        add_worktree(root, &root.join("feature-x"), "feature/x", "main", &flags).unwrap();
+        git_cmd()
+            .args(["worktree", "add", "--detach", "detached", "main"])
+            .current_dir(root)
+            .output()
+            .unwrap();
+
+        assert_eq!(recorded_branches(root).unwrap(), vec!["feature/x", "main"]);
+    }
+
+    #[test]
+    fn test_recreate_worktrees_reports_missing_branches() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        init_repo_with_commit(root);
+        git_cmd()
+            .args(["update-ref", "refs/remotes/origin/develop", "main"])
+            .current_dir(root)
+            .status()
+            .unwrap();
+        for (key, value) in [
+            ("remote.origin.url", "https://example.com/user/repo.git"),
+            ("remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"),
+        ] {
+            git_cmd().args(["config", key, value]).current_dir(root).status().unwrap();
+        }
+
+        let branches = ["develop", "gone", "main"].map(String::from);
+        let failed = recreate_worktrees(root, &branches).unwrap();
+        assert_eq!(failed, vec![("gone".to_string(), "origin/gone not found".to_string())]);
+        assert!(root.join("develop").is_dir());
+        assert!(root.join("main").is_dir());
+    }
+
+    #[test]
+    fn test_unpushed_reason() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        init_repo_with_commit(root);
+        let git = |args: &[&str]| {
+            let output = git_cmd().args(args).current_dir(root).output().unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        let ahead = git(&[
+            "-c",
+            "user.name=bw",
+            "-c",
+            "user.email=bw@example.com",
+            "commit-tree",
+            "main^{tree}",
+            "-p",
+            "main",
+            "-m",
+            "local",
+        ]);
+        git(&["update-ref", "refs/heads/ahead", &ahead]);
+        for branch in ["main", "ahead"] {
+            git(&["update-ref", &format!("refs/remotes/origin/{}", branch), "main"]);
+        }
+
+        assert_eq!(unpushed_reason(root, "main"), None);
+        assert_eq!(
+            unpushed_reason(root, "ahead").unwrap(),
+            "1 commit(s) not on origin/ahead"
+        );
+        assert_eq!(unpushed_reason(root, "gone").unwrap(), "origin/gone not found");
+    }
+
+    #[test]
+    fn test_restore_root_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (backup, root) = (tmp.path().join("backup"), tmp.path().join("root"));
+        for dir in [".bare", "main", "scripts"] {
+            fs::create_dir_all(backup.join(dir)).unwrap();
+        }
+        fs::write(backup.join("main/.git"), "gitdir: ../.bare/worktrees/main\n").unwrap();
+        fs::write(backup.join("bw.toml"), "base_branch = \"develop\"\n").unwrap();
+        fs::write(backup.join(".envrc"), "old\n").unwrap();
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join(".envrc"), "new\n").unwrap();
+
+        restore_root_files(&backup, &root).unwrap();
+        assert!(root.join("bw.toml").exists());
+        assert!(root.join("scripts").is_dir());
+        assert!(!root.join("main").exists());
+        assert!(!root.join(".bare").exists());
+        assert_eq!(fs::read_to_string(root.join(".envrc")).unwrap(), "new\n");
+    }
+}
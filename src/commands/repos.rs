@@ -0,0 +1,77 @@
+use crate::index::{get_index_path, read_entries, write_entries, IndexEntry};
+use std::path::Path;
+
+pub fn execute(host: Option<&str>, owner: Option<&str>, prune: bool) -> anyhow::Result<()> {
+    let index_path = get_index_path()?;
+    let entries = read_entries(&index_path)?;
+
+    if prune {
+        let (kept, removed): (Vec<IndexEntry>, Vec<IndexEntry>) = entries
+            .into_iter()
+            .partition(|e| Path::new(&e.path).exists());
+        for entry in &removed {
+            eprintln!("Pruned: {}", entry.path);
+        }
+        write_entries(&index_path, &kept)?;
+        eprintln!("Done! Removed {} missing repositories from the index", removed.len());
+        return Ok(());
+    }
+
+    for entry in filter_entries(&entries, host, owner) {
+        let missing = if Path::new(&entry.path).exists() {
+            ""
+        } else {
+            " (missing)"
+        };
+        println!(
+            "{}/{}/{}\t{}{}",
+            entry.host, entry.owner, entry.repo, entry.path, missing
+        );
+    }
+
+    Ok(())
+}
+
+fn filter_entries<'a>(
+    entries: &'a [IndexEntry],
+    host: Option<&str>,
+    owner: Option<&str>,
+) -> Vec<&'a IndexEntry> {
+    entries
+        .iter()
+        .filter(|e| host.is_none_or(|h| e.host == h))
+        .filter(|e| owner.is_none_or(|o| e.owner == o))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(host: &str, owner: &str) -> IndexEntry {
+        IndexEntry {
+            host: host.to_string(),
+            owner: owner.to_string(),
+            repo: "repo".to_string(),
+            path: format!("/repos/{}/{}/repo", host, owner),
+            cloned_at: 0,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let entries = vec![
+            entry("github.com", "alice"),
+            entry("github.com", "bob"),
+            entry("gitlab.com", "alice"),
+        ];
+        assert_eq!(filter_entries(&entries, None, None).len(), 3);
+        assert_eq!(filter_entries(&entries, Some("github.com"), None).len(), 2);
+        assert_eq!(filter_entries(&entries, None, Some("alice")).len(), 2);
+        assert_eq!(
+            filter_entries(&entries, Some("gitlab.com"), Some("alice")),
+            vec![&entries[2]]
+        );
+    }
+}
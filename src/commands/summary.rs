@@ -0,0 +1,70 @@
+/// Key/value recap printed as a boxed block at the end of a command.
+#[derive(Debug)]
+pub struct Summary {
+    title: String,
+    rows: Vec<(String, String)>,
+}
+
+impl Summary {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, key: &str, value: impl Into<String>) {
+        self.rows.push((key.to_string(), value.into()));
+    }
+
+    pub fn render(&self) -> String {
+        let key_width = self.rows.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+        let lines: Vec<String> = self
+            .rows
+            .iter()
+            .map(|(k, v)| format!("{:<width$}  {}", k, v, width = key_width))
+            .collect();
+        let inner_width = lines
+            .iter()
+            .map(|l| l.chars().count())
+            .chain(std::iter::once(self.title.chars().count() + 1))
+            .max()
+            .unwrap_or(0);
+
+        let mut out = String::new();
+        out.push_str(&format!(
+            "┌─ {} {}┐\n",
+            self.title,
+            "─".repeat(inner_width - self.title.chars().count() - 1)
+        ));
+        for line in &lines {
+            let pad = inner_width - line.chars().count();
+            out.push_str(&format!("│ {}{} │\n", line, " ".repeat(pad)));
+        }
+        out.push_str(&format!("└{}┘", "─".repeat(inner_width + 2)));
+        out
+    }
+
+    pub fn print(&self) {
+        eprintln!("{}", self.render());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut summary = Summary::new("bw add");
+        summary.add("branch", "feature/x");
+        summary.add("base", "main");
+        assert_eq!(
+            summary.render(),
+            "┌─ bw add ──────────┐\n\
+             │ branch  feature/x │\n\
+             │ base    main      │\n\
+             └───────────────────┘"
+        );
+    }
+}
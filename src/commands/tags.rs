@@ -0,0 +1,71 @@
+use super::get::TAG_DIR;
+use crate::config;
+use crate::index::{get_index_path, read_entries, IndexEntry};
+use std::collections::BTreeMap;
+
+pub fn execute() -> anyhow::Result<()> {
+    let entries = read_entries(&get_index_path()?)?;
+    let tags = group_by_tag(&entries);
+
+    if tags.is_empty() {
+        eprintln!("No tags yet. Tag a clone with 'bw get <repo> --tag <name>'.");
+        return Ok(());
+    }
+
+    for (tag, repos) in &tags {
+        println!("{}", tag);
+        for entry in repos {
+            println!("  {}/{}/{}\t{}", entry.host, entry.owner, entry.repo, entry.path);
+        }
+    }
+    eprintln!(
+        "\nSymlinks: {}",
+        config::get_root()?.join(TAG_DIR).display()
+    );
+
+    Ok(())
+}
+
+/// Repositories per tag, sorted by tag name. A repo cloned twice appears once per path.
+fn group_by_tag(entries: &[IndexEntry]) -> BTreeMap<&str, Vec<&IndexEntry>> {
+    let mut tags: BTreeMap<&str, Vec<&IndexEntry>> = BTreeMap::new();
+    for entry in entries {
+        for tag in &entry.tags {
+            let repos = tags.entry(tag.as_str()).or_default();
+            if !repos.iter().any(|e| e.path == entry.path) {
+                repos.push(entry);
+            }
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(repo: &str, tags: &[&str]) -> IndexEntry {
+        IndexEntry {
+            host: "github.com".to_string(),
+            owner: "user".to_string(),
+            repo: repo.to_string(),
+            path: format!("/repos/github.com/user/{}", repo),
+            cloned_at: 0,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_group_by_tag() {
+        let entries = vec![
+            entry("a", &["rust", "cli"]),
+            entry("b", &["rust"]),
+            entry("c", &[]),
+            entry("a", &["rust"]),
+        ];
+        let tags = group_by_tag(&entries);
+        assert_eq!(tags.keys().copied().collect::<Vec<_>>(), ["cli", "rust"]);
+        let rust: Vec<&str> = tags["rust"].iter().map(|e| e.repo.as_str()).collect();
+        assert_eq!(rust, ["a", "b"]);
+    }
+}
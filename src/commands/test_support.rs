@@ -0,0 +1,19 @@
+use std::fs;
+use std::path::Path;
+
+/// Creates a `.bare` repository with one commit on `main` and points `.git` at it.
+pub fn init_repo_with_commit(repo_root: &Path) {
+    let repo = git2::Repository::init_opts(
+        repo_root.join(".bare"),
+        git2::RepositoryInitOptions::new()
+            .bare(true)
+            .initial_head("main"),
+    )
+    .unwrap();
+    let sig = git2::Signature::now("bw", "bw@example.com").unwrap();
+    let tree_id = repo.treebuilder(None).unwrap().write().unwrap();
+    let tree = repo.find_tree(tree_id).unwrap();
+    repo.commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[])
+        .unwrap();
+    fs::write(repo_root.join(".git"), "gitdir: .bare\n").unwrap();
+}
@@ -1,9 +1,12 @@
 use crate::error::GhbareError;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-#[derive(Debug, Deserialize)]
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     pub root: String,
     #[serde(default = "default_clone_method")]
@@ -12,15 +15,80 @@ pub struct Config {
     #[serde(default = "default_post_clone_commands")]
     pub post_clone_commands: String,
     pub suffix: Option<String>,
+    #[serde(default)]
+    pub mirror: Vec<MirrorRule>,
+    #[serde(default = "default_git_command")]
+    pub git_command: String,
+    #[serde(default)]
+    pub maintain_index: bool,
+    #[serde(default)]
+    pub write_clone_metadata: bool,
+    pub fzf_preview_command: Option<String>,
+    pub fzf_height: Option<String>,
+    #[serde(default)]
+    pub fzf_reverse: bool,
+    pub default_host: Option<String>,
+    pub default_owner: Option<String>,
+    pub path_template: Option<String>,
+    #[serde(default)]
+    pub omit_host: bool,
+    pub dir_mode: Option<String>,
+    /// No longer applied; superseded by `[[host]] ca_bundle`
+    pub ca_bundle: Option<String>,
+    #[serde(default)]
+    pub host: Vec<HostSettings>,
+}
+
+impl Config {
+    /// Whether post_clone_commands are the built-in ones (also written by the
+    /// config template), which check out the remote HEAD branch.
+    pub fn has_default_post_clone_commands(&self) -> bool {
+        self.post_clone_commands.trim() == default_post_clone_commands().trim()
+    }
+
+    /// Whether `[[host]]` allows skipping TLS verification for `host`.
+    pub fn is_insecure_host(&self, host: &str) -> bool {
+        self.host.iter().any(|h| h.name == host && h.insecure)
+    }
+
+    /// CA bundle `[[host]]` sets for `host`, with `~` expanded.
+    pub fn ca_bundle_for(&self, host: &str) -> Option<PathBuf> {
+        self.host
+            .iter()
+            .find(|h| h.name == host)
+            .and_then(|h| h.ca_bundle.as_deref())
+            .map(expand_tilde)
+    }
+}
+
+/// Per-host settings (`[[host]]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HostSettings {
+    pub name: String,
+    /// Accept any TLS certificate, as with `bw get --insecure`
+    #[serde(default)]
+    pub insecure: bool,
+    /// PEM file of CA certificates trusted for HTTPS clones from this host
+    pub ca_bundle: Option<String>,
+}
+
+/// Clone from `to` instead of `from` while keeping the local path under `from`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MirrorRule {
+    pub from: String,
+    pub to: String,
 }
 
 fn default_clone_method() -> String {
     "ssh".to_string()
 }
 
+fn default_git_command() -> String {
+    "git".to_string()
+}
+
 fn default_post_clone_commands() -> String {
-    r#"echo 'gitdir: .bare' > .git
-git config --file .bare/config remote.origin.fetch '+refs/heads/*:refs/remotes/origin/*'
+    r#"git config --file .bare/config remote.origin.fetch "${BW_FETCH_REFSPEC:-+refs/heads/*:refs/remotes/origin/*}"
 git fetch origin
 HEAD_BRANCH=$(git symbolic-ref refs/remotes/origin/HEAD 2>/dev/null | sed 's@^refs/remotes/origin/@@'); [ -n "$HEAD_BRANCH" ] && git worktree add "$HEAD_BRANCH" "$HEAD_BRANCH""#
         .to_string()
@@ -39,12 +107,67 @@ pub fn get_config_dir() -> Result<PathBuf, GhbareError> {
         ))
 }
 
+pub fn get_data_dir() -> Result<PathBuf, GhbareError> {
+    // Use XDG_DATA_HOME or default to ~/.local/share
+    if let Ok(xdg_data) = std::env::var("XDG_DATA_HOME") {
+        return Ok(PathBuf::from(xdg_data).join("ghqb"));
+    }
+
+    dirs::home_dir()
+        .map(|h| h.join(".local").join("share").join("ghqb"))
+        .ok_or(GhbareError::ConfigNotFound(
+            "Could not determine data directory".to_string(),
+        ))
+}
+
+/// Sets the profile from `--profile`. Without it, `$BW_PROFILE` is used.
+pub fn set_profile(profile: Option<String>) {
+    let _ = PROFILE.set(profile);
+}
+
+fn active_profile() -> Option<String> {
+    PROFILE
+        .get()
+        .cloned()
+        .flatten()
+        .or_else(|| std::env::var("BW_PROFILE").ok())
+        .filter(|p| !p.is_empty())
+}
+
+/// Config file of the active profile (`config.<profile>.toml`), used for editing.
 pub fn get_config_path() -> Result<PathBuf, GhbareError> {
-    Ok(get_config_dir()?.join("config.toml"))
+    Ok(profile_config_path(
+        &get_config_dir()?,
+        active_profile().as_deref(),
+    ))
+}
+
+/// Config file to read: the profile's file if it exists, otherwise `config.toml`.
+pub fn resolve_config_path() -> Result<PathBuf, GhbareError> {
+    Ok(resolve_config_path_in(
+        &get_config_dir()?,
+        active_profile().as_deref(),
+    ))
+}
+
+fn profile_config_path(config_dir: &Path, profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(p) => config_dir.join(format!("config.{}.toml", p)),
+        None => config_dir.join("config.toml"),
+    }
+}
+
+fn resolve_config_path_in(config_dir: &Path, profile: Option<&str>) -> PathBuf {
+    let path = profile_config_path(config_dir, profile);
+    if path.exists() {
+        path
+    } else {
+        profile_config_path(config_dir, None)
+    }
 }
 
 pub fn get_config() -> Result<Config, GhbareError> {
-    let config_path = get_config_path()?;
+    let config_path = resolve_config_path()?;
 
     if !config_path.exists() {
         return Err(GhbareError::ConfigNotFound(format!(
@@ -65,7 +188,7 @@ pub fn get_root() -> Result<PathBuf, GhbareError> {
     Ok(expand_tilde(&config.root))
 }
 
-fn expand_tilde(path: &str) -> PathBuf {
+pub fn expand_tilde(path: &str) -> PathBuf {
     if let Some(stripped) = path.strip_prefix("~/") {
         if let Some(home) = dirs::home_dir() {
             return home.join(stripped);
@@ -88,15 +211,64 @@ root = "~/repos"
 clone_method = "ssh"
 
 # Commands to run after bare clone (executed in project directory, line by line)
+# The .git file pointing at .bare is already written when they run
+# $BW_FETCH_REFSPEC holds the fetch refspec (limited to one branch with --single-branch)
 post_clone_commands = '''
-echo 'gitdir: .bare' > .git
-git config --file .bare/config remote.origin.fetch '+refs/heads/*:refs/remotes/origin/*'
+git config --file .bare/config remote.origin.fetch "${BW_FETCH_REFSPEC:-+refs/heads/*:refs/remotes/origin/*}"
 git fetch origin
 HEAD_BRANCH=$(git symbolic-ref refs/remotes/origin/HEAD 2>/dev/null | sed 's@^refs/remotes/origin/@@'); [ -n "$HEAD_BRANCH" ] && git worktree add "$HEAD_BRANCH" "$HEAD_BRANCH"
 '''
 
 # Optional: suffix for cloned directory (e.g., ".work" -> repo.work)
 # suffix = ".work"
+
+# Optional: project directory under root, with {host}, {owner}, {repo} and {suffix}
+# (default: {host}/{owner}/{repo}{suffix}; must contain {repo})
+# path_template = "{owner}/{repo}@{host}"
+
+# Optional: drop the host directory ({owner}/{repo}{suffix}) when you use a single host.
+# Ignored when path_template is set.
+# omit_host = true
+
+# Optional: permissions (octal) set on the project directory and .bare after cloning (Unix only)
+# dir_mode = "0750"
+
+# Optional: complete short specs (`bw get repo`, `bw get owner/repo`)
+# default_host = "github.com"
+# default_owner = "myorg"
+
+# Optional: git binary used for worktree operations (name or path)
+# git_command = "git"
+
+# Optional: record each clone in $XDG_DATA_HOME/ghqb/repos.jsonl (see `bw repos`)
+# maintain_index = true
+
+# Optional: record host/owner/repo, clone URL, method and time in <project>/.bw.toml
+# (next to .bare, outside the worktrees; shown by `bw info` inside the repository)
+# write_clone_metadata = true
+
+# Optional: fzf preview for `bw list --preview` ({1} is the worktree path)
+# fzf_preview_command = "git -C {1} -c color.ui=always diff --stat"
+
+# Optional: fzf window height and layout for `bw list` / `bw shell` (default: fzf's own)
+# fzf_height = "40%"
+# fzf_reverse = true
+
+# Optional: clone from a mirror host while keeping the upstream host in the local path
+# [[mirror]]
+# from = "github.com"
+# to = "git.internal.example.com"
+
+# Optional: per-host settings. Without default_host, `bw get owner/repo` asks which
+# of two or more [[host]] entries to use (fzf; an error when stdin is not a terminal).
+# insecure accepts self-signed TLS certificates from the host, as with `bw get --insecure`.
+# ca_bundle is a PEM file of CA certificates (e.g., a private CA) for HTTPS clones from
+# the host. git (and later fetches in the clone) uses it instead of the system store for
+# that host only, so include any public CAs the host needs.
+# [[host]]
+# name = "gitlab.internal.example.com"
+# insecure = true
+# ca_bundle = "~/.config/ghqb/internal-ca.pem"
 "#
 }
 
@@ -124,10 +296,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_profile_config_path() {
+        let dir = Path::new("/home/user/.config/ghqb");
+        assert_eq!(profile_config_path(dir, None), dir.join("config.toml"));
+        assert_eq!(
+            profile_config_path(dir, Some("work")),
+            dir.join("config.work.toml")
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_path_falls_back_to_default() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        assert_eq!(
+            resolve_config_path_in(dir, Some("work")),
+            dir.join("config.toml")
+        );
+
+        fs::write(dir.join("config.work.toml"), "root = \"~/work\"").unwrap();
+        assert_eq!(
+            resolve_config_path_in(dir, Some("work")),
+            dir.join("config.work.toml")
+        );
+        assert_eq!(resolve_config_path_in(dir, None), dir.join("config.toml"));
+    }
+
+    #[test]
+    fn test_git_command_default() {
+        let config: Config = toml::from_str("root = \"~/repos\"").unwrap();
+        assert_eq!(config.git_command, "git");
+
+        let config: Config =
+            toml::from_str("root = \"~/repos\"\ngit_command = \"/opt/git/bin/git\"").unwrap();
+        assert_eq!(config.git_command, "/opt/git/bin/git");
+    }
+
+    #[test]
+    fn test_is_insecure_host() {
+        let config: Config = toml::from_str(
+            "root = \"~/repos\"\n[[host]]\nname = \"git.internal\"\ninsecure = true\n\
+             [[host]]\nname = \"github.com\"\n",
+        )
+        .unwrap();
+        assert!(config.is_insecure_host("git.internal"));
+        assert!(!config.is_insecure_host("github.com"));
+        assert!(!config.is_insecure_host("gitlab.com"));
+    }
+
+    #[test]
+    fn test_ca_bundle_for() {
+        let config: Config = toml::from_str(
+            "root = \"~/repos\"\nca_bundle = \"/etc/ssl/old.pem\"\n\
+             [[host]]\nname = \"git.internal\"\nca_bundle = \"/etc/ssl/internal.pem\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.ca_bundle_for("git.internal"),
+            Some(PathBuf::from("/etc/ssl/internal.pem"))
+        );
+        // The top-level ca_bundle no longer applies to every host
+        assert_eq!(config.ca_bundle_for("github.com"), None);
+    }
+
     #[test]
     fn test_default_config_content_is_valid_toml() {
         let content = default_config_content();
         let config: Result<Config, _> = toml::from_str(content);
         assert!(config.is_ok());
     }
+
+    #[test]
+    fn test_has_default_post_clone_commands() {
+        let config: Config = toml::from_str(default_config_content()).unwrap();
+        assert!(config.has_default_post_clone_commands());
+        let config: Config = toml::from_str("root = \"~/repos\"\n").unwrap();
+        assert!(config.has_default_post_clone_commands());
+        let config: Config =
+            toml::from_str("root = \"~/repos\"\npost_clone_commands = \"git fetch origin\"\n")
+                .unwrap();
+        assert!(!config.has_default_post_clone_commands());
+    }
 }
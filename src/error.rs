@@ -5,12 +5,18 @@ pub enum GhbareError {
     #[error("Failed to parse repository URL: {0}")]
     UrlParseError(String),
 
+    #[error("Failed to parse repository URL: {reason} in '{input}'")]
+    InvalidRepoUrl { input: String, reason: UrlErrorReason },
+
     #[error("Config not found: {0}")]
     ConfigNotFound(String),
 
     #[error("Failed to parse config: {0}")]
     ConfigParseError(String),
 
+    #[error("Unknown config key: {0}")]
+    UnknownConfigKey(String),
+
     #[error("$EDITOR environment variable is not set")]
     EditorNotFound,
 
@@ -20,9 +26,19 @@ pub enum GhbareError {
     #[error("Post clone command failed: {0}")]
     PostCloneCommandError(String),
 
-    #[error("Repository already exists: {0}")]
+    #[error("Repository already cloned: {0}\nUse 'bw add' inside it to create worktrees.")]
     RepositoryAlreadyExists(String),
 
+    #[error("{path} already holds a clone from {existing}, not {requested}\nUse --suffix to clone under a different name.")]
+    HostCollision {
+        path: String,
+        existing: String,
+        requested: String,
+    },
+
+    #[error("Path is occupied by something other than a bw repository: {0}\nRemove it or use --suffix to clone under a different name.")]
+    PathOccupied(String),
+
     #[error("Repository root not found (no .bare directory)")]
     RepoRootNotFound,
 
@@ -32,6 +48,51 @@ pub enum GhbareError {
     #[error("Worktree already exists: {0}")]
     WorktreeAlreadyExists(String),
 
+    #[error("Directory name '{dirname}' of branch '{branch}' is already used by the worktree of '{existing}'")]
+    DirnameCollision {
+        dirname: String,
+        branch: String,
+        existing: String,
+    },
+
+    #[error("Worktree limit reached: {count} of max_worktrees = {max} in bw.toml\nRemove unused worktrees (bw rm, bw list --stale <DAYS> --remove) or pass --force.")]
+    TooManyWorktrees { count: usize, max: usize },
+
+    #[error("Branch already exists: {0}\nUse --reuse-branch to check it out in a new worktree, or set on_existing_branch = \"reuse\" in bw.toml.")]
+    BranchAlreadyExists(String),
+
+    #[error("Another bw operation is in progress ({0} is locked)")]
+    RepoLocked(String),
+
+    /// The user quit fzf with Ctrl-C or Esc; bw exits with 130 like fzf.
+    #[error("Interrupted")]
+    Interrupted,
+
+    #[error("bw doctor found {0} problem(s)")]
+    DoctorFoundProblems(usize),
+
+    #[error("Repository index error: {0}")]
+    IndexError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
+
+/// Why a repository URL or spec could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UrlErrorReason {
+    #[error("missing host")]
+    MissingHost,
+
+    #[error("missing owner")]
+    MissingOwner,
+
+    #[error("missing repository name")]
+    MissingRepo,
+
+    #[error("unsupported scheme '{0}'")]
+    UnsupportedScheme(String),
+
+    #[error("malformed URL")]
+    Malformed,
+}
@@ -1,52 +1,780 @@
+use super::netrc;
 use crate::error::GhbareError;
-use git2::{FetchOptions, RemoteCallbacks, Repository};
-use std::path::Path;
+use git2::{
+    AutotagOption, CertificateCheckStatus, Direction, FetchOptions, RemoteCallbacks, Repository,
+};
+use std::path::{Path, PathBuf};
 
-pub fn bare_clone(url: &str, dest: &Path) -> Result<Repository, GhbareError> {
-    let mut callbacks = RemoteCallbacks::new();
+#[derive(Debug, Default)]
+pub struct CloneOptions {
+    /// Fetch only the remote HEAD branch (or `branch`) instead of all branches
+    pub single_branch: bool,
+    /// Branch to clone and check out instead of the remote HEAD
+    pub branch: Option<String>,
+    /// Do not print transfer progress
+    pub quiet_progress: bool,
+    /// Borrow objects from this local repository (`git clone --reference`)
+    pub reference: Option<PathBuf>,
+    /// Copy the borrowed objects so the clone no longer depends on `reference`
+    pub dissociate: bool,
+    /// Fetch only this many commits of history (shallow clone)
+    pub depth: Option<u32>,
+    /// Do not fetch tags, now or in later fetches
+    pub no_tags: bool,
+    /// Accept any TLS certificate from the remote, now and in later fetches
+    pub insecure: bool,
+    /// PEM file of CA certificates to trust for an HTTPS `url` (the host's
+    /// `[[host]] ca_bundle`), now and in later fetches. Ignored for other URLs.
+    pub ca_bundle: Option<PathBuf>,
+}
 
-    callbacks.credentials(|_url, username_from_url, allowed_types| {
-        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-            git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
-        } else if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
-            let username = std::env::var("GIT_USERNAME").unwrap_or_default();
-            let password = std::env::var("GIT_PASSWORD").unwrap_or_default();
-            git2::Cred::userpass_plaintext(&username, &password)
-        } else {
-            Err(git2::Error::from_str("no authentication available"))
-        }
-    });
+/// Whether libgit2 uses OpenSSL, the only TLS backend here that can load CA
+/// certificates from a file (macOS builds use SecureTransport, Windows WinHTTP).
+const LIBGIT2_LOADS_CA_FILE: bool = cfg!(all(unix, not(target_vendor = "apple")));
 
-    callbacks.transfer_progress(|stats| {
-        if stats.received_objects() == stats.total_objects() {
-            eprint!(
-                "\rResolving deltas {}/{}   ",
-                stats.indexed_deltas(),
-                stats.total_deltas()
-            );
-        } else if stats.total_objects() > 0 {
-            eprint!(
-                "\rReceiving objects: {:3}% ({}/{})   ",
-                100 * stats.received_objects() / stats.total_objects(),
-                stats.received_objects(),
-                stats.total_objects()
-            );
+pub fn bare_clone(url: &str, dest: &Path, opts: &CloneOptions) -> Result<Repository, GhbareError> {
+    let ca_bundle = https_ca_bundle(url, opts);
+    if let Some(bundle) = ca_bundle {
+        validate_ca_bundle(bundle)?;
+    }
+    // libgit2 has no alternates support for clone, its local transport cannot
+    // fetch shallow, and only its OpenSSL backend can trust ca_bundle, so use git itself
+    if opts.reference.is_some()
+        || opts.depth.is_some()
+        || (ca_bundle.is_some() && !LIBGIT2_LOADS_CA_FILE)
+    {
+        if let Some(branch) = &opts.branch {
+            if !remote_has_branch(git_with_tls(url, opts), url, branch)? {
+                return Err(branch_not_found(branch, url));
+            }
         }
-        true
-    });
+        return bare_clone_with_git(url, dest, opts);
+    }
+
+    if let Some(bundle) = ca_bundle {
+        // SAFETY: set before libgit2 opens any connection in this process
+        // (including the --branch check below)
+        unsafe { git2::opts::set_ssl_cert_file(bundle) }.map_err(|e| {
+            GhbareError::CloneError(format!("Cannot use ca_bundle: {}", e.message()))
+        })?;
+    }
+    if let Some(branch) = &opts.branch {
+        check_remote_branch(url, branch, opts.insecure)?;
+    }
+
+    let mut callbacks = credential_callbacks(opts.insecure);
+
+    if !opts.quiet_progress {
+        callbacks.transfer_progress(|stats| {
+            if stats.received_objects() == stats.total_objects() {
+                eprint!(
+                    "\rResolving deltas {}/{}   ",
+                    stats.indexed_deltas(),
+                    stats.total_deltas()
+                );
+            } else if stats.total_objects() > 0 {
+                eprint!(
+                    "\rReceiving objects: {:3}% ({}/{})   ",
+                    100 * stats.received_objects() / stats.total_objects(),
+                    stats.received_objects(),
+                    stats.total_objects()
+                );
+            }
+            true
+        });
+    }
 
     let mut fetch_options = FetchOptions::new();
     fetch_options.remote_callbacks(callbacks);
+    if opts.no_tags {
+        fetch_options.download_tags(AutotagOption::None);
+    }
 
     let mut builder = git2::build::RepoBuilder::new();
     builder.bare(true);
     builder.fetch_options(fetch_options);
 
+    if opts.single_branch {
+        let branch = match &opts.branch {
+            Some(branch) => branch.clone(),
+            None => remote_default_branch(url, opts.insecure)?,
+        };
+        eprintln!("Single branch clone (branch: {})", branch);
+        let refspec = fetch_refspec(Some(&branch));
+        builder.remote_create(move |repo, name, url| repo.remote_with_fetch(name, url, &refspec));
+        builder.branch(&branch);
+    }
+
     let repo = builder
         .clone(url, dest)
         .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
 
-    eprintln!();
+    if !opts.quiet_progress {
+        eprintln!();
+    }
+
+    if opts.no_tags {
+        disable_tag_fetch(&repo)?;
+    }
+    apply_tls_config(&repo, url, opts)?;
+    if opts.branch.is_some() {
+        // libgit2 points origin/HEAD at the remote HEAD, not the requested branch
+        set_origin_head(&repo)?;
+    }
+
+    Ok(repo)
+}
+
+/// `opts.ca_bundle` when `url` is HTTPS.
+fn https_ca_bundle<'a>(url: &str, opts: &'a CloneOptions) -> Option<&'a Path> {
+    opts.ca_bundle
+        .as_deref()
+        .filter(|_| url::Url::parse(url).is_ok_and(|u| u.scheme() == "https"))
+}
+
+/// git with `--insecure` and `ca_bundle` applied for this one command.
+fn git_with_tls(url: &str, opts: &CloneOptions) -> std::process::Command {
+    let mut cmd = super::git_cmd();
+    if opts.insecure {
+        cmd.args(["-c", "http.sslVerify=false"]);
+    }
+    if let Some(bundle) = https_ca_bundle(url, opts) {
+        cmd.arg("-c").arg(format!("http.sslCAInfo={}", bundle.display()));
+    }
+    cmd
+}
+
+fn branch_not_found(branch: &str, url: &str) -> GhbareError {
+    GhbareError::CloneError(format!("Branch '{}' not found on {}", branch, url))
+}
 
+fn bare_clone_with_git(url: &str, dest: &Path, opts: &CloneOptions) -> Result<Repository, GhbareError> {
+    let mut cmd = git_with_tls(url, opts);
+    cmd.args(["clone", "--bare"]);
+    if let Some(reference) = &opts.reference {
+        let reference = validate_reference(reference)?;
+        eprintln!(
+            "Sharing objects with {}{}",
+            reference.display(),
+            if opts.dissociate { " (dissociated)" } else { "" }
+        );
+        cmd.arg("--reference").arg(&reference);
+        if opts.dissociate {
+            cmd.arg("--dissociate");
+        }
+    }
+    if opts.single_branch {
+        cmd.arg("--single-branch");
+    }
+    if let Some(branch) = &opts.branch {
+        cmd.arg("--branch").arg(branch);
+    }
+    if let Some(depth) = opts.depth {
+        cmd.arg(format!("--depth={}", depth));
+    }
+    if opts.no_tags {
+        cmd.arg("--no-tags");
+    }
+    if opts.quiet_progress {
+        cmd.arg("--quiet");
+    }
+    let status = cmd
+        .arg(url)
+        .arg(dest)
+        .status()
+        .map_err(|e| GhbareError::CloneError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::CloneError(format!("git clone failed for {}", url)));
+    }
+
+    // git clone --no-tags already sets remote.origin.tagOpt
+    let repo =
+        Repository::open_bare(dest).map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    apply_tls_config(&repo, url, opts)?;
+    set_origin_head(&repo)?;
     Ok(repo)
 }
+
+/// Clones into `dest` with `git fetch`, reusing whatever a previous attempt
+/// left there. The HEAD branch is fetched before the rest so that an interrupted
+/// transfer keeps it; each step is retried `attempts` times with a growing delay.
+pub fn resume_bare_clone(
+    url: &str,
+    dest: &Path,
+    opts: &CloneOptions,
+    attempts: u32,
+) -> Result<Repository, GhbareError> {
+    let repo = if dest.join("HEAD").exists() {
+        eprintln!("Resuming partial clone in {}", dest.display());
+        Repository::open_bare(dest)
+    } else {
+        Repository::init_bare(dest)
+    }
+    .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    if repo.find_remote("origin").is_err() {
+        repo.remote_with_fetch("origin", url, "+refs/heads/*:refs/remotes/origin/*")
+            .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    }
+    if let Some(bundle) = https_ca_bundle(url, opts) {
+        validate_ca_bundle(bundle)?;
+    }
+    apply_tls_config(&repo, url, opts)?;
+
+    // Both go through git, which reads the TLS settings apply_tls_config just wrote
+    let head = match &opts.branch {
+        Some(branch) => {
+            let origin = || {
+                let mut cmd = super::git_cmd();
+                cmd.current_dir(dest);
+                cmd
+            };
+            if !with_retries(attempts, || remote_has_branch(origin(), "origin", branch))? {
+                return Err(branch_not_found(branch, url));
+            }
+            branch.clone()
+        }
+        None => with_retries(attempts, || remote_head_branch(dest))?,
+    };
+    // Same layout as a normal clone: remote-tracking origin/* refs, and only the
+    // HEAD branch as a local branch tracking its origin counterpart
+    let mut steps = vec![format!("+refs/heads/{0}:refs/remotes/origin/{0}", head)];
+    if !opts.single_branch {
+        steps.push("+refs/heads/*:refs/remotes/origin/*".to_string());
+    }
+    for refspec in &steps {
+        with_retries(attempts, || fetch_into(dest, refspec, opts))?;
+    }
+
+    create_tracking_branch(&repo, &head)?;
+    repo.set_head(&format!("refs/heads/{}", head))
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    if opts.no_tags {
+        disable_tag_fetch(&repo)?;
+    }
+    set_origin_head(&repo)?;
+    Ok(repo)
+}
+
+/// Local `branch` at `origin/<branch>` with it as upstream, kept if it exists
+/// from an earlier attempt.
+fn create_tracking_branch(repo: &Repository, branch: &str) -> Result<(), GhbareError> {
+    let err = |e: git2::Error| GhbareError::CloneError(e.message().to_string());
+    if repo.find_branch(branch, git2::BranchType::Local).is_ok() {
+        return Ok(());
+    }
+    let remote = format!("origin/{}", branch);
+    let commit = repo
+        .find_branch(&remote, git2::BranchType::Remote)
+        .and_then(|b| b.get().peel_to_commit())
+        .map_err(err)?;
+    let mut local = repo.branch(branch, &commit, false).map_err(err)?;
+    local.set_upstream(Some(&remote)).map_err(err)
+}
+
+fn with_retries<T>(
+    attempts: u32,
+    mut f: impl FnMut() -> Result<T, GhbareError>,
+) -> Result<T, GhbareError> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts => {
+                let delay = std::time::Duration::from_secs(2 * attempt as u64);
+                eprintln!("{}; retrying in {}s ({}/{})", e, delay.as_secs(), attempt, attempts);
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Branch the remote HEAD points to, via `git ls-remote --symref`.
+fn remote_head_branch(git_dir: &Path) -> Result<String, GhbareError> {
+    let output = super::git_cmd()
+        .args(["ls-remote", "--symref", "origin", "HEAD"])
+        .current_dir(git_dir)
+        .output()
+        .map_err(|e| GhbareError::CloneError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GhbareError::CloneError(format!(
+            "git ls-remote failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("ref: refs/heads/"))
+        .and_then(|rest| rest.split('\t').next())
+        .map(str::to_string)
+        .ok_or_else(|| GhbareError::CloneError("Remote has no HEAD branch".to_string()))
+}
+
+/// Whether `remote` (a name or URL) has `branch`, via `git ls-remote` run with `cmd`.
+fn remote_has_branch(
+    mut cmd: std::process::Command,
+    remote: &str,
+    branch: &str,
+) -> Result<bool, GhbareError> {
+    let output = cmd
+        .args(["ls-remote", "--heads", remote, &format!("refs/heads/{}", branch)])
+        .output()
+        .map_err(|e| GhbareError::CloneError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GhbareError::CloneError(format!(
+            "git ls-remote failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(!output.stdout.is_empty())
+}
+
+fn fetch_into(git_dir: &Path, refspec: &str, opts: &CloneOptions) -> Result<(), GhbareError> {
+    let mut cmd = super::git_cmd();
+    cmd.args(["fetch", "--update-head-ok", "origin", refspec])
+        .current_dir(git_dir);
+    if !opts.no_tags {
+        cmd.arg("+refs/tags/*:refs/tags/*");
+    }
+    if let Some(depth) = opts.depth {
+        cmd.arg(format!("--depth={}", depth));
+    }
+    if opts.quiet_progress {
+        cmd.arg("--quiet");
+    }
+    let status = cmd
+        .status()
+        .map_err(|e| GhbareError::CloneError(e.to_string()))?;
+    if !status.success() {
+        return Err(GhbareError::CloneError(format!("git fetch origin {} failed", refspec)));
+    }
+    Ok(())
+}
+
+/// Keeps later `git fetch origin` from downloading tags (`remote.origin.tagOpt`).
+fn disable_tag_fetch(repo: &Repository) -> Result<(), GhbareError> {
+    repo.config()
+        .and_then(|mut c| c.set_str("remote.origin.tagOpt", "--no-tags"))
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))
+}
+
+/// Records `--insecure` and `ca_bundle` for `url`'s host in the clone's config
+/// (`http.<scheme://host>/.sslVerify` / `.sslCAInfo`), so post_clone_commands and
+/// later fetches reach the host the same way. Non-HTTPS URLs are left alone.
+fn apply_tls_config(repo: &Repository, url: &str, opts: &CloneOptions) -> Result<(), GhbareError> {
+    let Some(origin) = url::Url::parse(url)
+        .ok()
+        .filter(|u| u.scheme() == "https")
+        .map(|u| u.origin().ascii_serialization())
+    else {
+        return Ok(());
+    };
+    let mut config = repo
+        .config()
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    if opts.insecure {
+        config
+            .set_bool(&format!("http.{}/.sslVerify", origin), false)
+            .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    }
+    if let Some(bundle) = &opts.ca_bundle {
+        config
+            .set_str(&format!("http.{}/.sslCAInfo", origin), &bundle.display().to_string())
+            .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    }
+    Ok(())
+}
+
+fn validate_ca_bundle(path: &Path) -> Result<(), GhbareError> {
+    if !path.is_file() {
+        return Err(GhbareError::CloneError(format!(
+            "ca_bundle is not a file: {}",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Object-owning git directory of the repository at `path` (a bw project,
+/// worktree or plain repository).
+fn validate_reference(path: &Path) -> Result<PathBuf, GhbareError> {
+    let output = super::git_cmd()
+        .args(["rev-parse", "--path-format=absolute", "--git-common-dir"])
+        .current_dir(path)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .ok_or_else(|| {
+            GhbareError::CloneError(format!(
+                "--reference is not a git repository: {}",
+                path.display()
+            ))
+        })?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Fetch refspec for `origin`: all branches, or only `branch` when given.
+pub fn fetch_refspec(branch: Option<&str>) -> String {
+    match branch {
+        Some(b) => format!("+refs/heads/{b}:refs/remotes/origin/{b}"),
+        None => "+refs/heads/*:refs/remotes/origin/*".to_string(),
+    }
+}
+
+/// Credentials from ssh-agent, GIT_USERNAME/GIT_PASSWORD or netrc. With `insecure`,
+/// any TLS certificate is accepted (SSH host keys are still checked).
+fn credential_callbacks<'a>(insecure: bool) -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+
+    if insecure {
+        callbacks.certificate_check(|cert, _host| {
+            Ok(if cert.as_x509().is_some() {
+                CertificateCheckStatus::CertificateOk
+            } else {
+                CertificateCheckStatus::CertificatePassthrough
+            })
+        });
+    }
+
+    callbacks.credentials(|url, username_from_url, allowed_types| {
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
+            git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
+        } else if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            let mut username = std::env::var("GIT_USERNAME").unwrap_or_default();
+            let mut password = std::env::var("GIT_PASSWORD").unwrap_or_default();
+            if username.is_empty() && password.is_empty() {
+                // Fall back to netrc for token-based HTTPS auth
+                let host = url::Url::parse(url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(|h| h.to_string()));
+                if let Some(creds) = host.as_deref().and_then(netrc::lookup) {
+                    username = creds.login;
+                    password = creds.password;
+                }
+            }
+            git2::Cred::userpass_plaintext(&username, &password)
+        } else {
+            Err(git2::Error::from_str("no authentication available"))
+        }
+    });
+
+    callbacks
+}
+
+/// Asks the remote which branch its HEAD points to, without fetching anything.
+fn detached_remote(url: &str) -> Result<git2::Remote<'static>, GhbareError> {
+    // Detached remotes ignore git config, so apply url.<base>.insteadOf here
+    let url = match git2::Config::open_default() {
+        Ok(config) => rewrite_url(url, &config),
+        Err(_) => url.to_string(),
+    };
+    git2::Remote::create_detached(url.as_str())
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))
+}
+
+/// Fails with a readable error when `branch` is not on the remote, instead of
+/// the "reference not found" libgit2 reports after fetching.
+fn check_remote_branch(url: &str, branch: &str, insecure: bool) -> Result<(), GhbareError> {
+    let mut remote = detached_remote(url)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(credential_callbacks(insecure)), None)
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    let wanted = format!("refs/heads/{}", branch);
+    let found = connection
+        .list()
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))?
+        .iter()
+        .any(|head| head.name() == wanted);
+    if !found {
+        return Err(branch_not_found(branch, url));
+    }
+    Ok(())
+}
+
+fn remote_default_branch(url: &str, insecure: bool) -> Result<String, GhbareError> {
+    let mut remote = detached_remote(url)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(credential_callbacks(insecure)), None)
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    let head = connection
+        .default_branch()
+        .map_err(|e| GhbareError::CloneError(format!("Remote has no HEAD branch: {}", e.message())))?;
+    let head = head
+        .as_str()
+        .ok_or_else(|| GhbareError::CloneError("Remote HEAD is not valid UTF-8".to_string()))?;
+
+    Ok(head.strip_prefix("refs/heads/").unwrap_or(head).to_string())
+}
+
+/// Applies the longest matching `url.<base>.insteadOf` prefix, like git does.
+fn rewrite_url(url: &str, config: &git2::Config) -> String {
+    let mut best: Option<(String, String)> = None;
+    if let Ok(mut entries) = config.entries(Some(r"^url\..*\.insteadof$")) {
+        while let Some(Ok(entry)) = entries.next() {
+            let (Some(name), Some(prefix)) = (entry.name(), entry.value()) else {
+                continue;
+            };
+            let Some(base) = name
+                .strip_prefix("url.")
+                .and_then(|n| n.strip_suffix(".insteadof"))
+            else {
+                continue;
+            };
+            if url.starts_with(prefix) && best.as_ref().is_none_or(|(p, _)| prefix.len() > p.len()) {
+                best = Some((prefix.to_string(), base.to_string()));
+            }
+        }
+    }
+    match best {
+        Some((prefix, base)) => format!("{}{}", base, &url[prefix.len()..]),
+        None => url.to_string(),
+    }
+}
+
+/// Points `refs/remotes/origin/HEAD` at the cloned HEAD branch, as libgit2 clones do,
+/// so post_clone_commands can find the branch to check out.
+fn set_origin_head(repo: &Repository) -> Result<(), GhbareError> {
+    let head = repo
+        .find_reference("HEAD")
+        .ok()
+        .and_then(|h| h.symbolic_target().map(|t| t.to_string()));
+    if let Some(branch) = head.as_deref().and_then(|t| t.strip_prefix("refs/heads/")) {
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            &format!("refs/remotes/origin/{}", branch),
+            true,
+            "clone: set origin/HEAD",
+        )
+        .map_err(|e| GhbareError::CloneError(e.message().to_string()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_apply_tls_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = Repository::init_bare(tmp.path()).unwrap();
+        let opts = CloneOptions {
+            insecure: true,
+            ca_bundle: Some(PathBuf::from("/etc/ssl/internal.pem")),
+            ..Default::default()
+        };
+        apply_tls_config(&repo, "https://git.internal:8443/team/repo.git", &opts).unwrap();
+        apply_tls_config(&repo, "git@git.internal:team/repo.git", &opts).unwrap();
+
+        let config = repo.config().unwrap().snapshot().unwrap();
+        assert!(!config.get_bool("http.https://git.internal:8443/.sslVerify").unwrap());
+        assert_eq!(
+            config.get_str("http.https://git.internal:8443/.sslCAInfo").unwrap(),
+            "/etc/ssl/internal.pem"
+        );
+        assert!(config.get_bool("http.sslVerify").is_err());
+    }
+
+    #[test]
+    fn test_ca_bundle_only_for_https() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source");
+        fs::create_dir(&source).unwrap();
+        crate::commands::test_support::init_repo_with_commit(&source);
+        let opts = CloneOptions {
+            ca_bundle: Some(tmp.path().join("missing.pem")),
+            quiet_progress: true,
+            ..Default::default()
+        };
+        assert!(https_ca_bundle("https://git.internal/team/repo.git", &opts).is_some());
+        assert!(https_ca_bundle("git@git.internal:team/repo.git", &opts).is_none());
+
+        // A missing bundle does not matter for a non-HTTPS clone
+        let url = format!("file://{}", source.join(".bare").display());
+        bare_clone(&url, &tmp.path().join("clone"), &opts).unwrap();
+    }
+
+    #[test]
+    fn test_validate_ca_bundle() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bundle = tmp.path().join("ca.pem");
+        assert!(validate_ca_bundle(&bundle).is_err());
+        fs::write(&bundle, "").unwrap();
+        assert!(validate_ca_bundle(&bundle).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reference() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        fs::create_dir(&project).unwrap();
+        crate::commands::test_support::init_repo_with_commit(&project);
+
+        let git_dir = validate_reference(&project).unwrap();
+        assert_eq!(git_dir.canonicalize().unwrap(), project.join(".bare").canonicalize().unwrap());
+        assert!(validate_reference(tmp.path()).is_err());
+    }
+
+    #[test]
+    fn test_bare_clone_with_reference() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source");
+        fs::create_dir(&source).unwrap();
+        crate::commands::test_support::init_repo_with_commit(&source);
+        let url = source.join(".bare").display().to_string();
+
+        let opts = CloneOptions {
+            reference: Some(source.clone()),
+            quiet_progress: true,
+            ..Default::default()
+        };
+        let shared = tmp.path().join("shared.git");
+        bare_clone(&url, &shared, &opts).unwrap();
+        assert!(shared.join("objects/info/alternates").exists());
+
+        let opts = CloneOptions {
+            dissociate: true,
+            ..opts
+        };
+        let own = tmp.path().join("own.git");
+        let repo = bare_clone(&url, &own, &opts).unwrap();
+        assert!(!own.join("objects/info/alternates").exists());
+        assert!(repo.find_reference("refs/heads/main").is_ok());
+    }
+
+    #[test]
+    fn test_bare_clone_single_commit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source");
+        let git = |args: &[&str]| {
+            let status = super::super::git_cmd()
+                .args(["-c", "user.name=bw", "-c", "user.email=bw@example.com"])
+                .args(args)
+                .current_dir(&source)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        fs::create_dir(&source).unwrap();
+        git(&["init", "-q", "-b", "main"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&["tag", "v1"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+
+        let opts = CloneOptions {
+            single_branch: true,
+            quiet_progress: true,
+            depth: Some(1),
+            no_tags: true,
+            ..Default::default()
+        };
+        let dest = tmp.path().join("dest.git");
+        let url = format!("file://{}", source.display());
+        let repo = bare_clone(&url, &dest, &opts).unwrap();
+
+        assert!(repo.is_shallow());
+        assert!(repo.tag_names(None).unwrap().is_empty());
+        let head = repo.find_reference("refs/heads/main").unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.summary(), Some("second"));
+        assert_eq!(
+            repo.config().unwrap().get_string("remote.origin.tagOpt").unwrap(),
+            "--no-tags"
+        );
+    }
+
+    #[test]
+    fn test_resume_bare_clone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source");
+        let url = format!("file://{}", source.join(".bare").display());
+        let dest = tmp.path().join("dest.git");
+        let opts = CloneOptions {
+            quiet_progress: true,
+            ..Default::default()
+        };
+
+        // The source is not there yet: the attempt fails but leaves the partial clone
+        assert!(resume_bare_clone(&url, &dest, &opts, 1).is_err());
+        assert!(dest.join("HEAD").exists());
+
+        fs::create_dir(&source).unwrap();
+        crate::commands::test_support::init_repo_with_commit(&source);
+        let repo = resume_bare_clone(&url, &dest, &opts, 1).unwrap();
+
+        assert!(repo.find_reference("refs/heads/main").is_ok());
+        assert_eq!(repo.head().unwrap().shorthand(), Some("main"));
+        assert_eq!(
+            repo.find_reference("refs/remotes/origin/HEAD").unwrap().symbolic_target(),
+            Some("refs/remotes/origin/main")
+        );
+    }
+
+    #[test]
+    fn test_rewrite_url() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("gitconfig");
+        fs::write(
+            &path,
+            "[url \"file:///srv/git/\"]\n\tinsteadOf = https://example.com/\n\
+             [url \"file:///srv/mine/\"]\n\tinsteadOf = https://example.com/me/\n\
+             [url \"ssh://push.example.com/\"]\n\tpushInsteadOf = https://github.com/\n",
+        )
+        .unwrap();
+        let config = git2::Config::open(&path).unwrap();
+
+        assert_eq!(
+            rewrite_url("https://example.com/user/repo.git", &config),
+            "file:///srv/git/user/repo.git"
+        );
+        assert_eq!(
+            rewrite_url("https://example.com/me/repo.git", &config),
+            "file:///srv/mine/repo.git"
+        );
+        // pushInsteadOf only applies to pushes
+        assert_eq!(
+            rewrite_url("https://github.com/user/repo.git", &config),
+            "https://github.com/user/repo.git"
+        );
+    }
+
+    #[test]
+    fn test_set_origin_head() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = Repository::init_bare(tmp.path()).unwrap();
+        repo.set_head("refs/heads/develop").unwrap();
+
+        set_origin_head(&repo).unwrap();
+        assert_eq!(
+            repo.find_reference("refs/remotes/origin/HEAD").unwrap().symbolic_target(),
+            Some("refs/remotes/origin/develop")
+        );
+
+        // A later HEAD replaces it; a detached HEAD leaves it alone
+        repo.set_head("refs/heads/main").unwrap();
+        set_origin_head(&repo).unwrap();
+        assert_eq!(
+            repo.find_reference("refs/remotes/origin/HEAD").unwrap().symbolic_target(),
+            Some("refs/remotes/origin/main")
+        );
+        let sig = git2::Signature::now("bw", "bw@example.com").unwrap();
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let commit = repo.commit(None, &sig, &sig, "detached", &tree, &[]).unwrap();
+        repo.set_head_detached(commit).unwrap();
+        set_origin_head(&repo).unwrap();
+        assert_eq!(
+            repo.find_reference("refs/remotes/origin/HEAD").unwrap().symbolic_target(),
+            Some("refs/remotes/origin/main")
+        );
+    }
+
+    #[test]
+    fn test_fetch_refspec() {
+        assert_eq!(fetch_refspec(None), "+refs/heads/*:refs/remotes/origin/*");
+        assert_eq!(
+            fetch_refspec(Some("main")),
+            "+refs/heads/main:refs/remotes/origin/main"
+        );
+    }
+}
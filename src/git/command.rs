@@ -0,0 +1,28 @@
+use crate::config;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+static NO_LAZY_FETCH: AtomicBool = AtomicBool::new(false);
+
+/// Makes the commands from `git_cmd` fail instead of fetching objects missing
+/// from a partial clone (`GIT_NO_LAZY_FETCH=1`), for `bw add --offline`.
+pub fn disable_lazy_fetch() {
+    NO_LAZY_FETCH.store(true, Ordering::Relaxed);
+}
+
+/// Returns a `Command` for the configured git binary (`git_command`, default "git").
+/// Falls back to "git" when the config file is missing or invalid.
+pub fn git_cmd() -> Command {
+    static GIT: OnceLock<String> = OnceLock::new();
+    let git = GIT.get_or_init(|| {
+        config::get_config()
+            .map(|c| c.git_command)
+            .unwrap_or_else(|_| "git".to_string())
+    });
+    let mut cmd = Command::new(git);
+    if NO_LAZY_FETCH.load(Ordering::Relaxed) {
+        cmd.env("GIT_NO_LAZY_FETCH", "1");
+    }
+    cmd
+}
@@ -1,3 +1,6 @@
 mod clone;
+mod command;
+mod netrc;
 
-pub use clone::bare_clone;
+pub use clone::{bare_clone, fetch_refspec, resume_bare_clone, CloneOptions};
+pub use command::{disable_lazy_fetch, git_cmd};
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetrcCredentials {
+    pub login: String,
+    pub password: String,
+}
+
+/// Credentials for `host` from the netrc file ($NETRC or ~/.netrc).
+pub fn lookup(host: &str) -> Option<NetrcCredentials> {
+    let content = std::fs::read_to_string(netrc_path()?).ok()?;
+    find_credentials(&content, host)
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    dirs::home_dir().map(|h| h.join(".netrc"))
+}
+
+/// Which entry the login/password tokens being read belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    Matched,
+    Default,
+    Other,
+}
+
+/// Returns the `machine <host>` entry, falling back to `default`.
+fn find_credentials(content: &str, host: &str) -> Option<NetrcCredentials> {
+    let mut tokens = content.split_whitespace();
+    let mut matched: Option<NetrcCredentials> = None;
+    let mut default: Option<NetrcCredentials> = None;
+    let mut current: Option<Entry> = None;
+    let mut login = String::new();
+    let mut password = String::new();
+
+    let mut finish = |current: Option<Entry>, login: &mut String, password: &mut String| {
+        let creds = NetrcCredentials {
+            login: std::mem::take(login),
+            password: std::mem::take(password),
+        };
+        match current {
+            Some(Entry::Matched) if matched.is_none() => matched = Some(creds),
+            Some(Entry::Default) if default.is_none() => default = Some(creds),
+            _ => {}
+        }
+    };
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => {
+                finish(current, &mut login, &mut password);
+                current = Some(if tokens.next() == Some(host) {
+                    Entry::Matched
+                } else {
+                    Entry::Other
+                });
+            }
+            "default" => {
+                finish(current, &mut login, &mut password);
+                current = Some(Entry::Default);
+            }
+            "login" => login = tokens.next().unwrap_or_default().to_string(),
+            "password" => password = tokens.next().unwrap_or_default().to_string(),
+            "account" => {
+                tokens.next();
+            }
+            _ => {}
+        }
+    }
+    finish(current, &mut login, &mut password);
+
+    matched.or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "machine github.com
+  login alice
+  password ghp_token
+
+machine gitlab.internal login bob password glpat-secret
+
+default login anonymous password guest
+";
+
+    #[test]
+    fn test_find_credentials_by_host() {
+        assert_eq!(
+            find_credentials(SAMPLE, "gitlab.internal"),
+            Some(NetrcCredentials {
+                login: "bob".to_string(),
+                password: "glpat-secret".to_string(),
+            })
+        );
+        assert_eq!(
+            find_credentials(SAMPLE, "github.com").unwrap().password,
+            "ghp_token"
+        );
+    }
+
+    #[test]
+    fn test_find_credentials_default() {
+        assert_eq!(
+            find_credentials(SAMPLE, "example.com").unwrap().login,
+            "anonymous"
+        );
+        assert_eq!(find_credentials("machine a login x password y", "b"), None);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::config::get_data_dir;
+use crate::error::GhbareError;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One cloned repository, stored as a JSON line in the index file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    pub path: String,
+    /// Unix timestamp (seconds)
+    pub cloned_at: u64,
+    /// Topics given with `bw get --tag`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+pub fn get_index_path() -> Result<PathBuf, GhbareError> {
+    Ok(get_data_dir()?.join("repos.jsonl"))
+}
+
+pub fn append_entry(index_path: &Path, entry: &IndexEntry) -> Result<(), GhbareError> {
+    if let Some(parent) = index_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(entry)
+        .map_err(|e| GhbareError::IndexError(e.to_string()))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(index_path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Reads all entries. A missing index is treated as empty.
+pub fn read_entries(index_path: &Path) -> Result<Vec<IndexEntry>, GhbareError> {
+    if !index_path.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(index_path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| GhbareError::IndexError(e.to_string()))
+        })
+        .collect()
+}
+
+pub fn write_entries(index_path: &Path, entries: &[IndexEntry]) -> Result<(), GhbareError> {
+    let mut content = String::new();
+    for entry in entries {
+        let line = serde_json::to_string(entry)
+            .map_err(|e| GhbareError::IndexError(e.to_string()))?;
+        content.push_str(&line);
+        content.push('\n');
+    }
+    fs::write(index_path, content)?;
+    Ok(())
+}
+
+pub fn now_unix() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(owner: &str, path: &str) -> IndexEntry {
+        IndexEntry {
+            host: "github.com".to_string(),
+            owner: owner.to_string(),
+            repo: "repo".to_string(),
+            path: path.to_string(),
+            cloned_at: 1700000000,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_append_and_read_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let index_path = tmp.path().join("data").join("repos.jsonl");
+        assert!(read_entries(&index_path).unwrap().is_empty());
+
+        append_entry(&index_path, &entry("alice", "/repos/a")).unwrap();
+        append_entry(&index_path, &entry("bob", "/repos/b")).unwrap();
+
+        let entries = read_entries(&index_path).unwrap();
+        assert_eq!(entries, vec![entry("alice", "/repos/a"), entry("bob", "/repos/b")]);
+    }
+
+    #[test]
+    fn test_write_entries_replaces_content() {
+        let tmp = tempfile::tempdir().unwrap();
+        let index_path = tmp.path().join("repos.jsonl");
+        append_entry(&index_path, &entry("alice", "/repos/a")).unwrap();
+        write_entries(&index_path, &[entry("bob", "/repos/b")]).unwrap();
+        assert_eq!(read_entries(&index_path).unwrap(), vec![entry("bob", "/repos/b")]);
+    }
+}
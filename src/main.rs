@@ -1,10 +1,13 @@
+mod color;
 mod commands;
 mod config;
 mod error;
 mod git;
+mod index;
 mod url;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use std::path::PathBuf;
 
 const APP_VERSION: &str = concat!(
     env!("CARGO_PKG_NAME"),
@@ -27,14 +30,35 @@ struct Cli {
 
     #[arg(long, short = 'V', help = "Print version")]
     version: bool,
+
+    /// Config profile: reads config.<PROFILE>.toml (falls back to config.toml). Also $BW_PROFILE
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// When to color output: auto (terminals without $NO_COLOR), always
+    /// (even when piped, e.g. into less -R) or never
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto",
+          value_parser = ["auto", "always", "never"])]
+    color: String,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Clone a repository as bare with worktree-friendly structure
+    /// Clone a repository as bare with worktree-friendly structure (alias: clone)
+    #[command(visible_alias = "clone", after_help = "Examples:
+  bw get github.com/user/repo
+  bw clone github.com/user/repo       # same as bw get
+  bw get git@github.com:user/repo.git
+  bw get github.com/user/repo --https -s .work
+  bw get github.com/user/repo --then 'code .'
+  echo github.com/user/repo | bw get -
+  bw get github.com/user/repo --into /tmp/review
+  bw get github.com/user/repo --single-commit
+  bw get github.com/me/fork --reference ~/repos/github.com/upstream/repo")]
     Get {
-        /// Repository URL or path (e.g., github.com/user/repo, git@github.com:user/repo.git)
-        repo: String,
+        /// Repository URL or path (e.g., github.com/user/repo, git@github.com:user/repo.git).
+        /// `-` reads it from stdin. If omitted, prompts for host, owner and repository
+        repo: Option<String>,
 
         /// SSH clone (default)
         #[arg(long)]
@@ -47,31 +71,547 @@ enum Commands {
         /// Suffix for directory name (e.g., repo.suffix)
         #[arg(long, short = 's')]
         suffix: Option<String>,
+
+        /// Fetch only the remote HEAD branch. Other branches must be fetched
+        /// (git fetch origin <branch>:<branch>) before `bw add` can use them
+        #[arg(long)]
+        single_branch: bool,
+
+        /// Fetch only this branch and make it the clone's HEAD, so its
+        /// worktree is the one created (implies --single-branch)
+        #[arg(long, short = 'b', value_name = "NAME")]
+        branch: Option<String>,
+
+        /// Clone from this host instead (the local path keeps the original host)
+        #[arg(long, value_name = "HOST")]
+        mirror_to: Option<String>,
+
+        /// Initialize submodules in the created worktrees
+        #[arg(long)]
+        recursive: bool,
+
+        /// Fetch submodules with depth 1 (requires --recursive)
+        #[arg(long, requires = "recursive")]
+        shallow_submodules: bool,
+
+        /// Update this many submodules in parallel (requires --recursive;
+        /// default: git's submodule.fetchJobs, serial unless configured)
+        #[arg(long, value_name = "N", requires = "recursive",
+              value_parser = clap::value_parser!(u32).range(1..))]
+        submodule_jobs: Option<u32>,
+
+        /// Host for specs without one, e.g. `owner/repo` (overrides default_host)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Owner for specs with only a repo name (overrides default_owner)
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Hide clone progress output, keeping the other step messages
+        #[arg(long)]
+        quiet_clone: bool,
+
+        /// Run this shell command in the HEAD worktree after cloning
+        #[arg(long, value_name = "CMD")]
+        then: Option<String>,
+
+        /// Clone into this absolute directory instead of <root>/<host>/<owner>/<repo>
+        #[arg(long, value_name = "PATH", conflicts_with = "suffix")]
+        into: Option<String>,
+
+        /// Tag the repository: links it under <root>/by-tag/<TAG>/ and records
+        /// the tag in the index (repeatable; see `bw tags`)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Borrow objects from a local clone (e.g., the upstream of a fork) to save
+        /// disk. The new clone breaks if that repository is deleted or gc'd, unless
+        /// --dissociate is also given
+        #[arg(long, value_name = "PATH")]
+        reference: Option<String>,
+
+        /// Copy the borrowed objects after cloning so the clone is self-contained
+        /// (saves transfer time, not disk)
+        #[arg(long, requires = "reference")]
+        dissociate: bool,
+
+        /// Minimal footprint clone: only the tip commit of the HEAD branch, no
+        /// history and no tags (same as --depth 1 --single-branch --no-tags)
+        #[arg(long)]
+        single_commit: bool,
+
+        /// Shallow clone: fetch only the last N commits of each branch
+        /// (git fetch --deepen / --unshallow gets more later)
+        #[arg(long, value_name = "N", conflicts_with = "single_commit",
+              value_parser = clap::value_parser!(u32).range(1..))]
+        depth: Option<u32>,
+
+        /// For large repos on flaky networks: fetch into <dir>/../.<name>.bw-partial
+        /// with retries, and resume from there when the command is run again
+        #[arg(long, conflicts_with = "reference")]
+        resumable: bool,
+
+        /// Skip TLS certificate verification (self-signed internal hosts only;
+        /// also kept for later fetches in the clone). See [[host]] insecure in config
+        #[arg(long)]
+        insecure: bool,
     },
+    /// Show how a repository spec resolves (URLs and local path) without cloning
+    #[command(after_help = "Examples:
+  bw info user/repo
+  bw info git@github.com:user/repo.git --json
+  bw info   # inside a repository cloned with write_clone_metadata = true")]
+    Info {
+        /// Repository URL or spec, as accepted by `bw get` (default: the current
+        /// repository, described by its .bw.toml)
+        spec: Option<String>,
+
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Move every managed repository to a new root and repair worktree links
+    MvRoot {
+        /// New root directory (absolute path)
+        new_root: String,
+
+        /// Show what would be moved without moving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List repositories recorded in the clone index (maintain_index = true)
+    Repos {
+        /// Only show repositories on this host
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Only show repositories of this owner
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Remove entries whose directories no longer exist
+        #[arg(long)]
+        prune: bool,
+    },
+    /// List tags given with `bw get --tag` and their repositories
+    Tags,
     /// Open config file in editor
-    Config,
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
     /// Add a new worktree with a new branch
-    Add {
-        /// Branch name to create (e.g., feature/000). If omitted, auto-generates wip/MMDD-HHmmss
-        branch: Option<String>,
+    Add(AddArgs),
+    /// Select a worktree with fzf and print its path
+    List(ListArgs),
+    /// Select a worktree with fzf and open $SHELL in it
+    Shell,
+    /// Prune stale worktree entries
+    Prune(PruneArgs),
+    /// Repair worktree links (.git files and .bare/worktrees) after moving things by hand
+    #[command(after_help = "Examples:
+  bw repair
+  bw repair ../moved-feature")]
+    Repair {
+        /// Worktree paths to repair (default: all registered worktrees)
+        paths: Vec<String>,
+    },
+    /// Check the config and the worktree links of the current repository
+    #[command(after_help = "Examples:
+  bw doctor
+  bw doctor --fix")]
+    Doctor {
+        /// Repair what can be fixed: create a missing config, rewrite .git,
+        /// git worktree repair, and git worktree prune (asks first)
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Clone the current repository again from scratch and recreate a worktree
+    /// for each branch that had one (for a broken .bare)
+    #[command(after_help = "Examples:
+  bw reclone
+  bw reclone --yes")]
+    Reclone {
+        /// Do not ask before deleting the repository directory
+        #[arg(long, short = 'y')]
+        yes: bool,
+
+        /// Reclone even when a worktree branch is missing on origin or has
+        /// unpushed commits (the old directory is then kept)
+        #[arg(long, short = 'f')]
+        force: bool,
+    },
+    /// Run git gc on the shared .bare repository
+    Gc {
+        /// Pass --aggressive to git gc
+        #[arg(long)]
+        aggressive: bool,
+    },
+    /// Print shell functions for cd-ing into worktrees (bwcd, bwadd)
+    #[command(after_help = "Examples:
+  eval \"$(bw env zsh)\"    # in ~/.zshrc
+  bw env fish | source      # in ~/.config/fish/config.fish")]
+    Env {
+        /// Shell to print the functions for
+        #[arg(value_parser = ["bash", "zsh", "fish"])]
+        shell: String,
+    },
+    /// Print the shell completion script, or install it with `install`
+    #[command(args_conflicts_with_subcommands = true, after_help = "Examples:
+  bw completions install              # shell from $SHELL
+  bw completions install zsh --dir ~/.config/zsh/completions
+  bw completions fish > ~/.config/fish/completions/bw.fish")]
+    Completions {
+        #[command(subcommand)]
+        action: Option<CompletionsAction>,
 
-        /// Base branch to create from (overrides bw.toml)
-        #[arg(long, short = 'b')]
-        base: Option<String>,
+        /// Shell to print the script for
+        #[arg(value_parser = ["bash", "zsh", "fish"])]
+        shell: Option<String>,
     },
     /// Remove a worktree
-    Rm {
+    #[command(visible_alias = "remove")]
+    Rm(RmArgs),
+    /// Show, set or clear the label of a worktree
+    #[command(after_help = "Examples:
+  bw label feature-login \"review PR 123\"
+  bw label feature-login
+  bw label feature-login --clear")]
+    Label {
         /// Worktree name (directory name)
         name: String,
 
-        /// Force removal
-        #[arg(long, short = 'f')]
-        force: bool,
+        /// New label; prints the current label when omitted
+        text: Option<String>,
+
+        /// Remove the label
+        #[arg(long, conflicts_with = "text")]
+        clear: bool,
+    },
+    /// Manage worktrees (add, list, remove, prune), like `git worktree`
+    Worktree {
+        #[command(subcommand)]
+        action: WorktreeAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum CompletionsAction {
+    /// Write the script where the shell loads completions from and print the
+    /// rc-file line if one is needed (zsh: ~/.zsh/completions/_bw,
+    /// fish: ~/.config/fish/completions/bw.fish,
+    /// bash: ~/.local/share/bash-completion/completions/bw)
+    Install {
+        /// Shell to install for (default: from $SHELL)
+        #[arg(value_parser = ["bash", "zsh", "fish"])]
+        shell: Option<String>,
+
+        /// Write into this directory instead
+        #[arg(long, value_name = "PATH")]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorktreeAction {
+    /// Add a new worktree with a new branch (same as `bw add`)
+    Add(AddArgs),
+    /// Select a worktree with fzf and print its path (same as `bw list`)
+    List(ListArgs),
+    /// Remove a worktree (same as `bw rm`)
+    #[command(visible_alias = "rm")]
+    Remove(RmArgs),
+    /// Prune stale worktree entries (same as `bw prune`)
+    Prune(PruneArgs),
+}
+
+#[derive(Args)]
+#[command(after_help = "Examples:
+  bw add feature/login
+  bw add feature/login -b develop
+  bw add feature/login --from-head
+  bw add feature/login --stash
+  bw add feature/login --from-stash stash@{1}
+  bw add feature/login --path /tmp/login-review
+  cd \"$(bw add feature/login --switch --print-path)\"
+  bw add
+
+Sparse-checkout workflow:
+  bw add feature/login --sparse apps/web --sparse libs
+  # or by hand:
+  bw add feature/login --no-checkout
+  cd feature-login
+  git sparse-checkout set --cone apps/web
+  git checkout")]
+struct AddArgs {
+    /// Branch name to create (e.g., feature/000). If omitted, auto-generates wip/MMDD-HHmmss
+    branch: Option<String>,
+
+    /// Base branch to create from (overrides bw.toml). `HEAD` uses the commit
+    /// checked out in the current directory
+    #[arg(long, short = 'b')]
+    base: Option<String>,
+
+    /// Branch from the commit checked out in the current directory (same as --base HEAD)
+    #[arg(long, conflicts_with = "base")]
+    from_head: bool,
+
+    /// Pick the base from local and remote branches with fzf (falls back to
+    /// the configured base when fzf is missing or nothing is selected). With
+    /// --dry-run fzf still opens, so the dry run shows the picked base
+    #[arg(long, conflicts_with_all = ["base", "from_head"])]
+    pick_base: bool,
+
+    /// Create the worktree at this path (absolute or relative to the current
+    /// directory) instead of <repo>/<dirname>
+    #[arg(long, value_name = "PATH")]
+    path: Option<PathBuf>,
+
+    /// Branch from HEAD (--from-head / --base HEAD) even while a rebase, merge
+    /// or bisect is in progress in the current worktree, and create the
+    /// worktree even past max_worktrees in bw.toml
+    #[arg(long)]
+    force: bool,
+
+    /// Print the result as JSON to stdout (messages go to stderr)
+    #[arg(long)]
+    print_json: bool,
+
+    /// Print only the created worktree path to stdout, e.g. for
+    /// cd "$(bw add --print-path feature/x)" (see `bw env`)
+    #[arg(long, conflicts_with_all = ["print_json", "dry_run"])]
+    print_path: bool,
+
+    /// Only print warnings and the created worktree path (to stdout with
+    /// --print-path, otherwise to stderr)
+    #[arg(long, short = 'q')]
+    quiet: bool,
+
+    /// If the branch already has a worktree (wherever it is), succeed and report
+    /// its path instead of failing (create it otherwise); stdout only with --print-path
+    #[arg(long, conflicts_with_all = ["print_json", "dry_run"])]
+    switch: bool,
+
+    /// Show what would be created without creating anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Copy git-ignored .env* files from the base worktree
+    #[arg(long)]
+    copy_env: bool,
+
+    /// Skip post_add_commands from bw.toml
+    #[arg(long)]
+    no_commands: bool,
+
+    /// Skip post_add_commands and disable git hooks (e.g., post-checkout)
+    /// while creating the worktree
+    #[arg(long)]
+    no_verify: bool,
+
+    /// Stash uncommitted changes here and pop them in the new worktree
+    #[arg(long)]
+    stash: bool,
+
+    /// Register the worktree without checking out files (see sparse-checkout below)
+    #[arg(long)]
+    no_checkout: bool,
+
+    /// Check out only this directory via cone-mode sparse-checkout (repeatable;
+    /// overrides default_sparse_patterns in bw.toml)
+    #[arg(long, value_name = "PATTERN")]
+    sparse: Vec<String>,
+
+    /// Fetch the base from origin first when it exists neither locally nor as origin/<base>
+    #[arg(long)]
+    fetch: bool,
+
+    /// Check out the branch if it already exists instead of failing
+    /// (default set by on_existing_branch in bw.toml)
+    #[arg(long)]
+    reuse_branch: bool,
+
+    /// Never contact a remote: no fetch or push, only local and existing
+    /// remote-tracking refs (also sets BW_OFFLINE=1 for post_add_commands)
+    #[arg(long, conflicts_with_all = ["fetch", "push"])]
+    offline: bool,
+
+    /// Note shown next to the worktree in `bw list` and its JSON output
+    #[arg(long, value_name = "TEXT")]
+    label: Option<String>,
+
+    /// Run `git push -u origin <branch>` after creating the worktree
+    /// (skipped for existing branches that already have an upstream)
+    #[arg(long)]
+    push: bool,
+
+    /// Fail when the worktree directory name is already used by a worktree of
+    /// another branch (e.g. `feature/x` vs `feature-x`; default: strict_dirnames in bw.toml).
+    /// Not checked with --path
+    #[arg(long)]
+    no_auto_dir: bool,
+
+    /// Create an empty commit with this message on the new branch
+    /// (skipped when the branch already exists)
+    #[arg(long, short = 'm', value_name = "TEXT")]
+    message: Option<String>,
+
+    /// Create a new branch from the base even when origin/<branch> exists
+    /// (by default such a branch is checked out tracking origin/<branch>)
+    #[arg(long)]
+    new: bool,
+
+    /// Create the new branch from the commit this stash was made on and pop
+    /// the stash into the worktree (conflicts are left there to resolve)
+    #[arg(
+        long,
+        value_name = "STASH",
+        conflicts_with_all = ["base", "from_head", "pick_base", "stash"]
+    )]
+    from_stash: Option<String>,
+}
+
+#[derive(Args)]
+#[command(after_help = "Examples:
+  bw list
+  cd \"$(bw list)\"
+  bw list --multi -0 | xargs -0 -n1 echo
+  bw list --stale 30 --remove --yes
+  bw list --json --pretty
+  bw list --filter 'feature/*'
+  bw list --all-repos --json")]
+struct ListArgs {
+    /// Allow selecting multiple worktrees
+    #[arg(long, short = 'm')]
+    multi: bool,
+
+    /// Terminate each path with NUL instead of newline (for xargs -0)
+    #[arg(long, short = '0')]
+    null: bool,
+
+    /// Print every worktree's branch name instead of selecting a path
+    /// (detached worktrees print their short HEAD)
+    #[arg(long)]
+    branch_only: bool,
+
+    /// Preview each worktree's uncommitted diff in fzf
+    /// (command overridable with fzf_preview_command)
+    #[arg(long, short = 'p')]
+    preview: bool,
+
+    /// Do not pipe long plain output through $PAGER (default: less -FRX)
+    #[arg(long)]
+    no_pager: bool,
+
+    /// Print worktrees whose last commit (git log -1 --format=%ct) is at least
+    /// DAYS old, with their age, instead of opening fzf
+    #[arg(long, value_name = "DAYS")]
+    stale: Option<u64>,
+
+    /// Remove the worktrees listed by --stale after asking, except the default
+    /// branch's worktree and the one holding the current directory
+    #[arg(long, requires = "stale")]
+    remove: bool,
+
+    /// Do not ask before removing with --remove
+    #[arg(long, short = 'y', requires = "remove")]
+    yes: bool,
+
+    /// Print all worktrees as JSON instead of selecting:
+    /// {"version": 1, "worktrees": [{path, branch, head, detached, label?}]}
+    #[arg(long)]
+    json: bool,
+
+    /// Indent the --json output for reading
+    #[arg(long, requires = "json")]
+    pretty: bool,
+
+    /// Only show worktrees whose branch matches this glob (e.g., 'feature/*');
+    /// applies to every output mode
+    #[arg(long, value_name = "PATTERN")]
+    filter: Option<String>,
+
+    /// Treat --filter as a regular expression instead of a glob
+    #[arg(long, requires = "filter")]
+    regex: bool,
+
+    /// Print only the number of worktrees (respects --filter)
+    #[arg(long, conflicts_with_all = ["json", "stale", "branch_only"])]
+    count: bool,
+
+    /// Print the worktrees of every repository under root, grouped by repository
+    /// (--json: {"version": 1, "repos": [{path, worktrees}]})
+    #[arg(long, conflicts_with_all = ["multi", "null", "branch_only", "preview", "stale"])]
+    all_repos: bool,
+
+    /// Add the abbreviated HEAD commit (8 characters) after the branch,
+    /// and head_short to --json
+    #[arg(long)]
+    show_head: bool,
+}
+
+#[derive(Args)]
+#[command(after_help = "Examples:
+  bw rm feature-login
+  bw rm feature/login --force
+  bw rm feature-login --delete-branch
+  bw rm /tmp/login-review")]
+struct RmArgs {
+    /// Worktree name (directory name), or the path of a registered worktree
+    name: String,
+
+    /// Force removal of a worktree with uncommitted changes
+    #[arg(long, short = 'f')]
+    force: bool,
+
+    /// Also delete the worktree's branch (default set by remove_deletes_branch in bw.toml)
+    #[arg(long, conflicts_with = "keep_branch")]
+    delete_branch: bool,
+
+    /// Delete the worktree's branch even if it is not fully merged (git branch -D);
+    /// implies --delete-branch
+    #[arg(long, conflicts_with = "keep_branch")]
+    force_branch: bool,
+
+    /// Keep the worktree's branch (the default unless remove_deletes_branch = true)
+    #[arg(long)]
+    keep_branch: bool,
+}
+
+#[derive(Args)]
+struct PruneArgs {
+    /// Only prune entries older than this (passed to git, e.g., 2.weeks.ago)
+    #[arg(long, value_name = "TIME")]
+    expire: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the value of a top-level config key
+    Get {
+        /// Config key (e.g., root, clone_method, suffix)
+        key: String,
+    },
+    /// Set a top-level config key, keeping comments in the file
+    Set {
+        /// Config key (root, clone_method or suffix)
+        key: String,
+
+        /// New value
+        value: String,
+    },
+    /// Print the effective config with defaults filled in and root expanded
+    Dump {
+        /// Output format
+        #[arg(long, default_value = "toml", value_parser = ["toml", "json"])]
+        format: String,
     },
 }
 
 fn main() {
     let cli = Cli::parse();
+    color::set_choice(color::ColorChoice::parse(&cli.color).unwrap_or_default());
 
     if cli.version {
         println!("{APP_VERSION}");
@@ -79,31 +619,204 @@ fn main() {
     }
 
     if let Err(e) = run(cli) {
-        eprintln!("Error: {e}");
+        if matches!(e.downcast_ref(), Some(error::GhbareError::Interrupted)) {
+            std::process::exit(130);
+        }
+        let prefix = color::paint("Error:", color::Style::Red, color::Stream::Stderr);
+        eprintln!("{prefix} {e}");
         std::process::exit(1);
     }
 }
 
 fn run(cli: Cli) -> anyhow::Result<()> {
+    config::set_profile(cli.profile);
+
     let Some(command) = cli.command else {
         eprintln!("No command specified. Use --help for usage.");
         std::process::exit(1);
     };
 
     match command {
-        Commands::Get { repo, ssh, https, suffix } => {
-            commands::get::execute(&repo, ssh, https, suffix)?;
+        Commands::Get {
+            repo,
+            ssh,
+            https,
+            suffix,
+            single_branch,
+            branch,
+            mirror_to,
+            recursive,
+            shallow_submodules,
+            submodule_jobs,
+            host,
+            owner,
+            quiet_clone,
+            then,
+            into,
+            tags,
+            reference,
+            dissociate,
+            single_commit,
+            depth,
+            resumable,
+            insecure,
+        } => {
+            let opts = commands::get::GetOptions {
+                ssh,
+                https,
+                suffix,
+                single_branch,
+                branch,
+                mirror_to,
+                recursive,
+                shallow_submodules,
+                submodule_jobs,
+                host,
+                owner,
+                quiet_clone,
+                then,
+                into,
+                tags,
+                reference,
+                dissociate,
+                single_commit,
+                depth,
+                resumable,
+                insecure,
+            };
+            commands::get::execute(repo.as_deref(), opts)?;
+        }
+        Commands::Info { spec, json } => {
+            commands::info::execute(spec.as_deref(), json)?;
         }
-        Commands::Config => {
-            commands::config::execute()?;
+        Commands::MvRoot { new_root, dry_run } => {
+            commands::mv_root::execute(&new_root, dry_run)?;
         }
-        Commands::Add { branch, base } => {
-            commands::bw::execute_add(branch.as_deref(), base)?;
+        Commands::Repos { host, owner, prune } => {
+            commands::repos::execute(host.as_deref(), owner.as_deref(), prune)?;
         }
-        Commands::Rm { name, force } => {
-            commands::bw::execute_rm(&name, force)?;
+        Commands::Tags => {
+            commands::tags::execute()?;
         }
+        Commands::Config { action } => match action {
+            None => commands::config::execute()?,
+            Some(ConfigAction::Get { key }) => commands::config::execute_get(&key)?,
+            Some(ConfigAction::Set { key, value }) => {
+                commands::config::execute_set(&key, &value)?
+            }
+            Some(ConfigAction::Dump { format }) => commands::config::execute_dump(&format)?,
+        },
+        Commands::Add(args) => run_add(args)?,
+        Commands::List(args) => run_list(args)?,
+        Commands::Shell => {
+            commands::bw::execute_shell()?;
+        }
+        Commands::Prune(args) => run_prune(args)?,
+        Commands::Repair { paths } => {
+            commands::bw::execute_repair(&paths)?;
+        }
+        Commands::Doctor { fix } => {
+            commands::doctor::execute(fix)?;
+        }
+        Commands::Reclone { yes, force } => {
+            commands::reclone::execute(yes, force)?;
+        }
+        Commands::Gc { aggressive } => {
+            commands::gc::execute(aggressive)?;
+        }
+        Commands::Rm(args) => run_rm(args)?,
+        Commands::Completions { action, shell } => match (action, shell) {
+            (Some(CompletionsAction::Install { shell, dir }), _) => {
+                commands::completions::execute_install(
+                    shell.as_deref(),
+                    dir.as_deref(),
+                    Cli::command(),
+                )?
+            }
+            (None, Some(shell)) => commands::completions::execute(&shell, Cli::command())?,
+            (None, None) => {
+                eprintln!("Specify a shell (bash, zsh, fish) or `install`. Use --help for usage.");
+                std::process::exit(1);
+            }
+        },
+        Commands::Env { shell } => {
+            commands::env::execute(&shell)?;
+        }
+        Commands::Label { name, text, clear } => {
+            commands::labels::execute(&name, text.as_deref(), clear)?;
+        }
+        Commands::Worktree { action } => match action {
+            WorktreeAction::Add(args) => run_add(args)?,
+            WorktreeAction::List(args) => run_list(args)?,
+            WorktreeAction::Remove(args) => run_rm(args)?,
+            WorktreeAction::Prune(args) => run_prune(args)?,
+        },
     }
 
     Ok(())
 }
+
+fn run_add(args: AddArgs) -> anyhow::Result<()> {
+    let opts = commands::bw::AddOptions {
+        base: args.base,
+        print_json: args.print_json,
+        print_path: args.print_path,
+        dry_run: args.dry_run,
+        copy_env: args.copy_env,
+        no_commands: args.no_commands,
+        no_verify: args.no_verify,
+        stash: args.stash,
+        no_checkout: args.no_checkout,
+        sparse: args.sparse,
+        message: args.message,
+        from_head: args.from_head,
+        force: args.force,
+        reuse_branch: args.reuse_branch,
+        fetch: args.fetch,
+        push: args.push,
+        pick_base: args.pick_base,
+        label: args.label,
+        path: args.path,
+        quiet: args.quiet,
+        switch: args.switch,
+        new: args.new,
+        from_stash: args.from_stash,
+        offline: args.offline,
+        no_auto_dir: args.no_auto_dir,
+    };
+    commands::bw::execute_add(args.branch.as_deref(), opts)
+}
+
+fn run_list(args: ListArgs) -> anyhow::Result<()> {
+    let opts = commands::bw::ListOptions {
+        multi: args.multi,
+        null: args.null,
+        branch_only: args.branch_only,
+        preview: args.preview,
+        no_pager: args.no_pager,
+        stale: args.stale,
+        remove: args.remove,
+        yes: args.yes,
+        json: args.json,
+        pretty: args.pretty,
+        filter: args.filter,
+        regex: args.regex,
+        count: args.count,
+        all_repos: args.all_repos,
+        show_head: args.show_head,
+    };
+    commands::bw::execute_list(opts)
+}
+
+fn run_rm(args: RmArgs) -> anyhow::Result<()> {
+    let delete_branch = match (args.delete_branch || args.force_branch, args.keep_branch) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    commands::bw::execute_rm(&args.name, args.force, delete_branch, args.force_branch)
+}
+
+fn run_prune(args: PruneArgs) -> anyhow::Result<()> {
+    commands::bw::execute_prune(args.expire.as_deref())
+}
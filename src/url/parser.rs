@@ -1,19 +1,37 @@
-use crate::error::GhbareError;
+use crate::error::{GhbareError, UrlErrorReason};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RepoInfo {
     pub host: String,
     pub owner: String,
     pub repo: String,
+    /// Non-default port of an `ssh://` URL; only used for SSH clones
+    pub ssh_port: Option<u16>,
+    /// Non-default port of an `https://` URL; only used for HTTPS clones
+    pub https_port: Option<u16>,
 }
 
 impl RepoInfo {
+    /// scp-like `git@host:owner/repo.git`, or `ssh://git@host:port/...` with a
+    /// port, which the scp-like form cannot express.
     pub fn to_ssh_url(&self) -> String {
-        format!("git@{}:{}/{}.git", self.host, self.owner, self.repo)
+        match self.ssh_port {
+            Some(port) => format!(
+                "ssh://git@{}:{}/{}/{}.git",
+                self.host, port, self.owner, self.repo
+            ),
+            None => format!("git@{}:{}/{}.git", self.host, self.owner, self.repo),
+        }
     }
 
     pub fn to_https_url(&self) -> String {
-        format!("https://{}/{}/{}.git", self.host, self.owner, self.repo)
+        match self.https_port {
+            Some(port) => format!(
+                "https://{}:{}/{}/{}.git",
+                self.host, port, self.owner, self.repo
+            ),
+            None => format!("https://{}/{}/{}.git", self.host, self.owner, self.repo),
+        }
     }
 
     pub fn to_local_path(&self) -> String {
@@ -24,6 +42,10 @@ impl RepoInfo {
 pub fn parse_repo_url(input: &str) -> Result<RepoInfo, GhbareError> {
     let input = input.trim();
 
+    if input.is_empty() {
+        return Err(invalid(input, UrlErrorReason::MissingHost));
+    }
+
     if input.starts_with("git@") {
         return parse_ssh_url(input);
     }
@@ -36,85 +58,164 @@ pub fn parse_repo_url(input: &str) -> Result<RepoInfo, GhbareError> {
         return parse_ssh_protocol_url(input);
     }
 
+    if let Some((scheme, _)) = input.split_once("://") {
+        return Err(invalid(
+            input,
+            UrlErrorReason::UnsupportedScheme(scheme.to_string()),
+        ));
+    }
+
+    if is_scp_like(input) {
+        return parse_ssh_url(input);
+    }
+
     parse_short_url(input)
 }
 
-fn parse_ssh_url(input: &str) -> Result<RepoInfo, GhbareError> {
-    let without_prefix = input
-        .strip_prefix("git@")
-        .ok_or_else(|| GhbareError::UrlParseError(input.to_string()))?;
+/// scp-like `[user@]host:owner/repo`, as git decides it: a colon before any
+/// slash. `host:8080/owner/repo` keeps meaning a short form with a port.
+fn is_scp_like(input: &str) -> bool {
+    let Some((host, path)) = input.split_once(':') else {
+        return false;
+    };
+    let port = path.split('/').next().unwrap_or_default();
+    let is_port = !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit());
+    !host.contains('/') && !is_port
+}
 
-    let parts: Vec<&str> = without_prefix.splitn(2, ':').collect();
-    if parts.len() != 2 {
-        return Err(GhbareError::UrlParseError(input.to_string()));
+fn invalid(input: &str, reason: UrlErrorReason) -> GhbareError {
+    GhbareError::InvalidRepoUrl {
+        input: input.to_string(),
+        reason,
     }
+}
 
-    let host = parts[0].to_string();
-    let path = parts[1].trim_end_matches(".git");
+/// `[user@]host:owner/repo`; the user (`git@` or any other) is dropped.
+fn parse_ssh_url(input: &str) -> Result<RepoInfo, GhbareError> {
+    let (user_host, path) = input
+        .split_once(':')
+        .ok_or_else(|| invalid(input, UrlErrorReason::Malformed))?;
+    let host = user_host.rsplit_once('@').map_or(user_host, |(_, host)| host);
+    if host.is_empty() {
+        return Err(invalid(input, UrlErrorReason::MissingHost));
+    }
 
-    parse_owner_repo(path, &host, input)
+    parse_owner_repo(strip_git_suffix(path), host, input)
 }
 
 fn parse_https_url(input: &str) -> Result<RepoInfo, GhbareError> {
-    let parsed =
-        url::Url::parse(input).map_err(|_| GhbareError::UrlParseError(input.to_string()))?;
-
-    let host = parsed
-        .host_str()
-        .ok_or_else(|| GhbareError::UrlParseError(input.to_string()))?
-        .to_string();
-
-    let path = parsed
-        .path()
-        .trim_start_matches('/')
-        .trim_end_matches(".git");
-
-    parse_owner_repo(path, &host, input)
+    parse_url_with_host(input)
 }
 
 fn parse_ssh_protocol_url(input: &str) -> Result<RepoInfo, GhbareError> {
-    let parsed =
-        url::Url::parse(input).map_err(|_| GhbareError::UrlParseError(input.to_string()))?;
+    parse_url_with_host(input)
+}
+
+fn parse_url_with_host(input: &str) -> Result<RepoInfo, GhbareError> {
+    let parsed = url::Url::parse(input).map_err(|e| match e {
+        url::ParseError::EmptyHost => invalid(input, UrlErrorReason::MissingHost),
+        _ => invalid(input, UrlErrorReason::Malformed),
+    })?;
 
     let host = parsed
         .host_str()
-        .ok_or_else(|| GhbareError::UrlParseError(input.to_string()))?
+        .filter(|h| !h.is_empty())
+        .ok_or_else(|| invalid(input, UrlErrorReason::MissingHost))?
         .to_string();
 
-    let path = parsed
-        .path()
-        .trim_start_matches('/')
-        .trim_end_matches(".git");
+    let mut path = strip_git_suffix(parsed.path().trim_start_matches('/'));
+    // Only github.com web pages have a known layout; elsewhere (e.g. GitLab
+    // subgroups `group/tree/project`) these segments may be part of the name
+    if parsed.scheme() == "https" && host == "github.com" {
+        path = truncate_web_path(path);
+    }
 
-    parse_owner_repo(path, &host, input)
+    // url omits the scheme's default port (443 for https). A port belongs to its
+    // scheme: the SSH daemon of https://host:8443 does not listen on 8443.
+    let port = parsed.port();
+    Ok(RepoInfo {
+        ssh_port: port.filter(|_| parsed.scheme() == "ssh"),
+        https_port: port.filter(|_| parsed.scheme() == "https"),
+        ..parse_owner_repo(path, &host, input)?
+    })
 }
 
 fn parse_short_url(input: &str) -> Result<RepoInfo, GhbareError> {
-    let path = input.trim_end_matches(".git");
+    let path = strip_git_suffix(input);
     let parts: Vec<&str> = path.splitn(3, '/').collect();
 
     if parts.len() != 3 {
-        return Err(GhbareError::UrlParseError(input.to_string()));
+        // `github.com/repo` lacks an owner; `user/repo` lacks a host
+        let reason = if looks_like_host(parts[0]) {
+            UrlErrorReason::MissingOwner
+        } else {
+            UrlErrorReason::MissingHost
+        };
+        return Err(invalid(input, reason));
+    }
+
+    let reason = if parts[0].is_empty() {
+        Some(UrlErrorReason::MissingHost)
+    } else if parts[1].is_empty() {
+        Some(UrlErrorReason::MissingOwner)
+    } else if parts[2].is_empty() {
+        Some(UrlErrorReason::MissingRepo)
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        return Err(invalid(input, reason));
     }
 
     Ok(RepoInfo {
         host: parts[0].to_string(),
         owner: parts[1].to_string(),
         repo: parts[2].to_string(),
+        ssh_port: None,
+        https_port: None,
     })
 }
 
+fn looks_like_host(segment: &str) -> bool {
+    segment.contains('.') || segment.contains(':') || segment == "localhost"
+}
+
+/// Strips a single trailing `.git`, keeping any other dots in the name (e.g. `repo.wiki`).
+fn strip_git_suffix(path: &str) -> &str {
+    path.strip_suffix(".git").unwrap_or(path)
+}
+
+/// Segments that follow `owner/repo` in GitHub web URLs copied from the browser
+/// (`/blob/main/src/lib.rs`, `/tree/dev`, `/pull/1`, `/commit/<sha>`).
+const WEB_PATH_MARKERS: &[&str] = &["blob", "tree", "pull", "commit"];
+
+/// Cuts `owner/repo/<marker>/...` down to `owner/repo`.
+fn truncate_web_path(path: &str) -> &str {
+    let mut segments = path.splitn(4, '/');
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some(owner), Some(repo), Some(marker)) if WEB_PATH_MARKERS.contains(&marker) => {
+            &path[..owner.len() + 1 + repo.len()]
+        }
+        _ => path,
+    }
+}
+
 fn parse_owner_repo(path: &str, host: &str, original: &str) -> Result<RepoInfo, GhbareError> {
     let parts: Vec<&str> = path.splitn(2, '/').collect();
 
-    if parts.len() != 2 {
-        return Err(GhbareError::UrlParseError(original.to_string()));
+    if parts[0].is_empty() {
+        return Err(invalid(original, UrlErrorReason::MissingOwner));
+    }
+    if parts.len() != 2 || parts[1].is_empty() {
+        return Err(invalid(original, UrlErrorReason::MissingRepo));
     }
 
     Ok(RepoInfo {
         host: host.to_string(),
         owner: parts[0].to_string(),
         repo: parts[1].to_string(),
+        ssh_port: None,
+        https_port: None,
     })
 }
 
@@ -146,6 +247,27 @@ mod tests {
         assert_eq!(info.repo, "repo");
     }
 
+    #[test]
+    fn test_parse_scp_like_url() {
+        let expected = RepoInfo {
+            host: "example.com".to_string(),
+            owner: "team".to_string(),
+            repo: "project".to_string(),
+            ssh_port: None,
+            https_port: None,
+        };
+        assert_eq!(parse_repo_url("example.com:team/project").unwrap(), expected);
+        assert_eq!(parse_repo_url("user@example.com:team/project").unwrap(), expected);
+        assert_eq!(parse_repo_url("user@example.com:team/project.git").unwrap(), expected);
+
+        // A port-like segment keeps the short form
+        let info = parse_repo_url("localhost:8080/team/project").unwrap();
+        assert_eq!(info.host, "localhost:8080");
+        assert_eq!(info.owner, "team");
+        assert!(!is_scp_like("github.com/user/repo"));
+        assert!(!is_scp_like("github.com/user/re:po"));
+    }
+
     #[test]
     fn test_parse_https_url() {
         let info = parse_repo_url("https://github.com/user/repo").unwrap();
@@ -162,12 +284,80 @@ mod tests {
         assert_eq!(info.repo, "repo");
     }
 
+    #[test]
+    fn test_parse_browser_urls() {
+        let expected = RepoInfo {
+            host: "github.com".to_string(),
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
+        };
+        for url in [
+            "https://github.com/owner/repo/blob/main/path/to/file.rs",
+            "https://github.com/owner/repo/tree/feature/x",
+            "https://github.com/owner/repo/pull/123",
+            "https://github.com/owner/repo/pull/123/files",
+            "https://github.com/owner/repo/commit/0123abc",
+        ] {
+            assert_eq!(parse_repo_url(url).unwrap(), expected, "{}", url);
+        }
+        assert_eq!(truncate_web_path("owner/blob"), "owner/blob");
+        assert_eq!(truncate_web_path("owner/repo/wiki"), "owner/repo/wiki");
+
+        // Other hosts and forms keep the whole path
+        for (url, repo) in [
+            ("https://gitlab.com/group/tree/project", "tree/project"),
+            ("github.com/owner/repo/blob/main", "repo/blob/main"),
+            ("git@github.com:owner/repo/pull/1.git", "repo/pull/1"),
+            ("ssh://git@github.com/owner/repo/commit/abc", "repo/commit/abc"),
+        ] {
+            assert_eq!(parse_repo_url(url).unwrap().repo, repo, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_port_round_trip() {
+        let info = parse_repo_url("ssh://git@gitlab.internal:2222/group/project.git").unwrap();
+        assert_eq!(info.host, "gitlab.internal");
+        assert_eq!(info.ssh_port, Some(2222));
+        assert_eq!(info.to_ssh_url(), "ssh://git@gitlab.internal:2222/group/project.git");
+        assert_eq!(parse_repo_url(&info.to_ssh_url()).unwrap(), info);
+
+        let info = parse_repo_url("https://git.internal:8443/group/project").unwrap();
+        assert_eq!(info.https_port, Some(8443));
+        assert_eq!(info.to_https_url(), "https://git.internal:8443/group/project.git");
+        assert_eq!(parse_repo_url(&info.to_https_url()).unwrap(), info);
+        assert_eq!(info.to_local_path(), "git.internal/group/project");
+
+        // Default ports and short forms carry none
+        for url in [
+            "https://github.com:443/user/repo",
+            "github.com/user/repo",
+            "git@github.com:user/repo.git",
+        ] {
+            let info = parse_repo_url(url).unwrap();
+            assert_eq!((info.ssh_port, info.https_port), (None, None), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_port_stays_with_its_scheme() {
+        let info = parse_repo_url("https://git.internal:8443/group/project").unwrap();
+        assert_eq!(info.to_ssh_url(), "git@git.internal:group/project.git");
+
+        let info = parse_repo_url("ssh://git@git.internal:2222/group/project.git").unwrap();
+        assert_eq!(info.to_https_url(), "https://git.internal/group/project.git");
+    }
+
     #[test]
     fn test_to_ssh_url() {
         let info = RepoInfo {
             host: "github.com".to_string(),
             owner: "user".to_string(),
             repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
         };
         assert_eq!(info.to_ssh_url(), "git@github.com:user/repo.git");
     }
@@ -178,6 +368,8 @@ mod tests {
             host: "github.com".to_string(),
             owner: "user".to_string(),
             repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
         };
         assert_eq!(info.to_https_url(), "https://github.com/user/repo.git");
     }
@@ -188,13 +380,84 @@ mod tests {
             host: "github.com".to_string(),
             owner: "user".to_string(),
             repo: "repo".to_string(),
+            ssh_port: None,
+            https_port: None,
         };
         assert_eq!(info.to_local_path(), "github.com/user/repo");
     }
 
+    #[test]
+    fn test_parse_wiki_repo() {
+        let info = parse_repo_url("github.com/user/repo.wiki").unwrap();
+        assert_eq!(info.repo, "repo.wiki");
+        assert_eq!(info.to_local_path(), "github.com/user/repo.wiki");
+        assert_eq!(info.to_ssh_url(), "git@github.com:user/repo.wiki.git");
+        assert_eq!(info.to_https_url(), "https://github.com/user/repo.wiki.git");
+    }
+
+    #[test]
+    fn test_parse_wiki_repo_with_git_suffix() {
+        let info = parse_repo_url("github.com/user/repo.wiki.git").unwrap();
+        assert_eq!(info.repo, "repo.wiki");
+        assert_eq!(info.to_local_path(), "github.com/user/repo.wiki");
+
+        let info = parse_repo_url("git@github.com:user/repo.wiki.git").unwrap();
+        assert_eq!(info.repo, "repo.wiki");
+
+        let info = parse_repo_url("https://github.com/user/repo.wiki.git").unwrap();
+        assert_eq!(info.repo, "repo.wiki");
+    }
+
+    #[test]
+    fn test_strip_only_single_git_suffix() {
+        let info = parse_repo_url("github.com/user/repo.git.git").unwrap();
+        assert_eq!(info.repo, "repo.git");
+    }
+
     #[test]
     fn test_invalid_url() {
         let result = parse_repo_url("invalid");
         assert!(result.is_err());
     }
+
+    fn reason(input: &str) -> UrlErrorReason {
+        match parse_repo_url(input) {
+            Err(GhbareError::InvalidRepoUrl { reason, .. }) => reason,
+            other => panic!("expected InvalidRepoUrl for {:?}, got {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_url_reasons() {
+        assert_eq!(reason("user/repo"), UrlErrorReason::MissingHost);
+        assert_eq!(reason("/user/repo"), UrlErrorReason::MissingHost);
+        assert_eq!(reason("git@:user/repo.git"), UrlErrorReason::MissingHost);
+        assert_eq!(reason(":user/repo"), UrlErrorReason::MissingHost);
+        assert_eq!(reason("https://"), UrlErrorReason::MissingHost);
+
+        assert_eq!(reason("github.com/repo"), UrlErrorReason::MissingOwner);
+        assert_eq!(reason("github.com//repo"), UrlErrorReason::MissingOwner);
+        assert_eq!(reason("https://github.com/"), UrlErrorReason::MissingOwner);
+
+        assert_eq!(reason("github.com/user/"), UrlErrorReason::MissingRepo);
+        assert_eq!(reason("git@github.com:user"), UrlErrorReason::MissingRepo);
+        assert_eq!(reason("https://github.com/user"), UrlErrorReason::MissingRepo);
+
+        assert_eq!(
+            reason("ftp://github.com/user/repo"),
+            UrlErrorReason::UnsupportedScheme("ftp".to_string())
+        );
+
+        assert_eq!(reason("git@github.com"), UrlErrorReason::Malformed);
+        assert_eq!(reason("https://[::1/user/repo"), UrlErrorReason::Malformed);
+    }
+
+    #[test]
+    fn test_invalid_url_message() {
+        let err = parse_repo_url("github.com/repo").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse repository URL: missing owner in 'github.com/repo'"
+        );
+    }
 }
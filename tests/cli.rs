@@ -0,0 +1,176 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+fn bw() -> Command {
+    assert_cmd::cargo::cargo_bin_cmd!("bw")
+}
+
+#[test]
+fn test_get_help_has_examples() {
+    bw().args(["get", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Examples:"))
+        .stdout(predicate::str::contains(
+            "bw get github.com/user/repo --https -s .work",
+        ));
+}
+
+#[test]
+fn test_add_help_has_examples() {
+    bw().args(["add", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bw add feature/login -b develop"));
+}
+
+#[test]
+fn test_list_help_has_examples() {
+    bw().args(["list", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("cd \"$(bw list)\""));
+}
+
+#[test]
+fn test_rm_help_has_examples() {
+    bw().args(["rm", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bw rm feature-login"));
+}
+
+#[test]
+fn test_shallow_submodules_requires_recursive() {
+    bw().args(["get", "github.com/user/repo", "--shallow-submodules"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--recursive"));
+}
+
+#[test]
+fn test_submodule_jobs_must_be_positive() {
+    bw().args(["get", "github.com/user/repo", "--recursive", "--submodule-jobs", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--submodule-jobs"));
+}
+
+#[test]
+fn test_worktree_group_has_subcommands() {
+    bw().args(["worktree", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("add"))
+        .stdout(predicate::str::contains("list"))
+        .stdout(predicate::str::contains("remove"))
+        .stdout(predicate::str::contains("prune"));
+}
+
+#[test]
+fn test_worktree_add_shares_add_options() {
+    bw().args(["worktree", "add", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--no-verify"));
+}
+
+#[test]
+fn test_remove_is_alias_for_rm() {
+    bw().args(["remove", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bw rm feature-login"));
+    bw().args(["worktree", "rm", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Force removal"));
+}
+
+#[test]
+fn test_rm_branch_flags_conflict() {
+    bw().args(["rm", "feature-login", "--delete-branch", "--keep-branch"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn test_offline_conflicts_with_network_flags() {
+    for flag in ["--fetch", "--push"] {
+        bw().args(["add", "feature/x", "--offline", flag])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+#[test]
+fn test_env_prints_shell_functions() {
+    bw().args(["env", "zsh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bwcd()"))
+        .stdout(predicate::str::contains("bw add --print-path"));
+    bw().args(["env", "tcsh"]).assert().failure();
+}
+
+#[test]
+fn test_clone_is_alias_for_get() {
+    bw().args(["clone", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bw get github.com/user/repo --https -s .work"));
+    bw().arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[aliases: clone]"));
+}
+
+#[test]
+fn test_color_option() {
+    let tmp = tempfile::tempdir().unwrap();
+    bw().args(["rm", "x", "--color", "always"])
+        .current_dir(tmp.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("\x1b[31mError:\x1b[0m "));
+    // piped stderr is not a terminal, so auto means no color
+    bw().args(["rm", "x"])
+        .current_dir(tmp.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("Error: "));
+    bw().args(["--color", "sometimes", "rm", "x"]).assert().failure();
+}
+
+#[test]
+fn test_completions_install() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("completions");
+    bw().args(["completions", "install", "zsh", "--dir"])
+        .arg(&dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("fpath=({} $fpath)", dir.display())));
+    let script = std::fs::read_to_string(dir.join("_bw")).unwrap();
+    assert!(script.starts_with("#compdef bw"));
+
+    // Detected from $SHELL
+    bw().args(["completions", "install", "--dir"])
+        .arg(&dir)
+        .env("SHELL", "/usr/bin/fish")
+        .assert()
+        .success();
+    assert!(dir.join("bw.fish").exists());
+    bw().args(["completions", "install"])
+        .env("SHELL", "/bin/tcsh")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot detect a supported shell"));
+
+    bw().args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("complete -F _bw"));
+}
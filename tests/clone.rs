@@ -0,0 +1,801 @@
+//! End-to-end `bw get` against local source repositories.
+//!
+//! `Sandbox` isolates HOME and the XDG directories and rewrites
+//! `https://example.com/` to a `file://` directory with `url.<base>.insteadOf`,
+//! so the real clone path runs offline and deterministically.
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+struct Sandbox {
+    tmp: TempDir,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        let sandbox = Self {
+            tmp: tempfile::tempdir().unwrap(),
+        };
+        for dir in ["home", "config/ghqb", "data", "remotes"] {
+            fs::create_dir_all(sandbox.path(dir)).unwrap();
+        }
+        fs::write(
+            sandbox.path("config/ghqb/config.toml"),
+            format!("root = \"{}\"\n", sandbox.root().display()),
+        )
+        .unwrap();
+        fs::write(
+            sandbox.path("home/.gitconfig"),
+            format!(
+                "[url \"file://{}/\"]\n\tinsteadOf = https://example.com/\n",
+                sandbox.path("remotes").display()
+            ),
+        )
+        .unwrap();
+        sandbox
+    }
+
+    fn path(&self, rel: &str) -> PathBuf {
+        self.tmp.path().join(rel)
+    }
+
+    fn root(&self) -> PathBuf {
+        self.path("repos")
+    }
+
+    /// Creates the source of `https://example.com/<owner>/<repo>.git`: two
+    /// commits on `main`, a `develop` branch and a `v1.0` tag on the first commit.
+    fn add_remote(&self, owner: &str, repo: &str) {
+        let remote = self.path(&format!("remotes/{}/{}.git", owner, repo));
+        let work = self.path(&format!("work/{}/{}", owner, repo));
+        fs::create_dir_all(&work).unwrap();
+        self.git(self.tmp.path(), &["init", "-q", "--bare", "-b", "main", path_str(&remote)]);
+        self.git(&work, &["init", "-q", "-b", "main"]);
+        fs::write(work.join("README.md"), "first\n").unwrap();
+        self.git(&work, &["add", "README.md"]);
+        self.git(&work, &["commit", "-q", "-m", "first"]);
+        self.git(&work, &["tag", "v1.0"]);
+        self.git(&work, &["branch", "develop"]);
+        fs::write(work.join("README.md"), "second\n").unwrap();
+        self.git(&work, &["commit", "-q", "-am", "second"]);
+        self.git(&work, &["push", "-q", path_str(&remote), "main", "develop", "v1.0"]);
+    }
+
+    /// `bw` with HOME, XDG directories and git config confined to the sandbox.
+    fn bw(&self) -> Command {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("bw");
+        cmd.env("HOME", self.path("home"))
+            .env("XDG_CONFIG_HOME", self.path("config"))
+            .env("XDG_DATA_HOME", self.path("data"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env_remove("GIT_CONFIG_GLOBAL")
+            .env_remove("BW_PROFILE");
+        cmd
+    }
+
+    /// Runs git in `dir` and returns its stdout.
+    fn git(&self, dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(["-c", "user.name=bw", "-c", "user.email=bw@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .env("HOME", self.path("home"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env_remove("GIT_CONFIG_GLOBAL")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Names of the refs under `prefix` in the clone's `.bare`.
+    fn refs(&self, project: &Path, prefix: &str) -> Vec<String> {
+        self.git(
+            &project.join(".bare"),
+            &["for-each-ref", "--format=%(refname:short)", prefix],
+        )
+        .lines()
+        .map(str::to_string)
+        .collect()
+    }
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn test_get_creates_bare_layout() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--quiet-clone"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    assert!(project.join(".bare").is_dir());
+    assert_eq!(
+        fs::read_to_string(project.join(".git")).unwrap(),
+        "gitdir: .bare\n"
+    );
+    assert!(project.join(".envrc").exists());
+    assert_eq!(
+        fs::read_to_string(project.join("main/README.md")).unwrap(),
+        "second\n"
+    );
+    assert_eq!(
+        sandbox.refs(&project, "refs/remotes/origin"),
+        vec!["origin/HEAD", "origin/develop", "origin/main"]
+    );
+    assert_eq!(sandbox.refs(&project, "refs/tags"), vec!["v1.0"]);
+}
+
+#[test]
+fn test_get_single_branch() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--single-branch"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    assert_eq!(
+        sandbox.refs(&project, "refs/remotes/origin"),
+        vec!["origin/HEAD", "origin/main"]
+    );
+    assert!(project.join("main").is_dir());
+}
+
+#[test]
+fn test_get_single_branch_with_custom_post_clone_commands() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    // Commands that ignore $BW_FETCH_REFSPEC fetch every branch
+    let config = sandbox.path("config/ghqb/config.toml");
+    let content = fs::read_to_string(&config).unwrap();
+    fs::write(
+        &config,
+        content
+            + "post_clone_commands = \"\"\"\n\
+               git config --file .bare/config remote.origin.fetch '+refs/heads/*:refs/remotes/origin/*'\n\
+               git fetch -q origin\n\"\"\"\n",
+    )
+    .unwrap();
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--single-branch"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    assert_eq!(
+        sandbox.refs(&project, "refs/remotes/origin"),
+        vec!["origin/HEAD", "origin/main"]
+    );
+    assert_eq!(
+        sandbox.git(&project, &["config", "remote.origin.fetch"]),
+        "+refs/heads/main:refs/remotes/origin/main\n"
+    );
+}
+
+#[test]
+fn test_get_single_commit() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--single-commit"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    assert!(project.join(".bare/shallow").exists());
+    assert!(sandbox.refs(&project, "refs/tags").is_empty());
+    let log = sandbox.git(&project.join("main"), &["log", "--format=%s"]);
+    assert_eq!(log, "second\n");
+}
+
+#[test]
+fn test_get_invalid_tag_fails_before_cloning() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--tag", "ok", "--tag", "a/b"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid tag name: 'a/b'"));
+    assert!(!sandbox.root().join("example.com/user/repo").exists());
+}
+
+#[test]
+fn test_get_existing_repository_fails() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    for expected_success in [true, false] {
+        let assert = sandbox
+            .bw()
+            .args(["get", "example.com/user/repo", "--https"])
+            .assert();
+        if expected_success {
+            assert.success();
+        } else {
+            assert
+                .failure()
+                .stderr(predicates::str::contains("Repository already cloned"));
+        }
+    }
+}
+
+#[test]
+fn test_clone_alias_runs_get() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["clone", "example.com/user/repo", "--https"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Done! Repository cloned to"));
+    assert!(sandbox.root().join("example.com/user/repo/main").is_dir());
+}
+
+#[test]
+fn test_get_resumable() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--resumable"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    assert!(!sandbox.root().join("example.com/user/.repo.bw-partial").exists());
+    assert_eq!(
+        fs::read_to_string(project.join("main/README.md")).unwrap(),
+        "second\n"
+    );
+    // Laid out like a normal clone: only main is local, tracking origin/main
+    assert_eq!(sandbox.refs(&project, "refs/heads"), vec!["main"]);
+    assert_eq!(
+        sandbox.refs(&project, "refs/remotes/origin"),
+        vec!["origin/HEAD", "origin/develop", "origin/main"]
+    );
+    assert_eq!(
+        sandbox.git(&project, &["rev-parse", "--abbrev-ref", "main@{upstream}"]),
+        "origin/main\n"
+    );
+    assert_eq!(sandbox.refs(&project, "refs/tags"), vec!["v1.0"]);
+
+    sandbox
+        .bw()
+        .args(["add", "develop"])
+        .current_dir(&project)
+        .assert()
+        .success();
+    assert_eq!(
+        sandbox.git(&project, &["rev-parse", "--abbrev-ref", "develop@{upstream}"]),
+        "origin/develop\n"
+    );
+}
+
+#[test]
+fn test_add_at_external_path() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    fs::write(project.join("bw.toml"), "strict_dirnames = true\n").unwrap();
+    sandbox
+        .bw()
+        .args(["add", "review", "--label", "inner"])
+        .current_dir(&project)
+        .assert()
+        .success();
+    // Sharing the last component with <repo>/review is neither a collision
+    // nor a shared label
+    let external = sandbox.path("elsewhere/review");
+    sandbox
+        .bw()
+        .args(["add", "feature/x", "--path", path_str(&external), "--label", "outer"])
+        .current_dir(&project)
+        .assert()
+        .success();
+    for (name, label) in [("review", "inner\n"), (path_str(&external), "outer\n")] {
+        sandbox
+            .bw()
+            .args(["label", name])
+            .current_dir(&project)
+            .assert()
+            .success()
+            .stdout(label);
+    }
+
+    assert!(!project.join("feature-x").exists());
+    assert_eq!(
+        fs::read_to_string(external.join("README.md")).unwrap(),
+        "second\n"
+    );
+    assert_eq!(
+        sandbox.git(&external, &["branch", "--show-current"]),
+        "feature/x\n"
+    );
+    let worktrees = sandbox.git(&project, &["worktree", "list", "--porcelain"]);
+    assert!(worktrees.contains(&format!("worktree {}\n", external.display())));
+}
+
+#[test]
+fn test_add_quiet_prints_only_the_path() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    let output = sandbox
+        .bw()
+        .args(["add", "feature/x", "--quiet", "--print-path"])
+        .current_dir(&project)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let path = fs::canonicalize(project.join("feature-x")).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(fs::canonicalize(stdout.trim_end()).unwrap(), path);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+
+    // Every optional step stays silent too
+    fs::write(
+        project.join("bw.toml"),
+        "copy_from_worktree = [\".env\"]\n\
+         default_sparse_patterns = [\"docs\"]\n\
+         post_add_commands = \"true\"\n\
+         [worktree_git_config]\n\
+         \"core.autocrlf\" = \"false\"\n",
+    )
+    .unwrap();
+    fs::write(project.join(".bare/info/exclude"), ".env\n").unwrap();
+    let main = project.join("main");
+    // No worktree is on origin/release, so any may be the copy source
+    for source in [&main, &project.join("feature-x")] {
+        fs::write(source.join(".env"), "TOKEN=x\n").unwrap();
+    }
+    fs::write(main.join("README.md"), "changed\n").unwrap();
+    let remote = sandbox.path("remotes/user/repo.git");
+    sandbox.git(&remote, &["branch", "release", "main"]);
+
+    let output = sandbox
+        .bw()
+        .args(["add", "feature/y", "--quiet", "--print-path", "--stash"])
+        .args(["--base", "release", "--fetch", "--message", "start", "--push"])
+        .current_dir(&main)
+        .env("GIT_AUTHOR_NAME", "bw")
+        .env("GIT_AUTHOR_EMAIL", "bw@example.com")
+        .env("GIT_COMMITTER_NAME", "bw")
+        .env("GIT_COMMITTER_EMAIL", "bw@example.com")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    let feature = project.join("feature-y");
+    assert_eq!(fs::read_to_string(feature.join(".env")).unwrap(), "TOKEN=x\n");
+    assert_eq!(fs::read_to_string(feature.join("README.md")).unwrap(), "changed\n");
+    assert_eq!(sandbox.git(&feature, &["log", "-1", "--format=%s"]), "start\n");
+    assert_eq!(sandbox.git(&remote, &["branch", "--list", "feature/y"]), "  feature/y\n");
+}
+
+#[test]
+fn test_add_failure_after_stash_says_changes_are_stashed() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+    let project = sandbox.root().join("example.com/user/repo");
+    // git config rejects a key without a section, after the worktree was added
+    fs::write(project.join("bw.toml"), "[worktree_git_config]\n\"nosection\" = \"x\"\n").unwrap();
+    let main = project.join("main");
+    fs::write(main.join("README.md"), "changed\n").unwrap();
+
+    sandbox
+        .bw()
+        .args(["add", "feature/x", "--stash"])
+        .current_dir(&main)
+        .env("GIT_AUTHOR_NAME", "bw")
+        .env("GIT_AUTHOR_EMAIL", "bw@example.com")
+        .env("GIT_COMMITTER_NAME", "bw")
+        .env("GIT_COMMITTER_EMAIL", "bw@example.com")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("your changes are still in the stash"));
+    assert!(sandbox
+        .git(&main, &["stash", "list"])
+        .contains("bw add --stash: feature/x"));
+}
+
+#[test]
+fn test_get_writes_clone_metadata() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    let config = sandbox.path("config/ghqb/config.toml");
+    let content = fs::read_to_string(&config).unwrap();
+    fs::write(&config, content + "write_clone_metadata = true\n").unwrap();
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    let metadata = fs::read_to_string(project.join(".bw.toml")).unwrap();
+    assert!(metadata.contains("clone_url = \"https://example.com/user/repo.git\""));
+    assert!(metadata.contains("clone_method = \"https\""));
+
+    sandbox
+        .bw()
+        .args(["info"])
+        .current_dir(project.join("main"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("method https\n"));
+}
+
+#[test]
+fn test_add_switch_creates_then_reuses() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+    let project = sandbox.root().join("example.com/user/repo");
+    let path = project.join("feature-x");
+
+    for _ in 0..2 {
+        let output = sandbox
+            .bw()
+            .args(["add", "feature/x", "--switch", "--print-path"])
+            .current_dir(&project)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            fs::canonicalize(stdout.trim_end()).unwrap(),
+            fs::canonicalize(&path).unwrap()
+        );
+    }
+
+    // The directory belongs to feature/x, not feature-x
+    sandbox
+        .bw()
+        .args(["add", "feature-x", "--switch"])
+        .current_dir(&project)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("already used by the worktree of 'feature/x'"));
+
+    // Found by branch even outside <repo>/<dirname>; stdout stays empty without --print-path
+    let elsewhere = sandbox.path("review");
+    sandbox
+        .bw()
+        .args(["add", "feature/y", "--path"])
+        .arg(&elsewhere)
+        .current_dir(&project)
+        .assert()
+        .success();
+    sandbox
+        .bw()
+        .args(["add", "feature/y", "--switch"])
+        .current_dir(&project)
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicates::str::contains("Worktree already exists"));
+    let output = sandbox
+        .bw()
+        .args(["add", "feature/y", "--switch", "--print-path"])
+        .current_dir(&project)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        fs::canonicalize(String::from_utf8(output.stdout).unwrap().trim_end()).unwrap(),
+        fs::canonicalize(&elsewhere).unwrap()
+    );
+    assert!(!project.join("feature-y").exists());
+}
+
+#[test]
+fn test_add_tracks_existing_remote_branch() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+    let project = sandbox.root().join("example.com/user/repo");
+
+    sandbox
+        .bw()
+        .args(["add", "develop"])
+        .current_dir(&project)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("tracking origin/develop"));
+    let develop = project.join("develop");
+    assert_eq!(fs::read_to_string(develop.join("README.md")).unwrap(), "first\n");
+    assert_eq!(
+        sandbox.git(&develop, &["rev-parse", "--abbrev-ref", "develop@{upstream}"]),
+        "origin/develop\n"
+    );
+}
+
+#[test]
+fn test_add_new_ignores_existing_remote_branch() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+    let project = sandbox.root().join("example.com/user/repo");
+
+    sandbox
+        .bw()
+        .args(["add", "develop", "--new"])
+        .current_dir(&project)
+        .assert()
+        .success();
+    let develop = project.join("develop");
+    // Branched from main, not origin/develop
+    assert_eq!(fs::read_to_string(develop.join("README.md")).unwrap(), "second\n");
+    assert_eq!(
+        sandbox.git(&develop, &["for-each-ref", "--format=%(upstream)", "refs/heads/develop"]),
+        "\n"
+    );
+}
+
+// The fzf stub is a shell script
+#[cfg(unix)]
+#[test]
+fn test_add_pick_base_falls_back_when_nothing_selected() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+    let project = sandbox.root().join("example.com/user/repo");
+
+    // fzf exits with 1 when nothing matches
+    let bin = sandbox.path("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let fzf = bin.join("fzf");
+    fs::write(&fzf, "#!/bin/sh\nexit 1\n").unwrap();
+    fs::set_permissions(&fzf, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(bin).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    sandbox
+        .bw()
+        .args(["add", "feature/x", "--pick-base"])
+        .current_dir(&project)
+        .env("PATH", path)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "No base selected; using the configured base",
+        ));
+    let readme = fs::read_to_string(project.join("feature-x/README.md")).unwrap();
+    assert_eq!(readme, "second\n");
+
+    // Ctrl-C in fzf (exit 130) aborts bw with the same status
+    fs::write(&fzf, "#!/bin/sh\nexit 130\n").unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(sandbox.path("bin"))
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    sandbox
+        .bw()
+        .args(["add", "feature/y", "--pick-base"])
+        .current_dir(&project)
+        .env("PATH", path)
+        .assert()
+        .code(130);
+    assert!(!project.join("feature-y").exists());
+}
+
+#[test]
+fn test_reclone_recreates_worktree_branches() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https"])
+        .assert()
+        .success();
+    let project = sandbox.root().join("example.com/user/repo");
+    for branch in ["develop", "local-only"] {
+        sandbox
+            .bw()
+            .args(["add", branch])
+            .current_dir(&project)
+            .assert()
+            .success();
+    }
+    fs::write(project.join("bw.toml"), "base_branch = \"develop\"\n").unwrap();
+    fs::write(project.join(".bare/packed-refs"), "garbage\n").unwrap();
+    let backup = sandbox.root().join("example.com/user/repo.bw-reclone-0");
+
+    // local-only was never pushed, so it cannot come back without --force
+    sandbox
+        .bw()
+        .args(["reclone", "--yes"])
+        .current_dir(&project)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("local-only: origin/local-only not found"))
+        .stderr(predicates::str::contains("--force"));
+    assert!(project.join("local-only").is_dir());
+    assert!(!backup.exists());
+
+    sandbox
+        .bw()
+        .args(["reclone", "--yes", "--force"])
+        .current_dir(&project)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("local-only: origin/local-only not found"))
+        .stderr(predicates::str::contains("The old directory was kept"));
+
+    assert_eq!(fs::read_to_string(project.join("develop/README.md")).unwrap(), "first\n");
+    assert!(project.join("main").is_dir());
+    assert!(!project.join("local-only").exists());
+    assert!(project.join("bw.toml").exists());
+    let worktrees = sandbox.git(&project, &["worktree", "list", "--porcelain"]);
+    assert_eq!(worktrees.lines().filter(|l| l.starts_with("branch ")).count(), 2);
+    // The unpushed branch is still in the kept directory
+    assert!(backup.join("local-only").is_dir());
+}
+
+#[test]
+fn test_get_depth() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--depth", "1"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Shallow clone (depth: 1)"));
+
+    let project = sandbox.root().join("example.com/user/repo");
+    assert!(project.join(".bare/shallow").exists());
+    let log = sandbox.git(&project.join("main"), &["log", "--format=%s"]);
+    assert_eq!(log, "second\n");
+    // Other branches and tags are still fetched, unlike --single-commit
+    assert_eq!(sandbox.refs(&project, "refs/tags"), vec!["v1.0"]);
+    assert!(sandbox.refs(&project, "refs/remotes").contains(&"origin/develop".to_string()));
+}
+
+#[test]
+fn test_get_branch() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--branch", "develop"])
+        .assert()
+        .success();
+
+    let project = sandbox.root().join("example.com/user/repo");
+    let readme = fs::read_to_string(project.join("develop/README.md")).unwrap();
+    assert_eq!(readme.trim(), "first");
+    assert!(!project.join("main").exists());
+    assert_eq!(
+        sandbox.refs(&project, "refs/remotes"),
+        vec!["origin/HEAD", "origin/develop"]
+    );
+}
+
+#[test]
+fn test_get_missing_branch() {
+    let sandbox = Sandbox::new();
+    sandbox.add_remote("user", "repo");
+
+    sandbox
+        .bw()
+        .args(["get", "example.com/user/repo", "--https", "--branch", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Branch 'nope' not found"));
+    assert!(!sandbox.root().join("example.com/user/repo/.bare").exists());
+}
+
+/// Self-signed CA certificate, only loaded as a trust anchor.
+const TEST_CA_PEM: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBgjCCASegAwIBAgIUXU12twR2vJkvNbDCEW0mQpt0gjwwCgYIKoZIzj0EAwIw
+FTETMBEGA1UEAwwKYncgdGVzdCBDQTAgFw0yNjEwMTUxNzE4NDVaGA8yMTI2MDky
+MTE3MTg0NVowFTETMBEGA1UEAwwKYncgdGVzdCBDQTBZMBMGByqGSM49AgEGCCqG
+SM49AwEHA0IABOdXwQnXGl6L6ZlNXveVO6TsOWkkoqTjZyCE7M5QC8LEanWNd0jZ
+hLzNVqQbqwOGq3skldOeKo+IK0Ssvu7X2TSjUzBRMB0GA1UdDgQWBBRJqg6auIJM
+WGLJHQzL10C5MOeDFjAfBgNVHSMEGDAWgBRJqg6auIJMWGLJHQzL10C5MOeDFjAP
+BgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0kAMEYCIQCKbUmqogbZ0+gFoiIM
+qXCa0LMoxTkC4Z5qVQrzocyG4wIhAI8TxfyNfbny2Urm6BekV3GmiKYOTl+nhOP+
+OS2eZzBd
+-----END CERTIFICATE-----
+";
+
+#[test]
+fn test_get_branch_with_ca_bundle() {
+    let sandbox = Sandbox::new();
+    let bundle = sandbox.path("internal-ca.pem");
+    fs::write(&bundle, TEST_CA_PEM).unwrap();
+    let config = sandbox.path("config/ghqb/config.toml");
+    let mut settings = fs::read_to_string(&config).unwrap();
+    settings.push_str(&format!(
+        "[[host]]\nname = \"example.com\"\nca_bundle = \"{}\"\n",
+        bundle.display()
+    ));
+    fs::write(&config, settings).unwrap();
+
+    // The --branch check runs before the clone, so it must already trust the bundle
+    for (owner, extra) in [("plain", None), ("resumable", Some("--resumable"))] {
+        sandbox.add_remote(owner, "repo");
+        let mut cmd = sandbox.bw();
+        cmd.args(["get", &format!("example.com/{}/repo", owner), "--https", "--branch", "develop"]);
+        cmd.args(extra);
+        cmd.assert().success();
+
+        let project = sandbox.root().join(format!("example.com/{}/repo", owner));
+        assert!(project.join("develop").is_dir());
+        assert_eq!(
+            sandbox.git(&project.join(".bare"), &["config", "http.https://example.com/.sslCAInfo"]),
+            format!("{}\n", bundle.display())
+        );
+    }
+}